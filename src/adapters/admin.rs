@@ -1,4 +1,7 @@
-use crate::primitives::{ChainAddress, ChainName, IdentityContext, JudgementStateBlanked};
+use crate::connector::Judgement;
+use crate::primitives::{
+    ChainAddress, ChainName, IdentityContext, JudgementAuditEntry, JudgementStateBlanked,
+};
 use crate::Database;
 use std::str::FromStr;
 
@@ -6,8 +9,11 @@ pub type Result<T> = std::result::Result<T, Response>;
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Command {
-    Status(ChainAddress),
-    Verify(ChainAddress, Vec<RawFieldName>),
+    Status(Target),
+    Verify(Target, Vec<RawFieldName>),
+    Unlock(Target, Vec<RawFieldName>),
+    Reject(Target, Judgement, String),
+    Audit(ChainAddress),
     Help,
 }
 
@@ -24,7 +30,14 @@ impl FromStr for Command {
                 return Err(Response::UnknownCommand);
             }
 
-            Ok(Command::Status(ChainAddress::from(parts[0].to_string())))
+            Ok(Command::Status(parse_target(parts[0])?))
+        } else if s.starts_with("audit") {
+            let parts: Vec<&str> = s.split(' ').skip(1).collect();
+            if parts.len() != 1 {
+                return Err(Response::UnknownCommand);
+            }
+
+            Ok(Command::Audit(parse_address(parts[0])?))
         } else if s.starts_with("verify") {
             let parts: Vec<&str> = s.split(' ').skip(1).collect();
             if parts.len() < 2 {
@@ -32,12 +45,43 @@ impl FromStr for Command {
             }
 
             Ok(Command::Verify(
-                ChainAddress::from(parts[0].to_string()),
+                parse_target(parts[0])?,
+                parts[1..]
+                    .iter()
+                    .map(|s| RawFieldName::from_str(s))
+                    .collect::<Result<Vec<RawFieldName>>>()?,
+            ))
+        } else if s.starts_with("unlock") {
+            let parts: Vec<&str> = s.split(' ').skip(1).collect();
+            if parts.len() < 2 {
+                return Err(Response::UnknownCommand);
+            }
+
+            Ok(Command::Unlock(
+                parse_target(parts[0])?,
                 parts[1..]
                     .iter()
                     .map(|s| RawFieldName::from_str(s))
                     .collect::<Result<Vec<RawFieldName>>>()?,
             ))
+        } else if s.starts_with("reject") {
+            let parts: Vec<&str> = s.split(' ').skip(1).collect();
+            if parts.len() < 2 {
+                return Err(Response::UnknownCommand);
+            }
+
+            // The judgement is optional and defaults to `Erroneous`.
+            let (judgement, reason) = match parse_negative_judgement(parts[1]) {
+                Some(judgement) if parts.len() > 2 => (judgement, &parts[2..]),
+                Some(_) => return Err(Response::UnknownCommand),
+                None => (Judgement::Erroneous, &parts[1..]),
+            };
+
+            Ok(Command::Reject(
+                parse_target(parts[0])?,
+                judgement,
+                reason.join(" "),
+            ))
         } else if s.starts_with("help") {
             let count = s.split(' ').count();
 
@@ -52,10 +96,31 @@ impl FromStr for Command {
     }
 }
 
+/// The address of an identity, optionally prefixed with the network as
+/// `<NETWORK>:<ADDR>`. Otherwise, the network is derived from the SS58 prefix
+/// of the address, which must then match exactly one configured network.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Target {
+    pub address: ChainAddress,
+    pub network: Option<ChainName>,
+}
+
+impl From<ChainAddress> for Target {
+    fn from(address: ChainAddress) -> Self {
+        Target {
+            address,
+            network: None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Response {
-    Status(JudgementStateBlanked),
+    Status(Box<JudgementStateBlanked>),
     Verified(ChainAddress, Vec<RawFieldName>),
+    Unlocked(ChainAddress, Vec<RawFieldName>),
+    Rejected(ChainAddress, Judgement),
+    Audit(Vec<JudgementAuditEntry>),
     UnknownCommand,
     IdentityNotFound,
     InvalidSyntax(Option<String>),
@@ -82,6 +147,29 @@ impl std::fmt::Display for Response {
                     all
                 })
             }
+            Response::Unlocked(_, fields) => {
+                format!(
+                    "Unlocked the following fields: {}",
+                    fields
+                        .iter()
+                        .map(|field| field.to_string())
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                )
+            }
+            Response::Rejected(_, judgement) => {
+                format!(
+                    "Identity has been rejected ({:?}). The extrinsic will be submitted in a couple of seconds",
+                    judgement
+                )
+            }
+            Response::Audit(entries) => {
+                if entries.is_empty() {
+                    "No judgements were recorded for this address".to_string()
+                } else {
+                    serde_json::to_string_pretty(entries).unwrap()
+                }
+            }
             Response::UnknownCommand => "The provided command is unknown".to_string(),
             Response::IdentityNotFound => {
                 "Identity was not found or invalid query executed".to_string()
@@ -99,8 +187,12 @@ impl std::fmt::Display for Response {
                 "An internal error occured. Please contact the architects.".to_string()
             }
             Response::Help => "\
+                <ADDR> can be prefixed with the network as <NETWORK>:<ADDR>, required if multiple networks share its SS58 prefix.\n\
                 status <ADDR>\t\t\tShow the current verification status of the specified address.\n\
+                audit <ADDR>\t\t\tShow the judgements issued for the specified address.\n\
                 verify <ADDR> <FIELD>...\tVerify one or multiple fields of the specified address.\n\
+                unlock <ADDR> <FIELD>...\tUnlock one or multiple fields of the specified address after repeated failed attempts.\n\
+                reject <ADDR> [<JUDGEMENT>] <REASON>\tIssue a negative judgement (erroneous (default), low_quality or out_of_date).\n\
                 "
             .to_string(),
             Response::FullyVerified(_) => {
@@ -120,6 +212,9 @@ pub enum RawFieldName {
     Web,
     Twitter,
     Matrix,
+    PGPFingerprint,
+    Image,
+    Additional,
     // Represents the full identity
     All,
 }
@@ -134,6 +229,9 @@ impl std::fmt::Display for RawFieldName {
                 RawFieldName::Web => "web",
                 RawFieldName::Twitter => "twitter",
                 RawFieldName::Matrix => "matrix",
+                RawFieldName::PGPFingerprint => "pgp_fingerprint",
+                RawFieldName::Image => "image",
+                RawFieldName::Additional => "additional",
                 RawFieldName::All => "all",
             }
         })
@@ -145,7 +243,7 @@ impl FromStr for RawFieldName {
 
     fn from_str(s: &str) -> Result<Self> {
         // Convenience handler.
-        let s = s.trim().replace('-', "").replace('_', "").to_lowercase();
+        let s = s.trim().replace(['-', '_'], "").to_lowercase();
 
         let f = match s.as_str() {
             "legalname" => RawFieldName::LegalName,
@@ -154,6 +252,9 @@ impl FromStr for RawFieldName {
             "web" => RawFieldName::Web,
             "twitter" => RawFieldName::Twitter,
             "matrix" => RawFieldName::Matrix,
+            "pgpfingerprint" => RawFieldName::PGPFingerprint,
+            "image" => RawFieldName::Image,
+            "additional" => RawFieldName::Additional,
             "all" => RawFieldName::All,
             _ => return Err(Response::InvalidSyntax(Some(s.to_string()))),
         };
@@ -162,27 +263,102 @@ impl FromStr for RawFieldName {
     }
 }
 
+impl RawFieldName {
+    /// The type of the corresponding `IdentityFieldValue` as stored in the
+    /// database.
+    pub fn value_type(&self) -> String {
+        match self {
+            // As derived by serde from `IdentityFieldValue::PGPFingerprint`.
+            RawFieldName::PGPFingerprint => "p_g_p_fingerprint".to_string(),
+            _ => self.to_string(),
+        }
+    }
+}
+
+/// Parses and validates the (SS58 encoded) address.
+fn parse_address(s: &str) -> Result<ChainAddress> {
+    ChainAddress::from_str(s).map_err(|err| Response::InvalidSyntax(Some(err.to_string())))
+}
+
+/// Parses the address, optionally prefixed with the network.
+fn parse_target(s: &str) -> Result<Target> {
+    match s.split_once(':') {
+        Some((network, address)) => Ok(Target {
+            address: parse_address(address)?,
+            network: Some(ChainName::from(network)),
+        }),
+        None => Ok(Target::from(parse_address(s)?)),
+    }
+}
+
+/// Determines the identity context of the target. An error response is
+/// returned if no or multiple configured networks match the address.
+fn resolve_context(
+    db: &Database,
+    target: &Target,
+) -> crate::Result<std::result::Result<IdentityContext, Response>> {
+    let address = target.address.clone();
+
+    if let Some(network) = &target.network {
+        return Ok(Ok(IdentityContext::new(address, network.clone())));
+    }
+
+    let mut networks = db.address_networks(&address)?;
+    match networks.len() {
+        0 => Ok(Err(Response::IdentityNotFound)),
+        1 => Ok(Ok(IdentityContext::new(address, networks.remove(0)))),
+        _ => Ok(Err(Response::InvalidSyntax(Some(format!(
+            "{} is used by multiple networks ({}), specify one as <NETWORK>:<ADDR>",
+            address.as_str(),
+            networks
+                .iter()
+                .map(|network| network.as_str())
+                .collect::<Vec<&str>>()
+                .join(", ")
+        ))))),
+    }
+}
+
+/// Parses the negative judgements which can be issued via `reject`.
+fn parse_negative_judgement(s: &str) -> Option<Judgement> {
+    let judgement = match s.replace(['-', '_'], "").to_lowercase().as_str() {
+        "erroneous" => Judgement::Erroneous,
+        "lowquality" => Judgement::LowQuality,
+        "outofdate" => Judgement::OutOfDate,
+        _ => return None,
+    };
+
+    Some(judgement)
+}
+
 #[allow(clippy::needless_lifetimes)]
-pub async fn process_admin<'a>(db: &'a Database, command: Command) -> Response {
+pub async fn process_admin<'a>(db: &'a Database, command: Command, admin: &'a str) -> Response {
     let local = |db: &'a Database, command: Command| async move {
         match command {
-            Command::Status(addr) => {
-                let context = create_context(addr);
+            Command::Status(target) => {
+                let context = match resolve_context(db, &target)? {
+                    Ok(context) => context,
+                    Err(resp) => return Ok(resp),
+                };
                 let state = db.fetch_judgement_state(&context).await?;
 
                 // Determine response based on database lookup.
                 match state {
-                    Some(state) => Ok(Response::Status(state.into())),
+                    Some(state) => Ok(Response::Status(Box::new(state.into()))),
                     None => Ok(Response::IdentityNotFound),
                 }
             }
-            Command::Verify(addr, fields) => {
-                let context = create_context(addr.clone());
+            Command::Verify(target, fields) => {
+                let context = match resolve_context(db, &target)? {
+                    Ok(context) => context,
+                    Err(resp) => return Ok(resp),
+                };
+                let addr = target.address;
 
                 // Check if _all_ should be verified (respectively the full identity)
                 #[allow(clippy::collapsible_if)]
                 if fields.iter().any(|f| matches!(f, RawFieldName::All)) {
-                    if db.full_manual_verification(&context).await? {
+                    if db.full_manual_verification(&context, admin).await? {
                         return Ok(Response::FullyVerified(addr));
                     } else {
                         return Ok(Response::IdentityNotFound);
@@ -192,7 +368,7 @@ pub async fn process_admin<'a>(db: &'a Database, command: Command) -> Response {
                 // Verify each passed on field.
                 for field in &fields {
                     if db
-                        .verify_manually(&context, field, true, None)
+                        .verify_manually(&context, field, admin, true, None)
                         .await?
                         .is_none()
                     {
@@ -202,6 +378,35 @@ pub async fn process_admin<'a>(db: &'a Database, command: Command) -> Response {
 
                 Ok(Response::Verified(addr, fields))
             }
+            Command::Unlock(target, fields) => {
+                let context = match resolve_context(db, &target)? {
+                    Ok(context) => context,
+                    Err(resp) => return Ok(resp),
+                };
+                let addr = target.address;
+
+                for field in &fields {
+                    if db.unlock_field(&context, field).await?.is_none() {
+                        return Ok(Response::IdentityNotFound);
+                    }
+                }
+
+                Ok(Response::Unlocked(addr, fields))
+            }
+            Command::Reject(target, judgement, reason) => {
+                let context = match resolve_context(db, &target)? {
+                    Ok(context) => context,
+                    Err(resp) => return Ok(resp),
+                };
+                let addr = target.address;
+
+                if db.reject_identity(&context, judgement, &reason).await? {
+                    Ok(Response::Rejected(addr, judgement))
+                } else {
+                    Ok(Response::IdentityNotFound)
+                }
+            }
+            Command::Audit(addr) => Ok(Response::Audit(db.fetch_judgement_audit(&addr).await?)),
             Command::Help => Ok(Response::Help),
         }
     };
@@ -217,80 +422,153 @@ pub async fn process_admin<'a>(db: &'a Database, command: Command) -> Response {
     }
 }
 
-/// Convenience function for creating a full identity context when only the
-/// address itself is present. Only supports Kusama and Polkadot for now.
-pub fn create_context(address: ChainAddress) -> IdentityContext {
-    let chain = if address.as_str().starts_with('1') {
-        ChainName::Polkadot
-    } else {
-        ChainName::Kusama
-    };
-
-    IdentityContext { address, chain }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::primitives::JudgementState;
+    use crate::primitives::{IdentityFieldValue, JudgementState};
+
+    const ALICE: &str = "1a2YiGNu1UUhJtihq8961c7FZtWGQuWDVMWTNBKJdmpGhZP";
 
     #[test]
     fn command_status() {
-        let resp = Command::from_str("status Alice").unwrap();
-        assert_eq!(
-            resp,
-            Command::Status(ChainAddress::from("Alice".to_string()))
-        );
+        let resp = Command::from_str(&format!("status {}", ALICE)).unwrap();
+        assert_eq!(resp, Command::Status(ChainAddress::from(ALICE).into()));
 
-        let resp = Command::from_str("status  Alice").unwrap();
-        assert_eq!(
-            resp,
-            Command::Status(ChainAddress::from("Alice".to_string()))
-        );
+        let resp = Command::from_str(&format!("status  {}", ALICE)).unwrap();
+        assert_eq!(resp, Command::Status(ChainAddress::from(ALICE).into()));
 
         let resp = Command::from_str("status");
-        assert!(resp.is_err())
+        assert!(resp.is_err());
+
+        // Invalid address (checksum).
+        let resp = Command::from_str("status 1a2YiGNu1UUhJtihq8961c7FZtWGQuWDVMWTNBKJdmpGhZQ");
+        assert!(matches!(resp, Err(Response::InvalidSyntax(Some(_)))));
+    }
+
+    #[test]
+    fn command_audit() {
+        let resp = Command::from_str(&format!("audit {}", ALICE)).unwrap();
+        assert_eq!(resp, Command::Audit(ChainAddress::from(ALICE)));
+
+        let resp = Command::from_str("audit");
+        assert!(resp.is_err());
     }
 
     #[test]
     fn command_verify() {
-        let resp = Command::from_str("verify Alice email").unwrap();
+        let resp = Command::from_str(&format!("verify {} email", ALICE)).unwrap();
+        assert_eq!(
+            resp,
+            Command::Verify(ChainAddress::from(ALICE).into(), vec![RawFieldName::Email])
+        );
+
+        let resp = Command::from_str(&format!("verify {} email displayname", ALICE)).unwrap();
         assert_eq!(
             resp,
             Command::Verify(
-                ChainAddress::from("Alice".to_string()),
-                vec![RawFieldName::Email]
+                ChainAddress::from(ALICE).into(),
+                vec![RawFieldName::Email, RawFieldName::DisplayName]
             )
         );
 
-        let resp = Command::from_str("verify Alice email displayname").unwrap();
+        let resp = Command::from_str(&format!("verify {} email display_name", ALICE)).unwrap();
         assert_eq!(
             resp,
             Command::Verify(
-                ChainAddress::from("Alice".to_string()),
+                ChainAddress::from(ALICE).into(),
                 vec![RawFieldName::Email, RawFieldName::DisplayName]
             )
         );
 
-        let resp = Command::from_str("verify Alice email display_name").unwrap();
+        let resp = Command::from_str(&format!("verify {} pgp_fingerprint", ALICE)).unwrap();
         assert_eq!(
             resp,
             Command::Verify(
-                ChainAddress::from("Alice".to_string()),
-                vec![RawFieldName::Email, RawFieldName::DisplayName]
+                ChainAddress::from(ALICE).into(),
+                vec![RawFieldName::PGPFingerprint]
             )
         );
 
-        let resp = Command::from_str("verify Alice all").unwrap();
+        let resp = Command::from_str(&format!("verify {} all", ALICE)).unwrap();
+        assert_eq!(
+            resp,
+            Command::Verify(ChainAddress::from(ALICE).into(), vec![RawFieldName::All])
+        );
+
+        let resp = Command::from_str(&format!("verify polkadot:{} email", ALICE)).unwrap();
         assert_eq!(
             resp,
             Command::Verify(
-                ChainAddress::from("Alice".to_string()),
-                vec![RawFieldName::All]
+                Target {
+                    address: ChainAddress::from(ALICE),
+                    network: Some(ChainName::from("polkadot")),
+                },
+                vec![RawFieldName::Email]
+            )
+        );
+
+        let resp = Command::from_str(&format!("verify {}", ALICE));
+        assert!(resp.is_err());
+    }
+
+    #[test]
+    fn raw_field_name_value_type() {
+        let value = IdentityFieldValue::PGPFingerprint("0xALICE".to_string());
+        let stored = serde_json::to_value(&value).unwrap();
+
+        assert_eq!(
+            stored["type"],
+            RawFieldName::PGPFingerprint.value_type().as_str()
+        );
+        assert!(value.matches_raw_name(&RawFieldName::PGPFingerprint));
+        assert_eq!(RawFieldName::Email.value_type(), "email");
+    }
+
+    #[test]
+    fn command_unlock() {
+        let resp = Command::from_str(&format!("unlock {} email", ALICE)).unwrap();
+        assert_eq!(
+            resp,
+            Command::Unlock(ChainAddress::from(ALICE).into(), vec![RawFieldName::Email])
+        );
+
+        let resp = Command::from_str(&format!("unlock {} all", ALICE)).unwrap();
+        assert_eq!(
+            resp,
+            Command::Unlock(ChainAddress::from(ALICE).into(), vec![RawFieldName::All])
+        );
+
+        let resp = Command::from_str(&format!("unlock {}", ALICE));
+        assert!(resp.is_err());
+    }
+
+    #[test]
+    fn command_reject() {
+        let resp = Command::from_str(&format!("reject {} impersonates Bob", ALICE)).unwrap();
+        assert_eq!(
+            resp,
+            Command::Reject(
+                ChainAddress::from(ALICE).into(),
+                Judgement::Erroneous,
+                "impersonates Bob".to_string()
+            )
+        );
+
+        let resp =
+            Command::from_str(&format!("reject {} low_quality no real name", ALICE)).unwrap();
+        assert_eq!(
+            resp,
+            Command::Reject(
+                ChainAddress::from(ALICE).into(),
+                Judgement::LowQuality,
+                "no real name".to_string()
             )
         );
 
-        let resp = Command::from_str("verify Alice");
+        let resp = Command::from_str(&format!("reject {} erroneous", ALICE));
+        assert!(resp.is_err());
+
+        let resp = Command::from_str(&format!("reject {}", ALICE));
         assert!(resp.is_err());
     }
 
@@ -309,7 +587,7 @@ mod tests {
     #[test]
     #[ignore]
     fn response_status_debug() {
-        let resp = Response::Status(JudgementState::alice().into());
+        let resp = Response::Status(Box::new(JudgementState::alice().into()));
         println!("{}", resp);
     }
 
@@ -0,0 +1,573 @@
+use crate::adapters::web::TxtResolver;
+use crate::Result;
+use rsa::pkcs1::DecodeRsaPublicKey;
+use rsa::pkcs8::DecodePublicKey;
+use rsa::{Hash, PaddingScheme, PublicKey, RsaPublicKey};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+const DKIM_SIGNATURE: &str = "dkim-signature";
+const AUTHENTICATION_RESULTS: &str = "authentication-results";
+
+/// Authenticates the sender (`From` address) of a raw RFC822 message, either
+/// via the `Authentication-Results` header of a trusted server or by
+/// verifying the DKIM signatures of the message. The signing domain must
+/// align with the domain of the sender, as required by DMARC.
+///
+/// SPF can only be evaluated by the receiving server (which knows the
+/// connecting IP address), hence the trusted `Authentication-Results`.
+pub struct SenderAuthenticator<R> {
+    resolver: R,
+    // E.g. `mx.google.com`.
+    trusted_authserv_id: Option<String>,
+}
+
+impl<R: TxtResolver + Send + Sync> SenderAuthenticator<R> {
+    pub fn new(resolver: R, trusted_authserv_id: Option<String>) -> Self {
+        SenderAuthenticator {
+            resolver,
+            trusted_authserv_id,
+        }
+    }
+    /// Returns the reason if the sender could not be authenticated.
+    pub async fn authenticate(&self, raw: &[u8], sender: &str) -> Result<()> {
+        let from_domain = sender
+            .rsplit_once('@')
+            .map(|(_, domain)| domain.trim().to_lowercase())
+            .ok_or_else(|| anyhow!("invalid sender address"))?;
+
+        let (headers, body) = split_message(raw);
+
+        // The sender is read from the only `From` header, so a second (e.g.
+        // prepended, unsigned) instance would make it ambiguous which address
+        // was actually verified.
+        let from_count = headers
+            .iter()
+            .filter(|header| header.name.eq_ignore_ascii_case("From"))
+            .count();
+        if from_count != 1 {
+            return Err(anyhow!(
+                "expected exactly one From header, found {}",
+                from_count
+            ));
+        }
+
+        if let Some(authserv_id) = &self.trusted_authserv_id {
+            if let Some(result) = authentication_results(&headers, authserv_id, &from_domain) {
+                return result;
+            }
+        }
+
+        let signatures = headers
+            .iter()
+            .filter(|header| header.name.eq_ignore_ascii_case(DKIM_SIGNATURE))
+            .collect::<Vec<&Header>>();
+
+        if signatures.is_empty() {
+            return Err(anyhow!("no DKIM signature"));
+        }
+
+        // A single valid signature is sufficient.
+        let mut reasons = vec![];
+        for signature in signatures {
+            match self
+                .verify_signature(signature, &headers, &body, &from_domain)
+                .await
+            {
+                Ok(()) => return Ok(()),
+                Err(err) => reasons.push(err.to_string()),
+            }
+        }
+
+        Err(anyhow!("invalid DKIM signature: {}", reasons.join(", ")))
+    }
+    async fn verify_signature(
+        &self,
+        signature: &Header,
+        headers: &[Header],
+        body: &str,
+        from_domain: &str,
+    ) -> Result<()> {
+        let tags = parse_tags(signature.value());
+        let tag = |name: &str| {
+            tags.get(name)
+                .map(|val| val.as_str())
+                .ok_or_else(|| anyhow!("missing `{}` tag", name))
+        };
+
+        if tag("v")? != "1" {
+            return Err(anyhow!("unsupported version"));
+        }
+        if tag("a")? != "rsa-sha256" {
+            return Err(anyhow!("unsupported algorithm {}", tag("a")?));
+        }
+        // Content could be appended to the signed body.
+        if tags.contains_key("l") {
+            return Err(anyhow!("body length limits are not supported"));
+        }
+
+        let canonicalization = tags.get("c").map(|c| c.as_str()).unwrap_or("simple");
+        let (header_relaxed, body_relaxed) = match canonicalization.split_once('/') {
+            Some((header, body)) => (header == "relaxed", body == "relaxed"),
+            None => (canonicalization == "relaxed", false),
+        };
+
+        let domain = tag("d")?.to_lowercase();
+        if !is_aligned(&domain, from_domain) {
+            return Err(anyhow!(
+                "signing domain {} does not align with {}",
+                domain,
+                from_domain
+            ));
+        }
+
+        let signed_headers = tag("h")?
+            .split(':')
+            .map(|name| name.trim().to_lowercase())
+            .collect::<Vec<String>>();
+
+        if !signed_headers.iter().any(|name| name == "from") {
+            return Err(anyhow!("`From` header is not signed"));
+        }
+
+        let body_hash = base64::encode(Sha256::digest(canonicalize_body(body, body_relaxed)));
+        if body_hash != tag("bh")? {
+            return Err(anyhow!("body hash mismatch"));
+        }
+
+        let key = self.public_key(tag("s")?, &domain).await?;
+        let data = signed_data(headers, &signed_headers, signature, header_relaxed);
+
+        key.verify(
+            PaddingScheme::new_pkcs1v15_sign(Some(Hash::SHA2_256)),
+            &Sha256::digest(data.as_bytes()),
+            &base64::decode(tag("b")?)?,
+        )
+        .map_err(|_| anyhow!("signature mismatch"))
+    }
+    async fn public_key(&self, selector: &str, domain: &str) -> Result<RsaPublicKey> {
+        let records = self
+            .resolver
+            .txt_records(&format!("{}._domainkey.{}", selector, domain))
+            .await?;
+
+        let tags = records
+            .iter()
+            .map(|record| parse_tags(record))
+            .find(|tags| tags.contains_key("p"))
+            .ok_or_else(|| anyhow!("no DKIM key for {}", domain))?;
+
+        if tags.get("k").map(|k| k.as_str()).unwrap_or("rsa") != "rsa" {
+            return Err(anyhow!("unsupported key type"));
+        }
+
+        // An empty key means that it was revoked.
+        let der = base64::decode(&tags["p"])?;
+        if der.is_empty() {
+            return Err(anyhow!("DKIM key of {} was revoked", domain));
+        }
+
+        RsaPublicKey::from_public_key_der(&der)
+            .or_else(|_| RsaPublicKey::from_pkcs1_der(&der))
+            .map_err(|_| anyhow!("invalid DKIM key of {}", domain))
+    }
+}
+
+/// Relaxed alignment; either domain can be a subdomain of the other.
+fn is_aligned(signing_domain: &str, from_domain: &str) -> bool {
+    signing_domain == from_domain
+        || from_domain.ends_with(&format!(".{}", signing_domain))
+        || signing_domain.ends_with(&format!(".{}", from_domain))
+}
+
+/// Checks the DMARC result of the top-most `Authentication-Results` header of
+/// the trusted server, if any. Returns `None` if it does not contain a
+/// conclusive result.
+fn authentication_results(
+    headers: &[Header],
+    authserv_id: &str,
+    from_domain: &str,
+) -> Option<Result<()>> {
+    let header = headers.iter().find(|header| {
+        header.name.eq_ignore_ascii_case(AUTHENTICATION_RESULTS)
+            && header
+                .value()
+                .split(';')
+                .next()
+                .and_then(|id| id.split_whitespace().next())
+                .map(|id| id.eq_ignore_ascii_case(authserv_id))
+                .unwrap_or(false)
+    })?;
+
+    let unfolded = header.value().replace("\r\n", "");
+    let dmarc = unfolded
+        .split(';')
+        .map(|result| result.trim())
+        .find(|result| result.to_lowercase().starts_with("dmarc="))?;
+
+    // Make sure the result refers to the sender.
+    let header_from = dmarc
+        .split_whitespace()
+        .find_map(|prop| prop.strip_prefix("header.from="))
+        .map(|domain| domain.to_lowercase());
+
+    if header_from.as_deref() != Some(from_domain) {
+        return None;
+    }
+
+    let result = dmarc["dmarc=".len()..]
+        .split(|c: char| c.is_whitespace() || c == '(')
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+
+    match result.as_str() {
+        "pass" => Some(Ok(())),
+        "fail" | "permerror" => Some(Err(anyhow!("DMARC {} reported by {}", result, authserv_id))),
+        _ => None,
+    }
+}
+
+struct Header {
+    name: String,
+    // The full header field, including the name and any folding (CRLF).
+    raw: String,
+}
+
+impl Header {
+    fn value(&self) -> &str {
+        self.raw
+            .split_once(':')
+            .map(|(_, value)| value)
+            .unwrap_or_default()
+    }
+}
+
+/// Splits the raw message into its header fields and its body, with CRLF line
+/// endings.
+fn split_message(raw: &[u8]) -> (Vec<Header>, String) {
+    let text = String::from_utf8_lossy(raw).replace("\r\n", "\n");
+    let (head, body) = text.split_once("\n\n").unwrap_or((&text, ""));
+
+    let mut headers: Vec<Header> = vec![];
+    for line in head.lines() {
+        if line.starts_with(' ') || line.starts_with('\t') {
+            // Continuation of a folded header.
+            if let Some(header) = headers.last_mut() {
+                header.raw.push_str("\r\n");
+                header.raw.push_str(line);
+            }
+        } else if let Some((name, _)) = line.split_once(':') {
+            headers.push(Header {
+                name: name.trim().to_string(),
+                raw: line.to_string(),
+            });
+        }
+    }
+
+    (headers, body.replace('\n', "\r\n"))
+}
+
+/// Parses a tag list (`a=1; b=2`). All whitespace is removed from the values.
+fn parse_tags(list: &str) -> HashMap<String, String> {
+    list.split(';')
+        .filter_map(|tag| tag.split_once('='))
+        .map(|(name, value)| {
+            (
+                name.trim().to_string(),
+                value.chars().filter(|c| !c.is_whitespace()).collect(),
+            )
+        })
+        .collect()
+}
+
+/// Reduces all sequences of whitespace to a single space.
+fn compress_whitespace(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut whitespace = false;
+    for c in input.chars() {
+        if c == ' ' || c == '\t' {
+            if !whitespace {
+                output.push(' ');
+            }
+            whitespace = true;
+        } else {
+            output.push(c);
+            whitespace = false;
+        }
+    }
+
+    output
+}
+
+fn canonicalize_body(body: &str, relaxed: bool) -> String {
+    let mut lines = body
+        .split("\r\n")
+        .map(|line| {
+            if relaxed {
+                compress_whitespace(line).trim_end().to_string()
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<String>>();
+
+    // Empty lines at the end of the body are ignored.
+    while lines.last().map(|line| line.is_empty()).unwrap_or(false) {
+        lines.pop();
+    }
+
+    if lines.is_empty() {
+        return if relaxed {
+            String::new()
+        } else {
+            "\r\n".to_string()
+        };
+    }
+
+    lines.iter().map(|line| format!("{}\r\n", line)).collect()
+}
+
+fn canonicalize_header(raw: &str, relaxed: bool) -> String {
+    if !relaxed {
+        return format!("{}\r\n", raw);
+    }
+
+    let (name, value) = raw.split_once(':').unwrap_or((raw, ""));
+    format!(
+        "{}:{}\r\n",
+        name.trim().to_lowercase(),
+        compress_whitespace(&value.replace("\r\n", "")).trim()
+    )
+}
+
+/// Creates the data which is signed, consisting of the signed headers and the
+/// DKIM signature header itself (without the signature).
+fn signed_data(
+    headers: &[Header],
+    signed_headers: &[String],
+    signature: &Header,
+    relaxed: bool,
+) -> String {
+    let mut data = String::new();
+
+    // If a header occurs multiple times, the instances are used from the
+    // bottom up.
+    let mut used: HashMap<&str, usize> = HashMap::new();
+    for name in signed_headers {
+        let skip = used.entry(name).or_default();
+        if let Some(header) = headers
+            .iter()
+            .rev()
+            .filter(|header| header.name.eq_ignore_ascii_case(name))
+            .nth(*skip)
+        {
+            data.push_str(&canonicalize_header(&header.raw, relaxed));
+        }
+
+        *skip += 1;
+    }
+
+    // Remove the value of the `b` tag.
+    let stripped = signature
+        .raw
+        .split(';')
+        .map(|tag| match tag.split_once('=') {
+            Some((name, _)) if name.trim() == "b" || name.trim().ends_with(":b") => {
+                format!("{}=", name)
+            }
+            _ => tag.to_string(),
+        })
+        .collect::<Vec<String>>()
+        .join(";");
+
+    let canonicalized = canonicalize_header(&stripped, relaxed);
+    data.push_str(canonicalized.trim_end_matches("\r\n"));
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::web::tests::TxtResolverMocker;
+    use rand::thread_rng;
+    use rsa::pkcs8::EncodePublicKey;
+    use rsa::RsaPrivateKey;
+
+    struct Signer {
+        key: RsaPrivateKey,
+    }
+
+    impl Signer {
+        fn new() -> Self {
+            Signer {
+                key: RsaPrivateKey::new(&mut thread_rng(), 1024).unwrap(),
+            }
+        }
+        fn record(&self) -> String {
+            let der = RsaPublicKey::from(&self.key).to_public_key_der().unwrap();
+            format!("v=DKIM1; k=rsa; p={}", base64::encode(der.as_ref()))
+        }
+        /// Signs the message (with LF line endings) and returns it with the
+        /// DKIM signature header prepended.
+        fn sign(&self, domain: &str, message: &str) -> Vec<u8> {
+            let (headers, body) = split_message(message.as_bytes());
+            let body_hash = base64::encode(Sha256::digest(canonicalize_body(&body, true)));
+
+            let signature = Header {
+                name: "DKIM-Signature".to_string(),
+                raw: format!(
+                    "DKIM-Signature: v=1; a=rsa-sha256; c=relaxed/relaxed; d={};\r\n\ts=sel; h=From:Subject; bh={}; b=",
+                    domain, body_hash
+                ),
+            };
+
+            let data = signed_data(
+                &headers,
+                &["from".to_string(), "subject".to_string()],
+                &signature,
+                true,
+            );
+            let signed = self
+                .key
+                .sign(
+                    PaddingScheme::new_pkcs1v15_sign(Some(Hash::SHA2_256)),
+                    &Sha256::digest(data.as_bytes()),
+                )
+                .unwrap();
+
+            format!("{}{}\n{}", signature.raw, base64::encode(signed), message).into_bytes()
+        }
+    }
+
+    const MESSAGE: &str = "From: Alice <alice@alice.com>\nSubject:  Registrar\n  challenge\n\nThe challenge  is \n\n123456\n\n";
+
+    #[actix::test]
+    async fn verify_dkim_signature() {
+        let signer = Signer::new();
+        let resolver = TxtResolverMocker::default();
+        let auth = SenderAuthenticator::new(resolver.clone(), None);
+
+        let signed = signer.sign("alice.com", MESSAGE);
+
+        // Key is not published yet.
+        assert!(auth.authenticate(&signed, "alice@alice.com").await.is_err());
+
+        resolver
+            .insert("sel._domainkey.alice.com", signer.record())
+            .await;
+        auth.authenticate(&signed, "alice@alice.com").await.unwrap();
+
+        // Whitespace changes are irrelevant with relaxed canonicalization.
+        let modified = String::from_utf8(signed.clone())
+            .unwrap()
+            .replace("is \n", "is\r\n")
+            .replace("123456\n\n", "123456\n");
+        auth.authenticate(modified.as_bytes(), "alice@alice.com")
+            .await
+            .unwrap();
+
+        // Modified body.
+        let modified = String::from_utf8(signed.clone())
+            .unwrap()
+            .replace("123456", "654321");
+        assert!(auth
+            .authenticate(modified.as_bytes(), "alice@alice.com")
+            .await
+            .is_err());
+
+        // Modified sender.
+        let modified = String::from_utf8(signed.clone())
+            .unwrap()
+            .replace("From: Alice", "From: Eve");
+        assert!(auth
+            .authenticate(modified.as_bytes(), "alice@alice.com")
+            .await
+            .is_err());
+
+        // Prepended, unsigned sender. The signature itself only covers the
+        // bottom-most `From` instance and remains valid.
+        let mut modified = b"From: Victim <victim@alice.com>\n".to_vec();
+        modified.extend_from_slice(&signed);
+        assert!(auth
+            .authenticate(&modified, "victim@alice.com")
+            .await
+            .is_err());
+        assert!(auth
+            .authenticate(&modified, "alice@alice.com")
+            .await
+            .is_err());
+
+        // Signing domain does not align with the sender.
+        assert!(auth.authenticate(&signed, "bob@bob.com").await.is_err());
+
+        // Unsigned.
+        assert!(auth
+            .authenticate(MESSAGE.as_bytes(), "alice@alice.com")
+            .await
+            .is_err());
+    }
+
+    #[actix::test]
+    async fn verify_authentication_results() {
+        let auth =
+            SenderAuthenticator::new(TxtResolverMocker::default(), Some("mx.google.com".into()));
+
+        let message = |results: &str| {
+            format!(
+                "Authentication-Results: {}\nFrom: alice@alice.com\n\n123456\n",
+                results
+            )
+            .into_bytes()
+        };
+
+        auth.authenticate(
+            &message("mx.google.com;\n       dmarc=pass (p=NONE) header.from=alice.com"),
+            "alice@alice.com",
+        )
+        .await
+        .unwrap();
+
+        // DMARC failure.
+        assert!(auth
+            .authenticate(
+                &message("mx.google.com; dmarc=fail (p=NONE) header.from=alice.com"),
+                "alice@alice.com",
+            )
+            .await
+            .is_err());
+
+        // Result of another domain, falls back to DKIM.
+        assert!(auth
+            .authenticate(
+                &message("mx.google.com; dmarc=pass header.from=eve.com"),
+                "alice@alice.com",
+            )
+            .await
+            .is_err());
+
+        // Untrusted server, falls back to DKIM.
+        assert!(auth
+            .authenticate(
+                &message("mx.eve.com; dmarc=pass header.from=alice.com"),
+                "alice@alice.com",
+            )
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn canonicalize() {
+        assert_eq!(
+            canonicalize_body("a  b \t\r\nc\r\n\r\n\r\n", true),
+            "a b\r\nc\r\n"
+        );
+        assert_eq!(canonicalize_body("a  b \r\n\r\n", false), "a  b \r\n");
+        assert_eq!(canonicalize_body("", true), "");
+        assert_eq!(canonicalize_body("", false), "\r\n");
+        assert_eq!(
+            canonicalize_header("Subject : a \r\n\t b ", true),
+            "subject:a b\r\n"
+        );
+    }
+}
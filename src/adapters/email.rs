@@ -1,13 +1,19 @@
-use std::collections::HashSet;
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
 
+use crate::adapters::dkim::SenderAuthenticator;
+use crate::adapters::web::{DnsResolver, TxtResolver};
 use crate::adapters::Adapter;
-use crate::primitives::{
-    ExpectedMessage, ExternalMessage, ExternalMessageType, MessageId, Timestamp,
-};
-use crate::Result;
+use crate::database::Database;
+use crate::primitives::{ExpectedMessage, ExternalMessage, ExternalMessageType, Timestamp};
+use crate::{EmailProvider, EmailSecurity, Result, SenderAuthConfig, SenderAuthPolicy};
+use chrono::Utc;
+use imap::extensions::idle::WaitOutcome;
 use lettre::smtp::authentication::Credentials;
-use lettre::smtp::SmtpClient;
-use lettre::Transport;
+use lettre::smtp::{ClientSecurity, SmtpClient, SUBMISSIONS_PORT, SUBMISSION_PORT};
+use lettre::{ClientTlsParameters, Transport};
 use lettre_email::EmailBuilder;
 
 trait ExtractSender<T> {
@@ -33,12 +39,28 @@ impl ExtractSender<String> for String {
     }
 }
 
+const IDLE_TIMEOUT: u64 = 600;
+const RECONNECT_DELAY: u64 = 5;
+const RECONNECT_MAX_DELAY: u64 = 300;
+const IMAP_PORT: u16 = 143;
+const IMAPS_PORT: u16 = 993;
+
+type ImapSession = imap::Session<native_tls::TlsStream<TcpStream>>;
+
 pub struct EmailClientBuilder {
     server: Option<String>,
     imap_server: Option<String>,
     inbox: Option<String>,
     user: Option<String>,
     password: Option<String>,
+    idle: bool,
+    provider: EmailProvider,
+    imap_port: Option<u16>,
+    imap_security: EmailSecurity,
+    smtp_port: Option<u16>,
+    smtp_security: EmailSecurity,
+    sender_auth: Option<SenderAuthConfig>,
+    db: Option<Database>,
 }
 
 impl EmailClientBuilder {
@@ -49,8 +71,20 @@ impl EmailClientBuilder {
             inbox: None,
             user: None,
             password: None,
+            idle: false,
+            provider: EmailProvider::Gmail,
+            imap_port: None,
+            imap_security: EmailSecurity::Tls,
+            smtp_port: None,
+            smtp_security: EmailSecurity::Tls,
+            sender_auth: None,
+            db: None,
         }
     }
+    pub fn database(mut self, db: Database) -> Self {
+        self.db = Some(db);
+        self
+    }
     pub fn smtp_server(mut self, server: String) -> Self {
         self.server = Some(server);
         self
@@ -71,19 +105,101 @@ impl EmailClientBuilder {
         self.password = Some(password);
         self
     }
+    /// Keep an IMAP session open and wait for new messages via IDLE, instead
+    /// of reconnecting on each request.
+    pub fn idle(mut self, idle: bool) -> Self {
+        self.idle = idle;
+        self
+    }
+    pub fn provider(mut self, provider: EmailProvider) -> Self {
+        self.provider = provider;
+        self
+    }
+    pub fn imap_port(mut self, port: u16) -> Self {
+        self.imap_port = Some(port);
+        self
+    }
+    pub fn imap_security(mut self, security: EmailSecurity) -> Self {
+        self.imap_security = security;
+        self
+    }
+    pub fn smtp_port(mut self, port: u16) -> Self {
+        self.smtp_port = Some(port);
+        self
+    }
+    pub fn smtp_security(mut self, security: EmailSecurity) -> Self {
+        self.smtp_security = security;
+        self
+    }
+    /// Check the sender of received messages via DKIM (or the
+    /// `Authentication-Results` of a trusted server).
+    pub fn sender_auth(mut self, config: SenderAuthConfig) -> Self {
+        self.sender_auth = Some(config);
+        self
+    }
+    /// Builds the client. In IDLE mode, this also starts the IMAP session in
+    /// the background.
     #[allow(clippy::or_fun_call)]
     pub fn build(self) -> Result<EmailClient> {
-        Ok(EmailClient {
-            smtp_server: self.server.ok_or(anyhow!("SMTP server not specified"))?,
-            imap_server: self
+        let user = self.user.ok_or(anyhow!("user server not specified"))?;
+        let password = self
+            .password
+            .ok_or(anyhow!("password server not specified"))?;
+
+        let account = ImapAccount {
+            server: self
                 .imap_server
                 .ok_or(anyhow!("IMAP server not specified"))?,
+            // Use the default port of the security mode, if not specified.
+            port: self.imap_port.unwrap_or(match self.imap_security {
+                EmailSecurity::Tls => IMAPS_PORT,
+                EmailSecurity::Starttls => IMAP_PORT,
+            }),
+            security: self.imap_security,
+            provider: self.provider,
             inbox: self.inbox.ok_or(anyhow!("inbox server not specified"))?,
-            user: self.user.ok_or(anyhow!("user server not specified"))?,
-            password: self
-                .password
-                .ok_or(anyhow!("password server not specified"))?,
-            cache: HashSet::new(),
+            user: user.clone(),
+            password: password.clone(),
+        };
+
+        let idle_messages = if self.idle {
+            let messages = Arc::new(Mutex::new(vec![]));
+            let (account, idle_messages) = (account.clone(), Arc::clone(&messages));
+
+            // The IMAP client is blocking, so it gets a thread of its own.
+            thread::Builder::new()
+                .name("imap-idle".to_string())
+                .spawn(move || run_idle(account, idle_messages))?;
+
+            Some(messages)
+        } else {
+            None
+        };
+
+        let sender_auth = match self.sender_auth {
+            Some(config) => Some((
+                config.policy,
+                Arc::new(SenderAuthenticator::new(
+                    DnsResolver::new()?,
+                    config.trusted_authserv_id,
+                )),
+            )),
+            None => None,
+        };
+
+        Ok(EmailClient {
+            smtp_server: self.server.ok_or(anyhow!("SMTP server not specified"))?,
+            smtp_port: self.smtp_port.unwrap_or(match self.smtp_security {
+                EmailSecurity::Tls => SUBMISSIONS_PORT,
+                EmailSecurity::Starttls => SUBMISSION_PORT,
+            }),
+            smtp_security: self.smtp_security,
+            user,
+            password,
+            account,
+            idle_messages,
+            sender_auth,
+            db: self.db.ok_or(anyhow!("database not specified"))?,
         })
     }
 }
@@ -91,18 +207,37 @@ impl EmailClientBuilder {
 #[derive(Clone)]
 pub struct EmailClient {
     smtp_server: String,
-    imap_server: String,
+    smtp_port: u16,
+    smtp_security: EmailSecurity,
+    user: String,
+    password: String,
+    account: ImapAccount,
+    // Messages received by the IDLE session, if enabled.
+    idle_messages: Option<Arc<Mutex<Vec<ReceivedEmail>>>>,
+    // Authentication of the sender, if enabled.
+    sender_auth: Option<(SenderAuthPolicy, Arc<SenderAuthenticator<DnsResolver>>)>,
+    db: Database,
+}
+
+#[derive(Clone)]
+struct ImapAccount {
+    server: String,
+    port: u16,
+    security: EmailSecurity,
+    provider: EmailProvider,
     inbox: String,
     user: String,
     password: String,
-    // Keep track of messages.
-    cache: HashSet<MessageId>,
 }
 
-impl EmailClient {
-    fn request_messages(&mut self) -> Result<Vec<ExternalMessage>> {
+impl ImapAccount {
+    fn connect(&self) -> Result<ImapSession> {
         let tls = native_tls::TlsConnector::builder().build()?;
-        let client = imap::connect((self.imap_server.as_str(), 993), &self.imap_server, &tls)?;
+        let address = (self.server.as_str(), self.port);
+        let client = match self.security {
+            EmailSecurity::Tls => imap::connect(address, &self.server, &tls)?,
+            EmailSecurity::Starttls => imap::connect_starttls(address, &self.server, &tls)?,
+        };
 
         let mut imap = client
             .login(&self.user, &self.password)
@@ -110,93 +245,279 @@ impl EmailClient {
 
         imap.select(&self.inbox)?;
 
+        Ok(imap)
+    }
+    /// Fetches the recent messages of the selected inbox. Those might have
+    /// been processed already.
+    fn fetch_recent(&self, imap: &mut ImapSession) -> Result<Vec<ReceivedEmail>> {
         // Fetch the messages of the last day. The database keeps track of which messages
         // have been processed.
-        //
-        // Gmail has a custom search syntax and does not support the IMAP
-        // standardized queries.
-        let recent_seq = imap.search("X-GM-RAW \"newer_than:1d\"")?;
+        let messages = match self.provider {
+            // Gmail has a custom search syntax and does not support the IMAP
+            // standardized queries.
+            EmailProvider::Gmail => {
+                let recent_seq = imap.search("X-GM-RAW \"newer_than:1d\"")?;
 
-        if recent_seq.is_empty() {
-            return Ok(vec![]);
-        }
+                if recent_seq.is_empty() {
+                    return Ok(vec![]);
+                }
 
-        // Unwrap is fine since length of sequence is checked above.
-        let min = recent_seq.iter().min().unwrap();
-        let max = recent_seq.iter().max().unwrap();
+                // Unwrap is fine since length of sequence is checked above.
+                let min = recent_seq.iter().min().unwrap();
+                let max = recent_seq.iter().max().unwrap();
 
-        let query = if min == max {
-            min.to_string()
-        } else {
-            format!("{}:{}", min, max)
-        };
+                let query = if min == max {
+                    min.to_string()
+                } else {
+                    format!("{}:{}", min, max)
+                };
 
-        let messages = imap.fetch(query, "(RFC822 UID)")?;
-        let mut parsed_messages = vec![];
-        for message in &messages {
-            if let Some(body) = message.body() {
-                let mail = mailparse::parse_mail(body)?;
+                imap.fetch(query, "(RFC822 UID)")?
+            }
+            // `SINCE` only considers the date, not the time.
+            EmailProvider::Standard => {
+                let since = (Utc::now() - chrono::Duration::days(1)).format("%d-%b-%Y");
+                let recent_uids = imap.uid_search(format!("SINCE {}", since))?;
 
-                let sender = mail
-                    .headers
+                if recent_uids.is_empty() {
+                    return Ok(vec![]);
+                }
+
+                let query = recent_uids
                     .iter()
-                    .find(|header| header.get_key_ref() == "From")
-                    .ok_or_else(|| anyhow!("unrecognized data"))?
-                    .get_value()
-                    .extract_sender()?;
+                    .map(|uid| uid.to_string())
+                    .collect::<Vec<String>>()
+                    .join(",");
+
+                imap.uid_fetch(query, "(RFC822 UID)")?
+            }
+        };
+
+        parse_messages(&messages)
+    }
+}
+
+/// A parsed message, including the raw message for sender authentication.
+struct ReceivedEmail {
+    message: ExternalMessage,
+    raw: Vec<u8>,
+}
 
-                let id = message
+fn parse_messages(messages: &[imap::types::Fetch]) -> Result<Vec<ReceivedEmail>> {
+    let mut parsed_messages = vec![];
+    for message in messages {
+        if let Some(body) = message.body() {
+            let mail = mailparse::parse_mail(body)?;
+
+            let from = mail
+                .headers
+                .iter()
+                .filter(|header| header.get_key_ref().eq_ignore_ascii_case("From"))
+                .collect::<Vec<_>>();
+
+            // Messages with multiple `From` headers are ambiguous about who
+            // the sender is and are never considered.
+            if from.len() > 1 {
+                warn!("Skipping email message with {} From headers", from.len());
+                continue;
+            }
+
+            let sender = from
+                .first()
+                .ok_or_else(|| anyhow!("unrecognized data"))?
+                .get_value()
+                .extract_sender()?;
+
+            // Prepare parsed message
+            let mut parsed_message = ExternalMessage {
+                origin: ExternalMessageType::Email(sender),
+                id: message
                     .uid
                     .ok_or_else(|| anyhow!("missing UID for email message"))?
-                    .into();
-
-                // Skip message if it was already processed.
-                if self.cache.contains(&id) {
-                    continue;
-                }
+                    .into(),
+                timestamp: Timestamp::now(),
+                values: vec![],
+            };
 
-                debug!("Received message from {}", sender);
-
-                // Prepare parsed message
-                let mut parsed_message = ExternalMessage {
-                    origin: ExternalMessageType::Email(sender),
-                    id: message
-                        .uid
-                        .ok_or_else(|| anyhow!("missing UID for email message"))?
-                        .into(),
-                    timestamp: Timestamp::now(),
-                    values: vec![],
-                };
+            // Add body content.
+            if let Ok(body) = mail.get_body() {
+                parsed_message.values.push(body.into());
+            } else {
+                warn!("No body found in message");
+            }
 
-                // Add body content.
-                if let Ok(body) = mail.get_body() {
+            // An email message can contain multiple "subparts". Add each of
+            // those into the prepared message.
+            for subpart in mail.subparts {
+                if let Ok(body) = subpart.get_body() {
                     parsed_message.values.push(body.into());
                 } else {
-                    warn!("No body found in message");
+                    debug!("No body found in subpart message");
                 }
+            }
 
-                // An email message can contain multiple "subparts". Add each of
-                // those into the prepared message.
-                for subpart in mail.subparts {
-                    if let Ok(body) = subpart.get_body() {
-                        parsed_message.values.push(body.into());
-                    } else {
-                        debug!("No body found in subpart message");
-                    }
-                }
+            parsed_messages.push(ReceivedEmail {
+                message: parsed_message,
+                raw: body.to_vec(),
+            });
+        } else {
+            warn!("No body found for message");
+        }
+    }
 
-                self.cache.insert(parsed_message.id);
-                parsed_messages.push(parsed_message);
-            } else {
-                warn!("No body found for message");
+    Ok(parsed_messages)
+}
+
+/// Runs the IDLE session, reconnecting with an increasing delay whenever the
+/// connection drops.
+fn run_idle(account: ImapAccount, messages: Arc<Mutex<Vec<ReceivedEmail>>>) {
+    let mut delay = RECONNECT_DELAY;
+
+    loop {
+        if let Err(err) = idle(&account, &messages, &mut delay) {
+            error!(
+                "IMAP IDLE session failed, reconnecting in {}s: {:?}",
+                delay, err
+            );
+        }
+
+        thread::sleep(Duration::from_secs(delay));
+        delay = (delay * 2).min(RECONNECT_MAX_DELAY);
+    }
+}
+
+/// Only returns on failure.
+fn idle(
+    account: &ImapAccount,
+    messages: &Mutex<Vec<ReceivedEmail>>,
+    delay: &mut u64,
+) -> Result<()> {
+    let mut imap = account.connect()?;
+    info!("IMAP IDLE session established");
+
+    // Reset the delay once connected.
+    *delay = RECONNECT_DELAY;
+
+    loop {
+        // Fetch any messages that have arrived in the meantime, including
+        // the ones received while (re-)connecting.
+        let fetched = account.fetch_recent(&mut imap)?;
+        messages
+            .lock()
+            .map_err(|_| anyhow!("IDLE message buffer is poisoned"))?
+            .extend(fetched);
+
+        loop {
+            let outcome = imap
+                .idle()?
+                .wait_with_timeout(Duration::from_secs(IDLE_TIMEOUT))?;
+
+            match outcome {
+                WaitOutcome::MailboxChanged => break,
+                // Make sure the connection is still alive.
+                WaitOutcome::TimedOut => imap.noop()?,
             }
         }
+    }
+}
+
+/// Authenticates the sender of the message. Returns whether the message
+/// should be processed. With `SenderAuthPolicy::Flag`, the failure is recorded
+/// on the field of the sender.
+pub async fn authenticate_sender<R: TxtResolver + Send + Sync>(
+    db: &Database,
+    policy: SenderAuthPolicy,
+    authenticator: &SenderAuthenticator<R>,
+    message: &ExternalMessage,
+    raw: &[u8],
+) -> Result<bool> {
+    let sender = match &message.origin {
+        ExternalMessageType::Email(sender) => sender,
+        _ => return Ok(false),
+    };
+
+    if let Err(err) = authenticator.authenticate(raw, sender).await {
+        warn!(
+            "Failed to authenticate sender of email field {} ({:?}): {}",
+            sender, policy, err
+        );
+
+        match policy {
+            SenderAuthPolicy::Flag => {
+                db.flag_sender_auth_failure(&message.origin, &err.to_string())
+                    .await?;
+            }
+            SenderAuthPolicy::Reject => return Ok(false),
+        }
+    }
+
+    Ok(true)
+}
+
+impl EmailClient {
+    async fn request_messages(&mut self) -> Result<Vec<ExternalMessage>> {
+        let messages = match &self.idle_messages {
+            Some(idle_messages) => std::mem::take(
+                &mut *idle_messages
+                    .lock()
+                    .map_err(|_| anyhow!("IDLE message buffer is poisoned"))?,
+            ),
+            None => {
+                // Do not block the runtime with the blocking IMAP client.
+                let account = self.account.clone();
+                tokio::task::spawn_blocking(move || {
+                    let mut imap = account.connect()?;
+                    account.fetch_recent(&mut imap)
+                })
+                .await??
+            }
+        };
+
+        let mut parsed_messages = vec![];
+        for ReceivedEmail { message, raw } in messages {
+            // Skip message if it was already processed, so the sender is not
+            // authenticated again. The remaining messages are tracked by the
+            // `AdapterListener`.
+            if self
+                .db
+                .is_message_processed(self.name(), message.id)
+                .await?
+            {
+                continue;
+            }
+
+            let sender = match &message.origin {
+                ExternalMessageType::Email(sender) => sender,
+                _ => continue,
+            };
+
+            debug!("Received message from {}", sender);
+
+            if let Some((policy, authenticator)) = &self.sender_auth {
+                if !authenticate_sender(&self.db, *policy, authenticator, &message, &raw).await? {
+                    // Rejected messages never reach the `AdapterListener`.
+                    self.db
+                        .set_message_processed(self.name(), message.id)
+                        .await?;
+                    continue;
+                }
+            }
+
+            parsed_messages.push(message);
+        }
 
         Ok(parsed_messages)
     }
     async fn send_message(&self, to: &str, message: &str) -> Result<()> {
         // SMTP transport
-        let mut smtp = SmtpClient::new_simple(&self.smtp_server)?
+        let tls =
+            ClientTlsParameters::new(self.smtp_server.clone(), native_tls::TlsConnector::new()?);
+        let security = match self.smtp_security {
+            EmailSecurity::Tls => ClientSecurity::Wrapper(tls),
+            EmailSecurity::Starttls => ClientSecurity::Required(tls),
+        };
+
+        let mut smtp = SmtpClient::new((self.smtp_server.as_str(), self.smtp_port), security)?
             .credentials(Credentials::new(
                 self.user.to_string(),
                 self.password.to_string(),
@@ -227,8 +548,11 @@ impl Adapter for EmailClient {
     fn name(&self) -> &'static str {
         "email"
     }
+    fn unique_message_ids(&self) -> bool {
+        true
+    }
     async fn fetch_messages(&mut self) -> Result<Vec<ExternalMessage>> {
-        self.request_messages()
+        self.request_messages().await
     }
     async fn send_message(&mut self, to: &str, content: Self::MessageType) -> Result<()> {
         Self::send_message(self, to, content.value.as_str()).await
@@ -1,13 +1,17 @@
 use crate::adapters::admin::{process_admin, Command, Response};
-use crate::adapters::Adapter;
+use crate::adapters::{Adapter, UserMessage};
 use crate::primitives::{ExternalMessage, ExternalMessageType, Timestamp};
 use crate::{Database, Result};
 use matrix_sdk::events::room::member::MemberEventContent;
 use matrix_sdk::events::room::message::MessageEventContent;
 use matrix_sdk::events::{AnyMessageEventContent, StrippedStateEvent, SyncMessageEvent};
-use matrix_sdk::room::Room;
+use matrix_sdk::room::{Joined, Room};
 use matrix_sdk::{Client, ClientConfig, EventHandler, SyncSettings};
+use ruma::api::client::r0::room::create_room::{self, RoomPreset};
 use ruma::events::room::message::{MessageType, TextMessageEventContent};
+use ruma::identifiers::{RoomId, UserId};
+use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::str::FromStr;
 use std::sync::Arc;
 use tokio::sync::Mutex;
@@ -16,11 +20,15 @@ use url::Url;
 
 const REJOIN_DELAY: u64 = 10;
 const REJOIN_MAX_ATTEMPTS: usize = 5;
+const SUBMISSION_ALERT_INTERVAL: u64 = 60;
 
 #[derive(Clone)]
 pub struct MatrixClient {
+    client: Client,
     // TODO: This should just be a channel.
     messages: Arc<Mutex<Vec<ExternalMessage>>>,
+    // Direct rooms with users, created on demand.
+    rooms: Arc<Mutex<HashMap<UserId, RoomId>>>,
 }
 
 impl MatrixClient {
@@ -31,6 +39,7 @@ impl MatrixClient {
         db_path: &str,
         db: Database,
         admins: Vec<MatrixHandle>,
+        admin_room: Option<String>,
     ) -> Result<MatrixClient> {
         info!("Setting up Matrix client");
         // Setup client
@@ -49,6 +58,17 @@ impl MatrixClient {
         info!("Syncing client");
         client.sync_once(SyncSettings::default()).await?;
 
+        // Notify the admins about judgements which could not be submitted.
+        if let Some(admin_room) = admin_room {
+            let room_id = RoomId::try_from(admin_room.as_str())?;
+            let alert_client = client.clone();
+            let alert_db = db.clone();
+
+            actix::spawn(async move {
+                report_failed_submissions(alert_client, alert_db, room_id).await;
+            });
+        }
+
         // Add event handler
         let messages = Arc::new(Mutex::new(vec![]));
         client
@@ -69,11 +89,143 @@ impl MatrixClient {
                 .ok_or_else(|| anyhow!("Failed to acquire sync token"))?,
         );
 
+        let sync_client = client.clone();
         actix::spawn(async move {
-            client.clone().sync(settings).await;
+            sync_client.sync(settings).await;
         });
 
-        Ok(MatrixClient { messages })
+        Ok(MatrixClient {
+            client,
+            messages,
+            rooms: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+    /// Whether the user is a member of the room and nobody else but the bot
+    /// is (or was invited to be) one, too.
+    async fn is_private_room(&self, room: &Joined, user_id: &UserId) -> Result<bool> {
+        let own_id = self.client.user_id().await;
+        let members = room.active_members().await?;
+
+        Ok(members.iter().any(|member| member.user_id() == user_id)
+            && members.iter().all(|member| {
+                member.user_id() == user_id || Some(member.user_id()) == own_id.as_ref()
+            }))
+    }
+    /// Returns the direct room with the user, creating one if necessary.
+    /// Challenges and follow-ups must not be readable by anyone else, so
+    /// rooms with other members are never used.
+    async fn direct_room(&self, user_id: &UserId) -> Result<RoomId> {
+        let mut rooms = self.rooms.lock().await;
+        if let Some(room_id) = rooms.get(user_id) {
+            // Other members might have been invited in the meantime. Newly
+            // created rooms might not be synced yet.
+            let private = match self.client.get_joined_room(room_id) {
+                Some(room) => self.is_private_room(&room, user_id).await?,
+                None => true,
+            };
+
+            if private {
+                return Ok(room_id.clone());
+            }
+
+            debug!("Room {} is no longer private, not using it", room_id);
+            rooms.remove(user_id);
+        }
+
+        // Reuse an existing room, e.g. if the user contacted the bot first.
+        let mut existing = None;
+        for room in self.client.joined_rooms() {
+            if room.direct_target().as_ref() == Some(user_id)
+                && self.is_private_room(&room, user_id).await?
+            {
+                existing = Some(room);
+                break;
+            }
+        }
+
+        let room_id = if let Some(room) = existing {
+            room.room_id().clone()
+        } else {
+            debug!("Creating direct room with {}", user_id);
+
+            let invite = [user_id.clone()];
+            let mut request = create_room::Request::new();
+            request.invite = &invite;
+            request.is_direct = true;
+            request.preset = Some(RoomPreset::TrustedPrivateChat);
+
+            self.client.create_room(request).await?.room_id
+        };
+
+        rooms.insert(user_id.clone(), room_id.clone());
+        Ok(room_id)
+    }
+}
+
+async fn report_failed_submissions(client: Client, db: Database, room_id: RoomId) {
+    let mut interval = time::interval(Duration::from_secs(SUBMISSION_ALERT_INTERVAL));
+
+    loop {
+        interval.tick().await;
+
+        let submissions = match db.fetch_unreported_failed_submissions().await {
+            Ok(submissions) => submissions,
+            Err(err) => {
+                error!("Failed to fetch failed judgement submissions: {:?}", err);
+                continue;
+            }
+        };
+
+        for submission in submissions {
+            let mut text = format!(
+                "Failed to submit the judgement for {} ({}): the Watcher did not confirm it \
+                after {} attempts.",
+                submission.context.address.as_str(),
+                submission.context.chain.as_str(),
+                submission.attempts
+            );
+
+            if let Some(error) = &submission.last_error {
+                text.push_str(&format!(" Last error: {}", error));
+            }
+
+            // Retried on the next interval on failure.
+            if let Err(err) = client
+                .room_send(
+                    &room_id,
+                    AnyMessageEventContent::RoomMessage(MessageEventContent::text_plain(text)),
+                    None,
+                )
+                .await
+            {
+                error!("Failed to notify admins about failed submission: {:?}", err);
+                continue;
+            }
+
+            let _ = db
+                .set_submission_reported(&submission)
+                .await
+                .map_err(|err| error!("Failed to track reported submission: {:?}", err));
+        }
+    }
+}
+
+fn message_text(message: &UserMessage) -> String {
+    match message {
+        UserMessage::Challenge(challenge) => format!(
+            "Hello! An identity judgement was requested from the W3F registrar for an on-chain \
+            identity which specifies this Matrix account. Reply to this message with the \
+            following challenge in order to verify it: {}",
+            challenge.value
+        ),
+        UserMessage::SecondChallenge(challenge) => format!(
+            "Insert the following challenge into the web interface: {}",
+            challenge.value
+        ),
+        UserMessage::FieldVerified => "Your Matrix account is now verified.".to_string(),
+        UserMessage::FieldVerificationFailed => {
+            "The message does not contain the expected challenge, please try again.".to_string()
+        }
     }
 }
 
@@ -154,10 +306,10 @@ impl EventHandler for Listener {
 
             // Check for admin message
             let sender = event.sender.to_string();
-            if self.admins.contains(&MatrixHandle(sender)) {
+            if self.admins.contains(&MatrixHandle(sender.clone())) {
                 let resp = match Command::from_str(msg_body) {
                     // If a valid admin command was found, execute it.
-                    Ok(cmd) => Some(process_admin(&self.db, cmd).await),
+                    Ok(cmd) => Some(process_admin(&self.db, cmd, &sender).await),
                     Err(err @ Response::InvalidSyntax(_)) => Some(err),
                     // Ignore, allow noise (catches `UnknownCommand`).
                     Err(_) => None,
@@ -201,17 +353,30 @@ impl EventHandler for Listener {
 
 #[async_trait]
 impl Adapter for MatrixClient {
-    type MessageType = ();
+    type MessageType = UserMessage;
 
     fn name(&self) -> &'static str {
-        "Matrix"
+        "matrix"
     }
     async fn fetch_messages(&mut self) -> Result<Vec<ExternalMessage>> {
         let mut lock = self.messages.lock().await;
         // Return messages and wipe inner field.
         Ok(std::mem::take(&mut *lock))
     }
-    async fn send_message(&mut self, _to: &str, _content: Self::MessageType) -> Result<()> {
-        unimplemented!()
+    async fn send_message(&mut self, to: &str, content: Self::MessageType) -> Result<()> {
+        let user_id = UserId::try_from(to)?;
+        let room_id = self.direct_room(&user_id).await?;
+
+        self.client
+            .room_send(
+                &room_id,
+                AnyMessageEventContent::RoomMessage(MessageEventContent::text_plain(message_text(
+                    &content,
+                ))),
+                None,
+            )
+            .await?;
+
+        Ok(())
     }
 }
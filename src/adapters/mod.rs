@@ -2,14 +2,17 @@ use crate::database::{Database, EventCursor};
 use crate::primitives::{
     ExpectedMessage, ExternalMessage, IdentityFieldValue, NotificationMessage,
 };
-use crate::{AdapterConfig, Result};
+use crate::{AdapterConfig, Result, TwitterApiConfig, TwitterMode};
+use std::convert::TryFrom;
 use tokio::time::{interval, Duration};
 use tracing::Instrument;
 
 pub mod admin;
+pub mod dkim;
 pub mod email;
 pub mod matrix;
 pub mod twitter;
+pub mod web;
 
 pub async fn run_adapters(config: AdapterConfig, db: Database) -> Result<()> {
     let listener = AdapterListener::new(db.clone()).await;
@@ -22,7 +25,11 @@ pub async fn run_adapters(config: AdapterConfig, db: Database) -> Result<()> {
         matrix: matrix_config,
         twitter: twitter_config,
         email: email_config,
+        web: web_config,
         display_name: _,
+        second_challenge: _,
+        challenge_ttl: _,
+        challenge_format: _,
     } = config;
 
     // Matrix client configuration and execution.
@@ -42,8 +49,9 @@ pub async fn run_adapters(config: AdapterConfig, db: Database) -> Result<()> {
                 &config.username,
                 &config.password,
                 &config.db_path,
-                db,
+                db.clone(),
                 config.admins.unwrap_or_default(),
+                config.admin_room,
             )
             .await?;
 
@@ -62,16 +70,40 @@ pub async fn run_adapters(config: AdapterConfig, db: Database) -> Result<()> {
         let config = twitter_config;
 
         let span = info_span!("twitter_adapter");
-        info!(api_key = config.api_key.as_str());
+        match &config.api {
+            TwitterApiConfig::V1 { api_key, .. } => info!(api_key = api_key.as_str(), "API v1.1"),
+            TwitterApiConfig::V2 { .. } => info!("API v2"),
+        }
 
         async {
             info!("Configuring client");
-            let twitter_client = twitter::TwitterBuilder::new()
-                .consumer_key(config.api_key)
-                .consumer_secret(config.api_secret)
-                .token(config.token)
-                .token_secret(config.token_secret)
-                .build()?;
+            let mut builder = match config.api {
+                TwitterApiConfig::V1 {
+                    api_key,
+                    api_secret,
+                    token,
+                    token_secret,
+                } => twitter::TwitterBuilder::new()
+                    .consumer_key(api_key)
+                    .consumer_secret(api_secret)
+                    .token(token)
+                    .token_secret(token_secret),
+                TwitterApiConfig::V2 { bearer_token } => {
+                    twitter::TwitterBuilder::new().bearer_token(bearer_token)
+                }
+            };
+
+            if let Some(base_url) = config.base_url {
+                builder = builder.base_url(base_url);
+            }
+
+            if let Some(modes) = config.modes {
+                builder = builder
+                    .direct_messages(modes.contains(&TwitterMode::DirectMessages))
+                    .tweets(modes.contains(&TwitterMode::Tweets));
+            }
+
+            let twitter_client = builder.database(db.clone()).build()?;
 
             info!("Starting message adapter");
             listener
@@ -89,6 +121,7 @@ pub async fn run_adapters(config: AdapterConfig, db: Database) -> Result<()> {
     // Email client configuration and execution.
     if email_config.enabled {
         let config = email_config;
+        let idle = config.idle.unwrap_or(false);
 
         let span = info_span!("email_adapter");
         info!(
@@ -96,21 +129,75 @@ pub async fn run_adapters(config: AdapterConfig, db: Database) -> Result<()> {
             imap_server = config.imap_server.as_str(),
             inbox = config.inbox.as_str(),
             user = config.user.as_str(),
+            idle,
         );
 
         async {
             info!("Configuring client");
-            let email_client = email::EmailClientBuilder::new()
+            let mut builder = email::EmailClientBuilder::new()
                 .smtp_server(config.smtp_server)
                 .imap_server(config.imap_server)
                 .email_inbox(config.inbox)
                 .email_user(config.user)
                 .email_password(config.password)
-                .build()?;
+                .idle(idle)
+                .database(db.clone());
+
+            if let Some(provider) = config.provider {
+                builder = builder.provider(provider);
+            }
+            if let Some(port) = config.imap_port {
+                builder = builder.imap_port(port);
+            }
+            if let Some(security) = config.imap_security {
+                builder = builder.imap_security(security);
+            }
+            if let Some(port) = config.smtp_port {
+                builder = builder.smtp_port(port);
+            }
+            if let Some(security) = config.smtp_security {
+                builder = builder.smtp_security(security);
+            }
+            if let Some(sender_auth) = config.sender_auth {
+                builder = builder.sender_auth(sender_auth);
+            }
+
+            let email_client = builder.build()?;
+
+            // In IDLE mode, new messages are buffered as soon as those arrive.
+            let request_interval = if idle { 1 } else { config.request_interval };
+
+            info!("Starting message adapter");
+            listener
+                .start_message_adapter(email_client, request_interval)
+                .await;
+
+            Result::Ok(())
+        }
+        .instrument(span)
+        .await?;
+
+        started = true;
+    }
+
+    // Web client configuration and execution.
+    if web_config.enabled {
+        let config = web_config;
+
+        let span = info_span!("web_adapter");
+        info!(request_interval = config.request_interval);
+
+        async {
+            info!("Configuring client");
+            let web_client = web::WebClient::new(
+                db.clone(),
+                web::DnsResolver::new()?,
+                web::HttpFetcher::new()?,
+            );
 
             info!("Starting message adapter");
             listener
-                .start_message_adapter(email_client, config.request_interval)
+                .start_message_adapter(web_client, config.request_interval)
                 .await;
 
             Result::Ok(())
@@ -133,13 +220,81 @@ pub trait Adapter {
     type MessageType;
 
     fn name(&self) -> &'static str;
+    /// Whether the `MessageId`s of the fetched messages are unique. If so,
+    /// processed messages are tracked in the database and are not processed
+    /// again, even after a restart.
+    fn unique_message_ids(&self) -> bool {
+        false
+    }
     async fn fetch_messages(&mut self) -> Result<Vec<ExternalMessage>>;
     async fn send_message(&mut self, to: &str, content: Self::MessageType) -> Result<()>;
 }
 
+/// Messages sent to the user directly, via the account that is being
+/// verified. Each adapter decides which of those it supports by implementing
+/// `TryFrom<UserMessage>` for its `MessageType`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum UserMessage {
+    Challenge(ExpectedMessage),
+    SecondChallenge(ExpectedMessage),
+    FieldVerified,
+    FieldVerificationFailed,
+}
+
 // Filler for adapters that do not send messages.
-impl From<ExpectedMessage> for () {
-    fn from(_: ExpectedMessage) -> Self {}
+impl TryFrom<UserMessage> for () {
+    type Error = ();
+
+    fn try_from(_: UserMessage) -> std::result::Result<Self, Self::Error> {
+        Err(())
+    }
+}
+
+// Only the second challenge is sent via email and Twitter.
+impl TryFrom<UserMessage> for ExpectedMessage {
+    type Error = ();
+
+    fn try_from(val: UserMessage) -> std::result::Result<Self, Self::Error> {
+        match val {
+            UserMessage::SecondChallenge(challenge) => Ok(challenge),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Returns the account of the field which the adapter can send messages to,
+/// if any.
+fn recipient<'a>(adapter: &str, field: &'a IdentityFieldValue) -> Option<&'a str> {
+    match (adapter, field) {
+        ("email", IdentityFieldValue::Email(to)) => Some(to.as_str()),
+        ("twitter", IdentityFieldValue::Twitter(to)) => Some(to.as_str()),
+        ("matrix", IdentityFieldValue::Matrix(to)) => Some(to.as_str()),
+        _ => None,
+    }
+}
+
+/// Messages which are not supported by the adapter are skipped.
+async fn send_user_message<T: Adapter>(
+    adapter: &mut T,
+    to: &str,
+    message: UserMessage,
+) -> Result<()>
+where
+    <T as Adapter>::MessageType: TryFrom<UserMessage>,
+{
+    if let Ok(content) = <T as Adapter>::MessageType::try_from(message) {
+        debug!("Sending message to {} ({} adapter)", to, adapter.name());
+        adapter.send_message(to, content).await.map_err(|err| {
+            anyhow!(
+                "failed to send message to {} ({} adapter): {:?}",
+                to,
+                adapter.name(),
+                err
+            )
+        })?;
+    }
+
+    Ok(())
 }
 
 pub struct AdapterListener {
@@ -153,7 +308,7 @@ impl AdapterListener {
     pub async fn start_message_adapter<T>(&self, mut adapter: T, timeout: u64)
     where
         T: 'static + Adapter + Send,
-        <T as Adapter>::MessageType: From<ExpectedMessage>,
+        <T as Adapter>::MessageType: TryFrom<UserMessage>,
     {
         let mut interval = interval(Duration::from_secs(timeout));
 
@@ -168,11 +323,39 @@ impl AdapterListener {
                 match adapter.fetch_messages().await {
                     Ok(messages) => {
                         for message in messages {
+                            let track = adapter.unique_message_ids();
+
+                            // Skip message if it was already processed.
+                            if track {
+                                match db.is_message_processed(adapter.name(), message.id).await {
+                                    Ok(false) => {}
+                                    Ok(true) => {
+                                        debug!(
+                                            "Skipping processed message from: {:?}",
+                                            message.origin
+                                        );
+                                        continue;
+                                    }
+                                    Err(err) => {
+                                        error!("Failed to check for processed message: {:?}", err);
+                                        continue;
+                                    }
+                                }
+                            }
+
                             debug!("Processing message from: {:?}", message.origin);
-                            let _ = db
-                                .verify_message(&message)
-                                .await
-                                .map_err(|err| error!("Error when verifying message: {:?}", err));
+                            match db.verify_message(&message).await {
+                                Ok(_) if track => {
+                                    let _ = db
+                                        .set_message_processed(adapter.name(), message.id)
+                                        .await
+                                        .map_err(|err| {
+                                            error!("Failed to track processed message: {:?}", err)
+                                        });
+                                }
+                                Ok(_) => {}
+                                Err(err) => error!("Error when verifying message: {:?}", err),
+                            }
                         }
                     }
                     Err(err) => {
@@ -184,29 +367,89 @@ impl AdapterListener {
                     }
                 }
 
-                // Check if a second challenge must be sent to the user directly.
-                match db.fetch_events(&mut cursor).await {
-                    Ok(events) => {
-                        for event in &events {
-                            if let NotificationMessage::AwaitingSecondChallenge { context, field } =
-                                event
-                            {
-                                if let IdentityFieldValue::Email(to) = field {
-                                    if adapter.name() == "email" {
-                                        debug!("Sending second challenge to {}", to);
-                                        if let Ok(challenge) = db
-                                            .fetch_second_challenge(context, field)
-                                            .await
-                                            .map_err(|err| error!("Failed to fetch second challenge from database: {:?}", err)) {
-                                                let _ = adapter
-                                                    .send_message(to.as_str(), challenge.into())
-                                                    .await
-                                                    .map_err(|err| error!("Failed to send second challenge to {} ({} adapter): {:?}", to, adapter.name(), err));
+                // Send the challenge to newly requested Matrix accounts, so
+                // users do not have to find the bot on their own.
+                if adapter.name() == "matrix" {
+                    match db.fetch_undelivered_matrix_challenges().await {
+                        Ok(pending) => {
+                            for (to, challenge) in pending {
+                                let res = match send_user_message(
+                                    &mut adapter,
+                                    &to,
+                                    UserMessage::Challenge(challenge.clone()),
+                                )
+                                .await
+                                {
+                                    Ok(()) => db.set_challenge_delivered(&challenge).await,
+                                    Err(err) => {
+                                        error!("{:?}", err);
+
+                                        // Retried on the next run, up to a limit.
+                                        db.set_challenge_delivery_failed(&challenge).await.map(
+                                            |retry| {
+                                                if !retry {
+                                                    error!(
+                                                        "Giving up sending the challenge to {}",
+                                                        to
+                                                    );
                                                 }
+                                            },
+                                        )
                                     }
+                                };
+
+                                if let Err(err) = res {
+                                    error!("Failed to track challenge delivery: {:?}", err);
                                 }
                             }
                         }
+                        Err(err) => {
+                            error!("Failed to fetch undelivered challenges: {:?}", err);
+                        }
+                    }
+                }
+
+                // Check if a second challenge or a follow-up must be sent to
+                // the user directly.
+                match db.fetch_events(&mut cursor).await {
+                    Ok(events) => {
+                        for event in events {
+                            let (context, field, message) = match event {
+                                NotificationMessage::AwaitingSecondChallenge { context, field } => {
+                                    (context, field, None)
+                                }
+                                NotificationMessage::FieldVerified { context, field } => {
+                                    (context, field, Some(UserMessage::FieldVerified))
+                                }
+                                NotificationMessage::FieldVerificationFailed { context, field } => {
+                                    (context, field, Some(UserMessage::FieldVerificationFailed))
+                                }
+                                _ => continue,
+                            };
+
+                            let to = match recipient(adapter.name(), &field) {
+                                Some(to) => to,
+                                None => continue,
+                            };
+
+                            let message = match message {
+                                Some(message) => message,
+                                None => match db.fetch_second_challenge(&context, &field).await {
+                                    Ok(challenge) => UserMessage::SecondChallenge(challenge),
+                                    Err(err) => {
+                                        error!(
+                                            "Failed to fetch second challenge from database: {:?}",
+                                            err
+                                        );
+                                        continue;
+                                    }
+                                },
+                            };
+
+                            if let Err(err) = send_user_message(&mut adapter, to, message).await {
+                                error!("{:?}", err);
+                            }
+                        }
                     }
                     Err(err) => {
                         error!(
@@ -229,34 +472,71 @@ pub mod tests {
 
     #[derive(Clone)]
     pub struct MessageInjector {
+        name: &'static str,
+        unique_ids: bool,
         messages: Arc<Mutex<Vec<ExternalMessage>>>,
+        sent: Arc<Mutex<Vec<(String, UserMessage)>>>,
+        // The number of upcoming sends which fail.
+        failing_sends: Arc<Mutex<usize>>,
     }
 
     impl MessageInjector {
         pub fn new() -> Self {
+            Self::with_name("test_state_injector")
+        }
+        /// Acts as the adapter with the given name, e.g. `matrix`.
+        pub fn with_name(name: &'static str) -> Self {
             MessageInjector {
+                name,
+                unique_ids: false,
                 messages: Arc::new(Mutex::new(vec![])),
+                sent: Arc::new(Mutex::new(vec![])),
+                failing_sends: Arc::new(Mutex::new(0)),
             }
         }
+        /// Processed messages are tracked in the database.
+        pub fn with_unique_ids(mut self) -> Self {
+            self.unique_ids = true;
+            self
+        }
         pub async fn send(&self, msg: ExternalMessage) {
             let mut lock = self.messages.lock().await;
             (*lock).push(msg);
         }
+        /// The next `count` messages fail to be sent.
+        pub async fn fail_sends(&self, count: usize) {
+            *self.failing_sends.lock().await = count;
+        }
+        pub async fn sent_messages(&self) -> Vec<(String, UserMessage)> {
+            let mut lock = self.sent.lock().await;
+            std::mem::take(&mut *lock)
+        }
     }
 
     #[async_trait]
     impl Adapter for MessageInjector {
-        type MessageType = ();
+        type MessageType = UserMessage;
 
         fn name(&self) -> &'static str {
-            "test_state_injector"
+            self.name
+        }
+        fn unique_message_ids(&self) -> bool {
+            self.unique_ids
         }
         async fn fetch_messages(&mut self) -> Result<Vec<ExternalMessage>> {
             let mut lock = self.messages.lock().await;
             Ok(std::mem::take(&mut *lock))
         }
-        async fn send_message(&mut self, _to: &str, _content: Self::MessageType) -> Result<()> {
-            unimplemented!()
+        async fn send_message(&mut self, to: &str, content: Self::MessageType) -> Result<()> {
+            let mut failing = self.failing_sends.lock().await;
+            if *failing > 0 {
+                *failing -= 1;
+                return Err(anyhow!("failed to send message"));
+            }
+
+            let mut lock = self.sent.lock().await;
+            (*lock).push((to.to_string(), content));
+            Ok(())
         }
     }
 }
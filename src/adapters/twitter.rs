@@ -1,5 +1,6 @@
 use crate::adapters::Adapter;
-use crate::primitives::{ExternalMessage, ExternalMessageType, MessageId, Timestamp};
+use crate::database::Database;
+use crate::primitives::{ExpectedMessage, ExternalMessage, ExternalMessageType, Timestamp};
 use crate::Result;
 use hmac::{Hmac, Mac};
 use rand::{thread_rng, Rng};
@@ -8,7 +9,7 @@ use reqwest::{Client, Request};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use sha1::Sha1;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::convert::{TryFrom, TryInto};
 use std::time::{SystemTime, UNIX_EPOCH};
 use std::{cmp::Ordering, hash::Hash};
@@ -55,11 +56,18 @@ impl TryFrom<String> for TwitterId {
     }
 }
 
+const DEFAULT_BASE_URL: &str = "https://api.twitter.com";
+
 pub struct TwitterBuilder {
     consumer_key: Option<String>,
     consumer_secret: Option<String>,
     token: Option<String>,
     token_secret: Option<String>,
+    bearer_token: Option<String>,
+    base_url: Option<String>,
+    direct_messages: bool,
+    tweets: bool,
+    db: Option<Database>,
 }
 
 impl TwitterBuilder {
@@ -69,6 +77,11 @@ impl TwitterBuilder {
             consumer_secret: None,
             token: None,
             token_secret: None,
+            bearer_token: None,
+            base_url: None,
+            direct_messages: true,
+            tweets: false,
+            db: None,
         }
     }
     pub fn consumer_key(mut self, key: String) -> Self {
@@ -87,21 +100,70 @@ impl TwitterBuilder {
         self.token_secret = Some(secret);
         self
     }
+    /// OAuth 2.0 (user context) access token. If specified, the v2 API is
+    /// used and the OAuth 1.0a credentials are not required.
+    pub fn bearer_token(mut self, token: String) -> Self {
+        self.bearer_token = Some(token);
+        self
+    }
+    pub fn base_url(mut self, url: String) -> Self {
+        self.base_url = Some(url);
+        self
+    }
+    /// Fetch direct messages sent to the registrar account (enabled by
+    /// default).
+    pub fn direct_messages(mut self, enabled: bool) -> Self {
+        self.direct_messages = enabled;
+        self
+    }
+    /// Fetch public tweets mentioning the registrar account, for users that
+    /// can not send direct messages.
+    pub fn tweets(mut self, enabled: bool) -> Self {
+        self.tweets = enabled;
+        self
+    }
+    /// Persist the looked up Twitter handles, so those do not have to be
+    /// looked up again after a restart.
+    pub fn database(mut self, db: Database) -> Self {
+        self.db = Some(db);
+        self
+    }
     pub fn build(self) -> Result<TwitterClient> {
+        if !self.direct_messages && !self.tweets {
+            return Err(anyhow!("neither direct messages nor tweets are enabled"));
+        }
+
+        let auth = if let Some(bearer_token) = self.bearer_token {
+            Auth::Bearer(bearer_token)
+        } else {
+            Auth::OAuth1 {
+                consumer_key: self
+                    .consumer_key
+                    .ok_or_else(|| anyhow!("consumer key name not specified"))?,
+                consumer_secret: self
+                    .consumer_secret
+                    .ok_or_else(|| anyhow!("consumer secret name not specified"))?,
+                token: self.token.ok_or_else(|| anyhow!("token not specified"))?,
+                token_secret: self
+                    .token_secret
+                    .ok_or_else(|| anyhow!("token secret not specified"))?,
+            }
+        };
+
         Ok(TwitterClient {
             client: Client::new(),
-            consumer_key: self
-                .consumer_key
-                .ok_or_else(|| anyhow!("consumer key name not specified"))?,
-            consumer_secret: self
-                .consumer_secret
-                .ok_or_else(|| anyhow!("consumer secret name not specified"))?,
-            token: self.token.ok_or_else(|| anyhow!("token not specified"))?,
-            token_secret: self
-                .token_secret
-                .ok_or_else(|| anyhow!("token secret not specified"))?,
+            auth,
+            base_url: self
+                .base_url
+                .as_deref()
+                .unwrap_or(DEFAULT_BASE_URL)
+                .trim_end_matches('/')
+                .to_string(),
+            direct_messages: self.direct_messages,
+            tweets: self.tweets,
+            own_id: None,
             twitter_ids: HashMap::new(),
-            cache: HashSet::new(),
+            db: self.db,
         })
     }
 }
@@ -119,33 +181,102 @@ fn gen_timestamp() -> u64 {
         .as_secs()
 }
 
+/// Percent-encodes the (query) parameters as required by OAuth 1.0a (RFC
+/// 3986, section 2.3).
+fn encode_params(params: &[(&str, &str)]) -> Vec<(String, String)> {
+    params
+        .iter()
+        .map(|(key, val)| (urlencoding::encode(key), urlencoding::encode(val)))
+        .collect()
+}
+
+/// Creates the OAuth 1.0a (HMAC-SHA1) signature of the request. The encoded
+/// parameters are signed, sorted by name and value.
+fn oauth_signature(
+    method: &str,
+    url: &str,
+    oauth_fields: &[(&str, &str)],
+    params: &[(&str, &str)],
+    consumer_secret: &str,
+    token_secret: &str,
+) -> String {
+    use urlencoding::encode;
+
+    let mut pairs = encode_params(oauth_fields);
+    pairs.extend(encode_params(params));
+    pairs.sort();
+
+    let params = pairs
+        .iter()
+        .map(|(name, val)| format!("{}={}", name, val))
+        .collect::<Vec<String>>()
+        .join("&");
+
+    let base = format!("{}&{}&{}", method, encode(url), encode(&params));
+
+    // Sign the base string.
+    let sign_key = format!("{}&{}", encode(consumer_secret), encode(token_secret));
+
+    let mut mac: Hmac<Sha1> = Hmac::new_from_slice(sign_key.as_bytes()).unwrap();
+    mac.update(base.as_bytes());
+
+    // Create the resulting hash.
+    base64::encode(mac.finalize().into_bytes())
+}
+
+/// The authentication method, which also determines the API version.
+#[derive(Clone)]
+enum Auth {
+    /// OAuth 1.0a, used with the v1.1 API.
+    OAuth1 {
+        consumer_key: String,
+        consumer_secret: String,
+        token: String,
+        token_secret: String,
+    },
+    /// OAuth 2.0 (user context) access token, used with the v2 API.
+    Bearer(String),
+}
+
 #[derive(Clone)]
 pub struct TwitterClient {
     client: Client,
-    consumer_key: String,
-    consumer_secret: String,
-    token: String,
-    token_secret: String,
+    auth: Auth,
+    base_url: String,
+    direct_messages: bool,
+    tweets: bool,
+    // The Id of the registrar account, only required for the v2 API.
+    own_id: Option<TwitterId>,
     twitter_ids: HashMap<TwitterId, String>,
-    // Keep track of messages.
-    cache: HashSet<MessageId>,
+    db: Option<Database>,
 }
 
 impl TwitterClient {
     async fn request_messages(&mut self) -> Result<Vec<ExternalMessage>> {
         debug!("Requesting Twitter messages");
         // Request message on parse those into a simpler type.
-        let mut messages = self
-            .get_request::<ApiMessageRequest>(
-                "https://api.twitter.com/1.1/direct_messages/events/list.json",
-                None,
-            )
-            .await?
-            .parse()?;
+        let mut messages = vec![];
+        if self.direct_messages {
+            messages.extend(match self.auth {
+                Auth::OAuth1 { .. } => self
+                    .get_request::<ApiMessageRequest>(
+                        &self.url("1.1/direct_messages/events/list.json"),
+                        None,
+                    )
+                    .await?
+                    .parse()?,
+                Auth::Bearer(_) => self.request_dm_events().await?,
+            });
+        }
 
-        // Skip message if it was already processed.
-        messages.retain(|message| !self.cache.contains(&message.id.into()));
+        if self.tweets {
+            messages.extend(match self.auth {
+                Auth::OAuth1 { .. } => self.request_mentions_timeline().await?,
+                Auth::Bearer(_) => self.request_mentions().await?,
+            });
+        }
 
+        // Processed messages are skipped by the `AdapterListener`.
         if messages.is_empty() {
             debug!("No new Twitter messages found");
             return Ok(vec![]);
@@ -171,8 +302,7 @@ impl TwitterClient {
         // Lookup Twitter Ids and insert those into the cache.
         debug!("Looking up Twitter Ids");
         if !to_lookup.is_empty() {
-            let lookup_results = self.lookup_twitter_id(Some(&to_lookup), None).await?;
-            self.twitter_ids.extend(lookup_results);
+            self.lookup_handles(&to_lookup).await?;
         }
 
         // Parse all messages into `TwitterMessage`.
@@ -184,76 +314,192 @@ impl TwitterClient {
                 .ok_or_else(|| anyhow!("Failed to find Twitter handle based on Id"))?
                 .clone();
 
-            let id = message.id.into();
-
             parsed_messages.push(ExternalMessage {
                 origin: ExternalMessageType::Twitter(sender),
-                id,
+                id: message.id.into(),
                 timestamp: Timestamp::now(),
                 values: vec![message.message.into()],
             });
-
-            self.cache.insert(id);
         }
 
         Ok(parsed_messages)
     }
-    /// Creates a signature as documented here:
+    /// Looks up the handles of the Twitter Ids. Handles which have been looked
+    /// up before (e.g. prior to a restart) are fetched from the database.
+    async fn lookup_handles(&mut self, twitter_ids: &[&TwitterId]) -> Result<()> {
+        let mut to_lookup = twitter_ids.to_vec();
+        if let Some(db) = &self.db {
+            let known = db.fetch_twitter_handles(twitter_ids).await?;
+            to_lookup.retain(|twitter_id| !known.contains_key(twitter_id));
+            self.twitter_ids.extend(known);
+        }
+
+        if to_lookup.is_empty() {
+            return Ok(());
+        }
+
+        let lookup_results = self.lookup_twitter_id(Some(&to_lookup), None).await?;
+        if let Some(db) = &self.db {
+            db.set_twitter_handles(&lookup_results).await?;
+        }
+
+        self.twitter_ids.extend(lookup_results);
+        Ok(())
+    }
+    async fn request_dm_events(&mut self) -> Result<Vec<ReceivedMessageContext>> {
+        let events = self
+            .get_request::<ApiDmEvents>(
+                &self.url("2/dm_events"),
+                Some(&[
+                    ("event_types", "MessageCreate"),
+                    ("dm_event.fields", "id,event_type,text,sender_id"),
+                    ("expansions", "sender_id"),
+                    ("user.fields", "username"),
+                ]),
+            )
+            .await?;
+
+        // The senders are included in the response, so those do not have to
+        // be looked up separately.
+        if let Some(includes) = &events.includes {
+            for user in &includes.users {
+                self.twitter_ids.insert(
+                    user.id.clone().try_into()?,
+                    format!("@{}", user.username.to_lowercase()),
+                );
+            }
+        }
+
+        events.parse()
+    }
+    async fn request_mentions_timeline(&mut self) -> Result<Vec<ReceivedMessageContext>> {
+        let tweets = self
+            .get_request::<Vec<ApiTweet>>(
+                &self.url("1.1/statuses/mentions_timeline.json"),
+                Some(&[("count", "200"), ("tweet_mode", "extended")]),
+            )
+            .await?;
+
+        // The authors are included in the response, so those do not have to
+        // be looked up separately.
+        let mut messages = vec![];
+        for tweet in tweets {
+            let sender: TwitterId = tweet.user.id_str.try_into()?;
+            self.twitter_ids.insert(
+                sender.clone(),
+                format!("@{}", tweet.user.screen_name.to_lowercase()),
+            );
+
+            messages.push(ReceivedMessageContext {
+                sender,
+                id: tweet
+                    .id_str
+                    .parse()
+                    .map_err(|_| anyhow!("unrecognized data"))?,
+                message: tweet.full_text,
+            });
+        }
+
+        Ok(messages)
+    }
+    async fn request_mentions(&mut self) -> Result<Vec<ReceivedMessageContext>> {
+        let own_id = match &self.own_id {
+            Some(own_id) => own_id.clone(),
+            None => {
+                let own_id: TwitterId = self
+                    .get_request::<ApiMe>(&self.url("2/users/me"), None)
+                    .await?
+                    .data
+                    .id
+                    .try_into()?;
+
+                self.own_id = Some(own_id.clone());
+                own_id
+            }
+        };
+
+        let tweets = self
+            .get_request::<ApiTweets>(
+                &self.url(&format!("2/users/{}/mentions", own_id.as_u64())),
+                Some(&[
+                    ("max_results", "100"),
+                    ("tweet.fields", "id,text,author_id"),
+                    ("expansions", "author_id"),
+                    ("user.fields", "username"),
+                ]),
+            )
+            .await?;
+
+        if let Some(includes) = &tweets.includes {
+            for user in &includes.users {
+                self.twitter_ids.insert(
+                    user.id.clone().try_into()?,
+                    format!("@{}", user.username.to_lowercase()),
+                );
+            }
+        }
+
+        tweets.parse()
+    }
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url, path)
+    }
+    /// Authenticates the request with the bearer token or creates a signature
+    /// as documented here:
     /// https://developer.twitter.com/en/docs/authentication/oauth-1-0a/creating-a-signature
     fn authenticate_request(
         &self,
+        method: &str,
         url: &str,
         request: &mut Request,
         params: Option<&[(&str, &str)]>,
     ) -> Result<()> {
         use urlencoding::encode;
 
+        let (consumer_key, consumer_secret, token, token_secret) = match &self.auth {
+            Auth::OAuth1 {
+                consumer_key,
+                consumer_secret,
+                token,
+                token_secret,
+            } => (consumer_key, consumer_secret, token, token_secret),
+            Auth::Bearer(token) => {
+                request.headers_mut().insert(
+                    header::AUTHORIZATION,
+                    HeaderValue::from_str(&format!("Bearer {}", token))?,
+                );
+
+                return Ok(());
+            }
+        };
+
         // Prepare  required data.
         let nonce = gen_nonce();
         let timestamp = gen_timestamp().to_string();
 
         // Create  OAuth 1.0 fields.
         let mut fields = vec![
-            ("oauth_consumer_key", self.consumer_key.as_str()),
+            ("oauth_consumer_key", consumer_key.as_str()),
             ("oauth_nonce", nonce.as_str()),
             ("oauth_signature_method", "HMAC-SHA1"),
             ("oauth_timestamp", &timestamp),
-            ("oauth_token", self.token.as_str()),
+            ("oauth_token", token.as_str()),
             ("oauth_version", "1.0"),
         ];
 
-        if let Some(params) = params {
-            fields.append(&mut params.to_vec());
-        }
-
-        fields.sort_by(|(a, _), (b, _)| a.cmp(b));
-
-        let mut params = String::new();
-        for (name, val) in &fields {
-            params.push_str(&format!("{}={}&", encode(name), encode(val)));
-        }
-
-        // Remove the trailing `&`.
-        params.pop();
-
-        let base = format!("GET&{}&{}", encode(url), encode(&params));
-
-        // Sign the base string.
-        let sign_key = format!(
-            "{}&{}",
-            encode(&self.consumer_secret),
-            encode(&self.token_secret)
+        let sig = oauth_signature(
+            method,
+            url,
+            &fields,
+            params.unwrap_or_default(),
+            consumer_secret,
+            token_secret,
         );
 
-        let mut mac: Hmac<Sha1> = Hmac::new_from_slice(sign_key.as_bytes()).unwrap();
-        mac.update(base.as_bytes());
-
-        // Create the resulting hash.
-        let sig = base64::encode(mac.finalize().into_bytes());
-
-        // Insert the signature;
+        // Insert the signature, the query parameters are not part of the
+        // header.
         fields.push(("oauth_signature", &sig));
-        fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+        fields.sort_by_key(|(name, _)| *name);
 
         // Merge all fields into the OAuth header.
         let mut oauth_header = String::new();
@@ -281,18 +527,20 @@ impl TwitterClient {
     ) -> Result<T> {
         let mut full_url = String::from(url);
 
-        if let Some(params) = params {
+        // The parameters are encoded the same way as for the OAuth signature.
+        if let Some(params) = params.filter(|params| !params.is_empty()) {
             full_url.push('?');
-            for (key, val) in params {
-                full_url.push_str(&format!("{}={}&", key, val));
-            }
-
-            // Remove trailing `&` or `?` in case "params" is empty.
-            full_url.pop();
+            full_url.push_str(
+                &encode_params(params)
+                    .iter()
+                    .map(|(key, val)| format!("{}={}", key, val))
+                    .collect::<Vec<String>>()
+                    .join("&"),
+            );
         }
 
         let mut request = self.client.get(&full_url).build()?;
-        self.authenticate_request(url, &mut request, params)?;
+        self.authenticate_request("GET", url, &mut request, params)?;
         let resp = self.client.execute(request).await?;
         let txt = resp.text().await?;
 
@@ -300,11 +548,100 @@ impl TwitterClient {
 
         serde_json::from_str::<T>(&txt).map_err(|err| err.into())
     }
+    async fn post_request<T: Serialize>(&self, url: &str, body: &T) -> Result<()> {
+        // The JSON body is not part of the signature.
+        let mut request = self
+            .client
+            .post(url)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(serde_json::to_string(body)?)
+            .build()?;
+        self.authenticate_request("POST", url, &mut request, None)?;
+        let resp = self.client.execute(request).await?;
+
+        if !resp.status().is_success() {
+            return Err(anyhow!(
+                "Twitter returned status {}: {:?}",
+                resp.status(),
+                resp.text().await?
+            ));
+        }
+
+        Ok(())
+    }
+    async fn send_direct_message(&mut self, to: &str, text: String) -> Result<()> {
+        let handle = format!("@{}", to.trim_start_matches('@').to_lowercase());
+
+        // Lookup the Twitter Id of the recipient, if not cached yet.
+        let mut cached = self
+            .twitter_ids
+            .iter()
+            .find(|(_, cached)| cached == &&handle)
+            .map(|(id, _)| id.clone());
+
+        if let (None, Some(db)) = (&cached, &self.db) {
+            cached = db.fetch_twitter_id(&handle).await?;
+        }
+
+        let twitter_id = match cached {
+            Some(twitter_id) => twitter_id,
+            None => {
+                let lookup_results = self.lookup_twitter_id(None, Some(&[&handle])).await?;
+                let twitter_id = lookup_results
+                    .iter()
+                    .find(|(_, found)| found == &&handle)
+                    .map(|(id, _)| id.clone())
+                    .ok_or_else(|| anyhow!("Failed to find Twitter Id of {}", handle))?;
+
+                if let Some(db) = &self.db {
+                    db.set_twitter_handles(&lookup_results).await?;
+                }
+
+                self.twitter_ids.extend(lookup_results);
+                twitter_id
+            }
+        };
+
+        match self.auth {
+            Auth::OAuth1 { .. } => {
+                self.post_request(
+                    &self.url("1.1/direct_messages/events/new.json"),
+                    &ApiNewEvent {
+                        event: ApiNewMessageEvent {
+                            t_type: "message_create".to_string(),
+                            message_create: ApiMessageCreate {
+                                target: ApiTarget {
+                                    recipient_id: twitter_id.as_u64().to_string(),
+                                },
+                                sender_id: None,
+                                message_data: ApiMessageData { text },
+                            },
+                        },
+                    },
+                )
+                .await
+            }
+            Auth::Bearer(_) => {
+                self.post_request(
+                    &self.url(&format!(
+                        "2/dm_conversations/with/{}/messages",
+                        twitter_id.as_u64()
+                    )),
+                    &ApiNewDmMessage { text },
+                )
+                .await
+            }
+        }
+    }
     async fn lookup_twitter_id(
         &self,
         twitter_ids: Option<&[&TwitterId]>,
         accounts: Option<&[&String]>,
     ) -> Result<HashMap<TwitterId, String>> {
+        if let Auth::Bearer(_) = self.auth {
+            return self.lookup_users(twitter_ids, accounts).await;
+        }
+
         let mut params = vec![];
 
         // Lookups for UserIds
@@ -345,10 +682,7 @@ impl TwitterClient {
         debug!("Params: {:?}", params);
 
         let user_objects = self
-            .get_request::<Vec<UserObject>>(
-                "https://api.twitter.com/1.1/users/lookup.json",
-                Some(&params),
-            )
+            .get_request::<Vec<UserObject>>(&self.url("1.1/users/lookup.json"), Some(&params))
             .await?;
 
         if user_objects.is_empty() {
@@ -360,6 +694,64 @@ impl TwitterClient {
             .map(|obj| (obj.id, format!("@{}", obj.screen_name.to_lowercase())))
             .collect())
     }
+    /// Equivalent to `lookup_twitter_id`, using the v2 API.
+    async fn lookup_users(
+        &self,
+        twitter_ids: Option<&[&TwitterId]>,
+        accounts: Option<&[&String]>,
+    ) -> Result<HashMap<TwitterId, String>> {
+        let mut users = vec![];
+
+        // Lookups for UserIds
+        if let Some(twitter_ids) = twitter_ids {
+            let lookup = twitter_ids
+                .iter()
+                .map(|twitter_id| twitter_id.as_u64().to_string())
+                .collect::<Vec<String>>()
+                .join(",");
+
+            users.extend(
+                self.get_request::<ApiUsers>(
+                    &self.url("2/users"),
+                    Some(&[("ids", lookup.as_str())]),
+                )
+                .await?
+                .data,
+            );
+        }
+
+        // Lookups for Accounts
+        if let Some(accounts) = accounts {
+            let lookup = accounts
+                .iter()
+                .map(|account| account.replace('@', ""))
+                .collect::<Vec<String>>()
+                .join(",");
+
+            users.extend(
+                self.get_request::<ApiUsers>(
+                    &self.url("2/users/by"),
+                    Some(&[("usernames", lookup.as_str())]),
+                )
+                .await?
+                .data,
+            );
+        }
+
+        if users.is_empty() {
+            return Err(anyhow!("unrecognized data"));
+        }
+
+        users
+            .into_iter()
+            .map(|user| {
+                Ok((
+                    user.id.try_into()?,
+                    format!("@{}", user.username.to_lowercase()),
+                ))
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -379,6 +771,7 @@ struct ApiEvent {
 #[derive(Debug, Deserialize, Serialize)]
 struct ApiMessageCreate {
     target: ApiTarget,
+    #[serde(skip_serializing_if = "Option::is_none")]
     sender_id: Option<String>,
     message_data: ApiMessageData,
 }
@@ -393,6 +786,18 @@ struct ApiMessageData {
     text: String,
 }
 
+#[derive(Debug, Serialize)]
+struct ApiNewEvent {
+    event: ApiNewMessageEvent,
+}
+
+#[derive(Debug, Serialize)]
+struct ApiNewMessageEvent {
+    #[serde(rename = "type")]
+    t_type: String,
+    message_create: ApiMessageCreate,
+}
+
 impl ApiMessageRequest {
     fn parse(self) -> Result<Vec<ReceivedMessageContext>> {
         let mut messages = vec![];
@@ -415,17 +820,379 @@ impl ApiMessageRequest {
     }
 }
 
+#[derive(Debug, Deserialize)]
+struct ApiDmEvents {
+    #[serde(default)]
+    data: Vec<ApiDmEvent>,
+    includes: Option<ApiIncludes>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiDmEvent {
+    id: String,
+    event_type: String,
+    text: Option<String>,
+    sender_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiIncludes {
+    #[serde(default)]
+    users: Vec<ApiUser>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiUsers {
+    #[serde(default)]
+    data: Vec<ApiUser>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiUser {
+    id: String,
+    username: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiMe {
+    data: ApiUser,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiTweets {
+    #[serde(default)]
+    data: Vec<ApiV2Tweet>,
+    includes: Option<ApiIncludes>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiV2Tweet {
+    id: String,
+    text: String,
+    author_id: Option<String>,
+}
+
+/// Tweet as returned by the v1.1 API (in extended mode).
+#[derive(Debug, Deserialize)]
+struct ApiTweet {
+    id_str: String,
+    full_text: String,
+    user: ApiTweetUser,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiTweetUser {
+    id_str: String,
+    screen_name: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ApiNewDmMessage {
+    text: String,
+}
+
+impl ApiDmEvents {
+    fn parse(self) -> Result<Vec<ReceivedMessageContext>> {
+        let mut messages = vec![];
+
+        // Other event types (e.g. participants joining) are ignored.
+        for event in self
+            .data
+            .into_iter()
+            .filter(|event| event.event_type == "MessageCreate")
+        {
+            let message = ReceivedMessageContext {
+                sender: event
+                    .sender_id
+                    .ok_or_else(|| anyhow!("unrecognized data"))?
+                    .try_into()?,
+                message: event.text.ok_or_else(|| anyhow!("unrecognized data"))?,
+                id: event.id.parse().map_err(|_| anyhow!("unrecognized data"))?,
+            };
+
+            messages.push(message);
+        }
+
+        Ok(messages)
+    }
+}
+
+impl ApiTweets {
+    fn parse(self) -> Result<Vec<ReceivedMessageContext>> {
+        let mut messages = vec![];
+
+        for tweet in self.data {
+            let message = ReceivedMessageContext {
+                sender: tweet
+                    .author_id
+                    .ok_or_else(|| anyhow!("unrecognized data"))?
+                    .try_into()?,
+                message: tweet.text,
+                id: tweet.id.parse().map_err(|_| anyhow!("unrecognized data"))?,
+            };
+
+            messages.push(message);
+        }
+
+        Ok(messages)
+    }
+}
+
 #[async_trait]
 impl Adapter for TwitterClient {
-    type MessageType = ();
+    type MessageType = ExpectedMessage;
 
     fn name(&self) -> &'static str {
-        "Twitter"
+        "twitter"
+    }
+    fn unique_message_ids(&self) -> bool {
+        true
     }
     async fn fetch_messages(&mut self) -> Result<Vec<ExternalMessage>> {
         self.request_messages().await
     }
-    async fn send_message(&mut self, _to: &str, _content: Self::MessageType) -> Result<()> {
-        unimplemented!()
+    async fn send_message(&mut self, to: &str, content: Self::MessageType) -> Result<()> {
+        self.send_direct_message(
+            to,
+            format!(
+                "Insert the following challenge into the web interface: {}",
+                content.value
+            ),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::MessageId;
+    use actix_web::http::header::AUTHORIZATION;
+    use actix_web::{web, App, HttpRequest, HttpResponse};
+    use std::sync::Mutex;
+
+    fn authorized(req: &HttpRequest) -> bool {
+        req.headers()
+            .get(AUTHORIZATION)
+            .map(|auth| auth == "Bearer token")
+            .unwrap_or(false)
+    }
+
+    async fn dm_events(req: HttpRequest) -> HttpResponse {
+        if !authorized(&req) {
+            return HttpResponse::Unauthorized().finish();
+        }
+
+        HttpResponse::Ok().content_type("application/json").body(
+            r#"{
+                "data": [
+                    {"id": "1001", "event_type": "MessageCreate", "text": "challenge", "sender_id": "1"},
+                    {"id": "1002", "event_type": "ParticipantsJoin", "sender_id": "1"}
+                ],
+                "includes": {"users": [{"id": "1", "name": "Alice", "username": "Alice"}]}
+            }"#,
+        )
+    }
+
+    async fn users_me(req: HttpRequest) -> HttpResponse {
+        if !authorized(&req) {
+            return HttpResponse::Unauthorized().finish();
+        }
+
+        HttpResponse::Ok()
+            .content_type("application/json")
+            .body(r#"{"data": {"id": "100", "name": "Registrar", "username": "registrar"}}"#)
+    }
+
+    async fn mentions(req: HttpRequest) -> HttpResponse {
+        if !authorized(&req) {
+            return HttpResponse::Unauthorized().finish();
+        }
+
+        HttpResponse::Ok().content_type("application/json").body(
+            r#"{
+                "data": [
+                    {"id": "2001", "text": "@registrar challenge", "author_id": "1"}
+                ],
+                "includes": {"users": [{"id": "1", "name": "Alice", "username": "Alice"}]}
+            }"#,
+        )
+    }
+
+    async fn users_by(
+        req: HttpRequest,
+        query: web::Query<HashMap<String, String>>,
+    ) -> HttpResponse {
+        if !authorized(&req) || query.get("usernames").map(|s| s.as_str()) != Some("bob") {
+            return HttpResponse::Unauthorized().finish();
+        }
+
+        HttpResponse::Ok()
+            .content_type("application/json")
+            .body(r#"{"data": [{"id": "2", "name": "Bob", "username": "Bob"}]}"#)
+    }
+
+    async fn new_message(
+        req: HttpRequest,
+        id: web::Path<String>,
+        body: web::Json<HashMap<String, String>>,
+        sent: web::Data<Mutex<Vec<(String, String)>>>,
+    ) -> HttpResponse {
+        if !authorized(&req) {
+            return HttpResponse::Unauthorized().finish();
+        }
+
+        sent.lock()
+            .unwrap()
+            .push((id.into_inner(), body["text"].clone()));
+
+        HttpResponse::Created().finish()
+    }
+
+    fn client(base_url: String, token: &str) -> TwitterClient {
+        TwitterBuilder::new()
+            .bearer_token(token.to_string())
+            .base_url(base_url)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn oauth_signature_of_encoded_params() {
+        // Example of the Twitter documentation ("Creating a signature").
+        let oauth_fields = [
+            ("oauth_consumer_key", "xvz1evFS4wEEPTGEFPHBog"),
+            ("oauth_nonce", "kYjzVBB8Y0ZFabxSWbWovY3uYSQ2pTgmZeNu2VS4cg"),
+            ("oauth_signature_method", "HMAC-SHA1"),
+            ("oauth_timestamp", "1318622958"),
+            (
+                "oauth_token",
+                "370773112-GmHxMAgYyLbNEtIKZeRNFsMKPR9EyMZeS9weJAEb",
+            ),
+            ("oauth_version", "1.0"),
+        ];
+        let params = [
+            (
+                "status",
+                "Hello Ladies + Gentlemen, a signed OAuth request!",
+            ),
+            ("include_entities", "true"),
+        ];
+
+        let sig = oauth_signature(
+            "POST",
+            "https://api.twitter.com/1.1/statuses/update.json",
+            &oauth_fields,
+            &params,
+            "kAcSOqF21Fu85e7zjz7ZN2U4ZRhfV3WpwPAoE3Z7kBw",
+            "LswwdoUaIvS8ltyTt5jkRh4J50vUPVVHtR2YPi5kE",
+        );
+        assert_eq!(sig, "hCtSmYh+iHYCEqBWrE7C7hYmtUk=");
+
+        assert_eq!(
+            encode_params(&[("screen_name", "alice,bob"), ("q", "a b+c")]),
+            vec![
+                ("screen_name".to_string(), "alice%2Cbob".to_string()),
+                ("q".to_string(), "a%20b%2Bc".to_string()),
+            ]
+        );
+    }
+
+    #[actix::test]
+    async fn fetch_messages_v2() {
+        let stub = actix_test::start(|| App::new().route("/2/dm_events", web::get().to(dm_events)));
+
+        // Invalid token.
+        let mut twitter = client(stub.url(""), "invalid");
+        assert!(twitter.fetch_messages().await.is_err());
+
+        let mut twitter = client(stub.url(""), "token");
+        let messages = twitter.fetch_messages().await.unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(
+            messages[0].origin,
+            ExternalMessageType::Twitter("@alice".to_string())
+        );
+        assert_eq!(messages[0].id, MessageId::from(1001u64));
+        assert_eq!(messages[0].values, vec!["challenge".to_string().into()]);
+
+        // Processed messages are skipped by the `AdapterListener`, not the
+        // adapter itself.
+        let again = twitter.fetch_messages().await.unwrap();
+        assert_eq!(again.len(), 1);
+        assert_eq!(again[0].id, messages[0].id);
+    }
+
+    #[actix::test]
+    async fn fetch_mentions_v2() {
+        let stub = actix_test::start(|| {
+            App::new()
+                .route("/2/users/me", web::get().to(users_me))
+                .route("/2/users/100/mentions", web::get().to(mentions))
+        });
+
+        // Direct messages are not fetched (`/2/dm_events` is not served).
+        let mut twitter = TwitterBuilder::new()
+            .bearer_token("token".to_string())
+            .base_url(stub.url(""))
+            .direct_messages(false)
+            .tweets(true)
+            .build()
+            .unwrap();
+
+        let messages = twitter.fetch_messages().await.unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(
+            messages[0].origin,
+            ExternalMessageType::Twitter("@alice".to_string())
+        );
+        assert_eq!(messages[0].id, MessageId::from(2001u64));
+        assert_eq!(
+            messages[0].values,
+            vec!["@registrar challenge".to_string().into()]
+        );
+
+        // Processed tweets are skipped by the `AdapterListener`.
+        assert_eq!(twitter.fetch_messages().await.unwrap().len(), 1);
+
+        // At least one mode must be enabled.
+        assert!(TwitterBuilder::new()
+            .bearer_token("token".to_string())
+            .direct_messages(false)
+            .build()
+            .is_err());
+    }
+
+    #[actix::test]
+    async fn send_message_v2() {
+        let sent = web::Data::new(Mutex::new(Vec::<(String, String)>::new()));
+        let data = sent.clone();
+        let stub = actix_test::start(move || {
+            App::new()
+                .app_data(data.clone())
+                .route("/2/users/by", web::get().to(users_by))
+                .route(
+                    "/2/dm_conversations/with/{id}/messages",
+                    web::post().to(new_message),
+                )
+        });
+
+        let mut twitter = client(stub.url(""), "token");
+        let challenge = ExpectedMessage::random();
+        twitter
+            .send_message("@Bob", challenge.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            *sent.lock().unwrap(),
+            vec![(
+                "2".to_string(),
+                format!(
+                    "Insert the following challenge into the web interface: {}",
+                    challenge.value
+                )
+            )]
+        );
     }
 }
@@ -0,0 +1,455 @@
+use crate::adapters::Adapter;
+use crate::database::Database;
+use crate::primitives::{
+    is_challenge_shaped, ExternalMessage, ExternalMessageType, MessagePart, Timestamp,
+};
+use crate::Result;
+use reqwest::redirect::Policy;
+use reqwest::ClientBuilder;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+use trust_dns_resolver::error::ResolveErrorKind;
+use trust_dns_resolver::TokioAsyncResolver;
+use url::Url;
+
+const WELL_KNOWN_PATH: &str = ".well-known/polkadot-registrar.txt";
+const HTTP_TIMEOUT: u64 = 10;
+// Anything bigger than this is certainly not a challenge.
+const MAX_CONTENT_LENGTH: usize = 4096;
+// Only TXT records with this prefix are considered, other records of the
+// domain (SPF, site verifications, etc.) are ignored.
+pub const TXT_RECORD_PREFIX: &str = "polkadot-registrar=";
+
+/// Looks up the TXT records of a domain.
+#[async_trait]
+pub trait TxtResolver {
+    async fn txt_records(&self, domain: &str) -> Result<Vec<String>>;
+}
+
+/// Fetches the `.well-known` challenge file of a domain, if it exists.
+#[async_trait]
+pub trait WellKnownFetcher {
+    async fn fetch_well_known(&self, domain: &str) -> Result<Option<String>>;
+}
+
+fn system_resolver() -> Result<TokioAsyncResolver> {
+    // Fall back to the default (Google) resolvers if there is no system
+    // configuration available (e.g. in some containers).
+    Ok(TokioAsyncResolver::tokio_from_system_conf().or_else(|err| {
+        warn!(
+            "Failed to read system DNS config ({:?}), using defaults",
+            err
+        );
+        TokioAsyncResolver::tokio(Default::default(), Default::default())
+    })?)
+}
+
+pub struct DnsResolver {
+    resolver: TokioAsyncResolver,
+}
+
+impl DnsResolver {
+    pub fn new() -> Result<Self> {
+        Ok(DnsResolver {
+            resolver: system_resolver()?,
+        })
+    }
+}
+
+#[async_trait]
+impl TxtResolver for DnsResolver {
+    async fn txt_records(&self, domain: &str) -> Result<Vec<String>> {
+        let lookup = match self.resolver.txt_lookup(domain).await {
+            Ok(lookup) => lookup,
+            Err(err) => {
+                return match err.kind() {
+                    ResolveErrorKind::NoRecordsFound { .. } => Ok(vec![]),
+                    _ => Err(err.into()),
+                }
+            }
+        };
+
+        // A single TXT record can consist of multiple strings, join those.
+        Ok(lookup
+            .iter()
+            .map(|txt| {
+                txt.iter()
+                    .map(|data| String::from_utf8_lossy(data).to_string())
+                    .collect::<String>()
+            })
+            .collect())
+    }
+}
+
+/// Whether the address is reachable on the public internet. The web field is
+/// user provided, so requests to internal services (loopback, private
+/// networks, cloud metadata endpoints, etc.) must be prevented.
+fn is_public_address(addr: &IpAddr) -> bool {
+    match addr {
+        IpAddr::V4(addr) => is_public_ipv4(addr),
+        IpAddr::V6(addr) => {
+            if let Some(mapped) = addr.to_ipv4_mapped() {
+                return is_public_ipv4(&mapped);
+            }
+
+            let segments = addr.segments();
+            !(addr.is_unspecified()
+                || addr.is_loopback()
+                || addr.is_multicast()
+                // Unique local (fc00::/7).
+                || (segments[0] & 0xfe00) == 0xfc00
+                // Link-local (fe80::/10).
+                || (segments[0] & 0xffc0) == 0xfe80
+                // Documentation (2001:db8::/32).
+                || (segments[0] == 0x2001 && segments[1] == 0xdb8)
+                // NAT64 (64:ff9b::/96) could be used to reach IPv4 addresses.
+                || (segments[0] == 0x64 && segments[1] == 0xff9b))
+        }
+    }
+}
+
+fn is_public_ipv4(addr: &Ipv4Addr) -> bool {
+    let octets = addr.octets();
+    !(addr.is_unspecified()
+        || addr.is_loopback()
+        || addr.is_private()
+        || addr.is_link_local()
+        || addr.is_broadcast()
+        || addr.is_documentation()
+        || addr.is_multicast()
+        // "This network" (0.0.0.0/8).
+        || octets[0] == 0
+        // Shared address space (100.64.0.0/10).
+        || (octets[0] == 100 && (octets[1] & 0xc0) == 64)
+        // IETF protocol assignments (192.0.0.0/24).
+        || (octets[0] == 192 && octets[1] == 0 && octets[2] == 0)
+        // Benchmarking (198.18.0.0/15).
+        || (octets[0] == 198 && (octets[1] & 0xfe) == 18)
+        // Reserved (240.0.0.0/4).
+        || octets[0] >= 240)
+}
+
+pub struct HttpFetcher {
+    resolver: TokioAsyncResolver,
+    // Replaces `https://<domain>`, only used for testing.
+    base_url: Option<String>,
+}
+
+impl HttpFetcher {
+    pub fn new() -> Result<Self> {
+        Ok(HttpFetcher {
+            resolver: system_resolver()?,
+            base_url: None,
+        })
+    }
+    fn client_builder() -> ClientBuilder {
+        // Redirects are not followed, those could point to any (internal)
+        // address.
+        ClientBuilder::new()
+            .timeout(Duration::from_secs(HTTP_TIMEOUT))
+            .redirect(Policy::none())
+    }
+    /// Resolves the domain and makes sure that none of its addresses are
+    /// internal.
+    async fn resolve_public(&self, domain: &str) -> Result<IpAddr> {
+        let addrs: Vec<IpAddr> = self.resolver.lookup_ip(domain).await?.iter().collect();
+
+        if let Some(addr) = addrs.iter().find(|addr| !is_public_address(addr)) {
+            return Err(anyhow!(
+                "{} resolves to non-public address {}",
+                domain,
+                addr
+            ));
+        }
+
+        addrs
+            .first()
+            .copied()
+            .ok_or_else(|| anyhow!("{} does not resolve to any address", domain))
+    }
+    #[cfg(test)]
+    pub fn with_base_url(base_url: String) -> Result<Self> {
+        let mut fetcher = Self::new()?;
+        fetcher.base_url = Some(base_url.trim_end_matches('/').to_string());
+        Ok(fetcher)
+    }
+}
+
+#[async_trait]
+impl WellKnownFetcher for HttpFetcher {
+    async fn fetch_well_known(&self, domain: &str) -> Result<Option<String>> {
+        let (url, client) = match &self.base_url {
+            Some(base_url) => (
+                format!("{}/{}", base_url, WELL_KNOWN_PATH),
+                Self::client_builder().build()?,
+            ),
+            None => {
+                // Pin the connection to the checked address, so a second
+                // lookup (e.g. DNS rebinding) cannot return a different one.
+                let addr = self.resolve_public(domain).await?;
+                (
+                    format!("https://{}/{}", domain, WELL_KNOWN_PATH),
+                    Self::client_builder()
+                        .resolve(domain, SocketAddr::new(addr, 443))
+                        .build()?,
+                )
+            }
+        };
+
+        let resp = client.get(&url).send().await?;
+        if !resp.status().is_success() {
+            return Ok(None);
+        }
+
+        let mut content = resp.text().await?;
+        content.truncate(MAX_CONTENT_LENGTH);
+
+        Ok(Some(content))
+    }
+}
+
+/// Extracts the challenge from a TXT record, if it has the registrar prefix.
+fn txt_record_challenge(record: &str) -> Option<String> {
+    record
+        .trim()
+        .strip_prefix(TXT_RECORD_PREFIX)
+        .map(|challenge| challenge.trim().to_string())
+        .filter(|challenge| is_challenge_shaped(challenge))
+}
+
+/// Extracts the challenge from the `.well-known` file, the prefix is
+/// optional.
+fn well_known_challenge(content: &str) -> Option<String> {
+    let content = content.trim();
+    let challenge = content
+        .strip_prefix(TXT_RECORD_PREFIX)
+        .unwrap_or(content)
+        .trim();
+
+    Some(challenge.to_string()).filter(|challenge| is_challenge_shaped(challenge))
+}
+
+/// Extracts the domain from the (user provided) web field, which might
+/// contain a scheme, path, etc.
+fn extract_domain(value: &str) -> Option<String> {
+    let value = value.trim();
+
+    let url = if value.contains("://") {
+        Url::parse(value)
+    } else {
+        Url::parse(&format!("https://{}", value))
+    }
+    .ok()?;
+
+    url.host_str()
+        .map(|host| host.trim_end_matches('.').to_lowercase())
+        .filter(|host| !host.is_empty())
+}
+
+pub struct WebClient<R, F> {
+    db: Database,
+    resolver: R,
+    fetcher: F,
+    // Keep track of the content that was last seen for each field. Avoids
+    // counting the same (invalid) content as a failed attempt on every poll.
+    cache: HashMap<String, Vec<MessagePart>>,
+}
+
+impl<R, F> WebClient<R, F>
+where
+    R: TxtResolver + Send + Sync,
+    F: WellKnownFetcher + Send + Sync,
+{
+    pub fn new(db: Database, resolver: R, fetcher: F) -> Self {
+        WebClient {
+            db,
+            resolver,
+            fetcher,
+            cache: HashMap::new(),
+        }
+    }
+    async fn request_messages(&mut self) -> Result<Vec<ExternalMessage>> {
+        let fields = self.db.fetch_unverified_web_fields().await?;
+
+        // Drop entries which have been verified in the meantime.
+        self.cache.retain(|field, _| fields.contains(field));
+
+        let mut messages = vec![];
+        for field in fields {
+            let domain = match extract_domain(&field) {
+                Some(domain) => domain,
+                None => {
+                    debug!("Skipping invalid domain: {}", field);
+                    continue;
+                }
+            };
+
+            // Only content which looks like a challenge is considered,
+            // anything else does not count as a failed attempt.
+            let mut challenges: Vec<String> = vec![];
+
+            match self.resolver.txt_records(&domain).await {
+                Ok(records) => challenges.extend(
+                    records
+                        .iter()
+                        .filter_map(|record| txt_record_challenge(record)),
+                ),
+                Err(err) => debug!("Failed to lookup TXT records of {}: {:?}", domain, err),
+            }
+
+            match self.fetcher.fetch_well_known(&domain).await {
+                Ok(Some(content)) => challenges.extend(well_known_challenge(&content)),
+                Ok(None) => {}
+                Err(err) => debug!("Failed to fetch well-known file of {}: {:?}", domain, err),
+            }
+
+            // Nothing was published (yet).
+            if challenges.is_empty() {
+                continue;
+            }
+
+            // DNS does not guarantee any order of the records.
+            challenges.sort();
+            challenges.dedup();
+            let values: Vec<MessagePart> = challenges.into_iter().map(|c| c.into()).collect();
+
+            // Skip domain if the content did not change since the last poll.
+            if self.cache.get(&field) == Some(&values) {
+                continue;
+            }
+
+            debug!("Found new content for {}", domain);
+            self.cache.insert(field.clone(), values.clone());
+
+            messages.push(ExternalMessage {
+                origin: ExternalMessageType::Web(field),
+                // A message UID is not relevant, the content itself is
+                // tracked by the cache.
+                id: 0u32.into(),
+                timestamp: Timestamp::now(),
+                values,
+            });
+        }
+
+        Ok(messages)
+    }
+}
+
+#[async_trait]
+impl<R, F> Adapter for WebClient<R, F>
+where
+    R: TxtResolver + Send + Sync,
+    F: WellKnownFetcher + Send + Sync,
+{
+    type MessageType = ();
+
+    fn name(&self) -> &'static str {
+        "web"
+    }
+    async fn fetch_messages(&mut self) -> Result<Vec<ExternalMessage>> {
+        self.request_messages().await
+    }
+    async fn send_message(&mut self, _to: &str, _content: Self::MessageType) -> Result<()> {
+        Err(anyhow!(
+            "sending messages is not supported by the {} adapter",
+            self.name()
+        ))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    #[derive(Clone, Default)]
+    pub struct TxtResolverMocker {
+        records: Arc<Mutex<HashMap<String, Vec<String>>>>,
+    }
+
+    impl TxtResolverMocker {
+        pub async fn insert(&self, domain: &str, record: String) {
+            let mut lock = self.records.lock().await;
+            lock.entry(domain.to_string()).or_default().push(record);
+        }
+    }
+
+    #[async_trait]
+    impl TxtResolver for TxtResolverMocker {
+        async fn txt_records(&self, domain: &str) -> Result<Vec<String>> {
+            let lock = self.records.lock().await;
+            Ok(lock.get(domain).cloned().unwrap_or_default())
+        }
+    }
+
+    #[test]
+    fn extract_challenges() {
+        let challenge = "8c2b0e9f4d6a1b3c5e7f9a0b2c4d6e8f";
+
+        assert_eq!(
+            txt_record_challenge(&format!("polkadot-registrar={}", challenge)).unwrap(),
+            challenge
+        );
+        assert_eq!(
+            txt_record_challenge("polkadot-registrar= maple otter river stone cloud amber ")
+                .unwrap(),
+            "maple otter river stone cloud amber"
+        );
+        assert!(txt_record_challenge(challenge).is_none());
+        assert!(txt_record_challenge("v=spf1 include:_spf.alice.com ~all").is_none());
+        assert!(txt_record_challenge("polkadot-registrar=").is_none());
+
+        assert_eq!(well_known_challenge(challenge).unwrap(), challenge);
+        assert_eq!(
+            well_known_challenge(&format!("polkadot-registrar={}\n", challenge)).unwrap(),
+            challenge
+        );
+        assert!(well_known_challenge("<!DOCTYPE html><html><body></body></html>").is_none());
+        assert!(well_known_challenge("").is_none());
+    }
+
+    #[test]
+    fn extract_domain_from_field() {
+        assert_eq!(extract_domain("alice.com").unwrap(), "alice.com");
+        assert_eq!(extract_domain(" Alice.com ").unwrap(), "alice.com");
+        assert_eq!(extract_domain("https://alice.com").unwrap(), "alice.com");
+        assert_eq!(
+            extract_domain("http://www.alice.com/about?x=1").unwrap(),
+            "www.alice.com"
+        );
+        assert_eq!(extract_domain("alice.com./path").unwrap(), "alice.com");
+        assert!(extract_domain("").is_none());
+        assert!(extract_domain("https://").is_none());
+    }
+
+    #[test]
+    fn reject_internal_addresses() {
+        let public = ["1.1.1.1", "93.184.216.34", "2606:4700:4700::1111"];
+        for addr in public {
+            assert!(is_public_address(&addr.parse().unwrap()), "{}", addr);
+        }
+
+        let internal = [
+            "127.0.0.1",
+            "10.0.0.1",
+            "172.16.5.4",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::ffff:169.254.169.254",
+            "64:ff9b::a9fe:a9fe",
+        ];
+        for addr in internal {
+            assert!(!is_public_address(&addr.parse().unwrap()), "{}", addr);
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use super::JsonResult;
+use crate::database::Database;
+use crate::primitives::{ChainAddress, JudgementAuditEntry};
+use actix::prelude::*;
+use actix_web::{web, HttpResponse};
+
+pub struct JudgementAuditor {
+    db: Database,
+}
+
+impl Default for JudgementAuditor {
+    fn default() -> Self {
+        panic!("JudgementAuditor is not initialized");
+    }
+}
+
+impl JudgementAuditor {
+    pub fn new(db: Database) -> Self {
+        JudgementAuditor { db }
+    }
+}
+
+impl SystemService for JudgementAuditor {}
+impl Supervised for JudgementAuditor {}
+
+impl Actor for JudgementAuditor {
+    type Context = Context<Self>;
+}
+
+impl Handler<FetchJudgementAudit> for JudgementAuditor {
+    type Result = ResponseActFuture<Self, JsonResult<Vec<JudgementAuditEntry>>>;
+
+    fn handle(&mut self, msg: FetchJudgementAudit, _ctx: &mut Self::Context) -> Self::Result {
+        let db = self.db.clone();
+
+        Box::pin(
+            async move {
+                debug!("Received judgement audit request: {:?}", msg);
+                db.fetch_judgement_audit(&msg.address)
+                    .await
+                    .map(JsonResult::Ok)
+                    .map_err(|err| error!("Failed to fetch judgement audit: {:?}", err))
+                    .unwrap_or_else(|_| JsonResult::Err("Backend error, contact admin".to_string()))
+            }
+            .into_actor(self),
+        )
+    }
+}
+
+/// Fetches the judgements issued for the address, oldest first.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Message)]
+#[rtype(result = "JsonResult<Vec<JudgementAuditEntry>>")]
+pub struct FetchJudgementAudit {
+    pub address: ChainAddress,
+}
+
+pub async fn judgement_audit(req: web::Json<FetchJudgementAudit>) -> HttpResponse {
+    HttpResponse::Ok().json(
+        JudgementAuditor::from_registry()
+            .send(req.into_inner())
+            .await
+            .unwrap(),
+    )
+}
@@ -194,24 +194,32 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WsAccountStatusSe
                     return;
                 }
 
-                if let Ok(context) = serde_json::from_slice::<IdentityContext>(msg.as_bytes()) {
-                    // Subscribe the the specified identity context.
-                    LookupServer::from_registry()
-                        .send(SubscribeAccountState {
-                            subscriber: ctx.address().recipient(),
-                            id_context: context,
-                        })
-                        .into_actor(self)
-                        .then(|_, _, _| fut::ready(()))
-                        .wait(ctx);
-                } else {
-                    // Invalid message type, inform caller.
-                    match serde_json::to_string(&JsonResult::<()>::Err(
-                        "Invalid message type".to_string(),
-                    )) {
-                        Ok(m) => ctx.text(m),
-                        Err(err) => {
-                            error!("Failed to serialize WS session message response: {:?}", err)
+                match serde_json::from_slice::<IdentityContext>(msg.as_bytes()) {
+                    Ok(context) => {
+                        // Subscribe the the specified identity context.
+                        LookupServer::from_registry()
+                            .send(SubscribeAccountState {
+                                subscriber: ctx.address().recipient(),
+                                id_context: context,
+                            })
+                            .into_actor(self)
+                            .then(|_, _, _| fut::ready(()))
+                            .wait(ctx);
+                    }
+                    Err(err) => {
+                        // Invalid message type or invalid values (e.g. the
+                        // address), inform caller.
+                        let msg = if err.is_data() {
+                            format!("Invalid message: {}", err)
+                        } else {
+                            "Invalid message type".to_string()
+                        };
+
+                        match serde_json::to_string(&JsonResult::<()>::Err(msg)) {
+                            Ok(m) => ctx.text(m),
+                            Err(err) => {
+                                error!("Failed to serialize WS session message response: {:?}", err)
+                            }
                         }
                     }
                 }
@@ -4,17 +4,36 @@ use crate::{NotifierConfig, Result};
 use actix::prelude::*;
 use actix::registry::SystemRegistry;
 use actix_cors::Cors;
+use actix_web::error::InternalError;
 use actix_web::{web, App, Error as ActixError, HttpRequest, HttpResponse, HttpServer};
 use actix_web_actors::ws;
 use display_name_check::{check_display_name, DisplayNameChecker};
+use judgement_audit::{judgement_audit, JudgementAuditor};
+use ownership_proof::{verify_ownership, OwnershipVerifier};
+use pgp_challenge::{verify_pgp_challenge, PgpChallengeVerifier};
+use refresh_challenge::{refresh_challenge, ChallengeRefresher};
 use second_challenge::{verify_second_challenge, SecondChallengeVerifier};
 
 mod display_name_check;
+mod judgement_audit;
 mod judgement_state;
+mod ownership_proof;
+mod pgp_challenge;
+mod refresh_challenge;
 mod second_challenge;
 
 // Reexport
-pub use self::judgement_state::{LookupServer, NotifyAccountState, ResponseAccountState};
+#[cfg(test)]
+pub use self::judgement_audit::FetchJudgementAudit;
+#[cfg(test)]
+pub use self::judgement_state::ResponseAccountState;
+pub use self::judgement_state::{LookupServer, NotifyAccountState};
+#[cfg(test)]
+pub use self::ownership_proof::{tests::OwnershipSigner, VerifyOwnership};
+#[cfg(test)]
+pub use self::pgp_challenge::{tests::PgpSigner, VerifyPgpChallenge};
+#[cfg(test)]
+pub use self::refresh_challenge::RefreshChallenge;
 pub use self::second_challenge::VerifyChallenge;
 
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, Message)]
@@ -25,6 +44,15 @@ pub enum JsonResult<T> {
     Err(String),
 }
 
+/// Responds with a `JsonResult::Err` to invalid requests (e.g. invalid
+/// addresses), instead of a plain text error.
+fn json_config() -> web::JsonConfig {
+    web::JsonConfig::default().error_handler(|err, _req| {
+        let resp = HttpResponse::BadRequest().json(JsonResult::<()>::Err(err.to_string()));
+        InternalError::from_response(err, resp).into()
+    })
+}
+
 async fn healthcheck() -> HttpResponse {
     HttpResponse::Ok().body("OK")
 }
@@ -37,6 +65,12 @@ pub async fn run_rest_api_server(
     let actor = LookupServer::new(db.clone()).start();
     SystemRegistry::set(actor.clone());
     SystemRegistry::set(SecondChallengeVerifier::new(db.clone()).start());
+    SystemRegistry::set(PgpChallengeVerifier::new(db.clone()).start());
+    SystemRegistry::set(
+        ChallengeRefresher::new(db.clone(), config.unauthenticated_challenge_refresh).start(),
+    );
+    SystemRegistry::set(OwnershipVerifier::new(db.clone()).start());
+    SystemRegistry::set(JudgementAuditor::new(db.clone()).start());
     SystemRegistry::set(DisplayNameChecker::new(db, config.display_name).start());
 
     // Run the WS server.
@@ -45,16 +79,24 @@ pub async fn run_rest_api_server(
 
         App::new()
             .wrap(cors)
+            .app_data(json_config())
             .route("/healthcheck", web::get().to(healthcheck))
             .service(web::resource("/api/account_status").to(account_status_server_route))
             .route(
                 "/api/verify_second_challenge",
                 web::post().to(verify_second_challenge),
             )
+            .route(
+                "/api/verify_pgp_challenge",
+                web::post().to(verify_pgp_challenge),
+            )
             .route(
                 "/api/check_display_name",
                 web::post().to(check_display_name),
             )
+            .route("/api/refresh_challenge", web::post().to(refresh_challenge))
+            .route("/api/verify_ownership", web::post().to(verify_ownership))
+            .route("/api/judgement_audit", web::post().to(judgement_audit))
     })
     .bind(config.api_address.as_str())?;
 
@@ -69,7 +111,7 @@ async fn account_status_server_route(
     req: HttpRequest,
     stream: web::Payload,
 ) -> std::result::Result<HttpResponse, ActixError> {
-    ws::start(WsAccountStatusSession::default(), &req, stream)
+    ws::start(WsAccountStatusSession, &req, stream)
 }
 
 #[cfg(test)]
@@ -97,20 +139,32 @@ pub mod tests {
             // Add configured actor to the registry.
             SystemRegistry::set(t_actor.clone());
             SystemRegistry::set(SecondChallengeVerifier::new(db.clone()).start());
+            SystemRegistry::set(PgpChallengeVerifier::new(db.clone()).start());
+            SystemRegistry::set(ChallengeRefresher::new(db.clone(), true).start());
+            SystemRegistry::set(OwnershipVerifier::new(db.clone()).start());
+            SystemRegistry::set(JudgementAuditor::new(db.clone()).start());
             SystemRegistry::set(
                 DisplayNameChecker::new(db.clone(), DisplayNameConfig::default()).start(),
             );
 
             App::new()
+                .app_data(json_config())
                 .service(web::resource("/api/account_status").to(account_status_server_route))
                 .route(
                     "/api/verify_second_challenge",
                     web::post().to(verify_second_challenge),
                 )
+                .route(
+                    "/api/verify_pgp_challenge",
+                    web::post().to(verify_pgp_challenge),
+                )
                 .route(
                     "/api/check_display_name",
                     web::post().to(check_display_name),
                 )
+                .route("/api/refresh_challenge", web::post().to(refresh_challenge))
+                .route("/api/verify_ownership", web::post().to(verify_ownership))
+                .route("/api/judgement_audit", web::post().to(judgement_audit))
         });
 
         (server, actor)
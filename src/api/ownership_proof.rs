@@ -0,0 +1,169 @@
+use super::JsonResult;
+use crate::database::Database;
+use crate::primitives::IdentityContext;
+use actix::prelude::*;
+use actix_web::{web, HttpResponse};
+
+pub struct OwnershipVerifier {
+    db: Database,
+}
+
+impl Default for OwnershipVerifier {
+    fn default() -> Self {
+        panic!("OwnershipVerifier is not initialized");
+    }
+}
+
+impl OwnershipVerifier {
+    pub fn new(db: Database) -> Self {
+        OwnershipVerifier { db }
+    }
+}
+
+impl SystemService for OwnershipVerifier {}
+impl Supervised for OwnershipVerifier {}
+
+impl Actor for OwnershipVerifier {
+    type Context = Context<Self>;
+}
+
+impl Handler<VerifyOwnership> for OwnershipVerifier {
+    type Result = ResponseActFuture<Self, JsonResult<bool>>;
+
+    fn handle(&mut self, msg: VerifyOwnership, _ctx: &mut Self::Context) -> Self::Result {
+        let db = self.db.clone();
+
+        Box::pin(
+            async move {
+                debug!("Received ownership proof for: {:?}", msg.context);
+                db.verify_ownership(&msg.context, &msg.signature)
+                    .await
+                    .map(JsonResult::Ok)
+                    .map_err(|err| error!("Failed to verify ownership proof: {:?}", err))
+                    .unwrap_or_else(|_| JsonResult::Err("Backend error, contact admin".to_string()))
+            }
+            .into_actor(self),
+        )
+    }
+}
+
+/// The (hex encoded) signature of the ownership nonce, created by the key of
+/// the on-chain account.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Message)]
+#[rtype(result = "JsonResult<bool>")]
+pub struct VerifyOwnership {
+    pub context: IdentityContext,
+    pub signature: String,
+}
+
+pub async fn verify_ownership(req: web::Json<VerifyOwnership>) -> HttpResponse {
+    HttpResponse::Ok().json(
+        OwnershipVerifier::from_registry()
+            .send(req.into_inner())
+            .await
+            .unwrap(),
+    )
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::primitives::{ChainAddress, OwnershipChallenge, OwnershipProofMode};
+    use blake2::{Blake2b512, Digest};
+    use ed25519_dalek::Signer;
+    use schnorrkel::{ExpansionMode, MiniSecretKey};
+    use std::convert::TryFrom;
+
+    pub enum OwnershipSigner {
+        Sr25519(schnorrkel::Keypair),
+        Ed25519(ed25519_dalek::SigningKey),
+    }
+
+    impl OwnershipSigner {
+        pub fn sr25519(seed: u8) -> Self {
+            let keypair = MiniSecretKey::from_bytes(&[seed; 32])
+                .unwrap()
+                .expand_to_keypair(ExpansionMode::Ed25519);
+
+            OwnershipSigner::Sr25519(keypair)
+        }
+        pub fn ed25519(seed: u8) -> Self {
+            OwnershipSigner::Ed25519(ed25519_dalek::SigningKey::from_bytes(&[seed; 32]))
+        }
+        /// The SS58 encoded address (Polkadot prefix).
+        pub fn address(&self) -> ChainAddress {
+            let public_key = match self {
+                OwnershipSigner::Sr25519(keypair) => keypair.public.to_bytes(),
+                OwnershipSigner::Ed25519(key) => key.verifying_key().to_bytes(),
+            };
+
+            let mut payload = vec![0];
+            payload.extend_from_slice(&public_key);
+
+            let hash = Blake2b512::new()
+                .chain_update(b"SS58PRE")
+                .chain_update(&payload)
+                .finalize();
+            payload.extend_from_slice(&hash[..2]);
+
+            ChainAddress::try_from(bs58::encode(payload).into_string()).unwrap()
+        }
+        pub fn sign(&self, message: &str) -> String {
+            let signature = match self {
+                OwnershipSigner::Sr25519(keypair) => keypair
+                    .sign_simple(b"substrate", message.as_bytes())
+                    .to_bytes(),
+                OwnershipSigner::Ed25519(key) => key.sign(message.as_bytes()).to_bytes(),
+            };
+
+            format!("0x{}", hex::encode(signature))
+        }
+    }
+
+    #[test]
+    fn verify_ownership_signature() {
+        let challenge = OwnershipChallenge::new(OwnershipProofMode::BeforeChallenges);
+
+        for signer in &[OwnershipSigner::sr25519(1), OwnershipSigner::ed25519(1)] {
+            let address = signer.address();
+            assert!(address.public_key().is_ok());
+
+            let signature = signer.sign(&challenge.nonce);
+            assert!(challenge.verify_signature(&address, &signature));
+            assert!(challenge.verify_signature(&address, signature.trim_start_matches("0x")));
+
+            // Wrapped nonce.
+            let wrapped = signer.sign(&format!("<Bytes>{}</Bytes>", challenge.nonce));
+            assert!(challenge.verify_signature(&address, &wrapped));
+
+            // Other message.
+            let other = signer.sign("polkadot-registrar:other");
+            assert!(!challenge.verify_signature(&address, &other));
+
+            // Invalid encoding.
+            assert!(!challenge.verify_signature(&address, "0xnothex"));
+        }
+
+        // Signed by another key.
+        let address = OwnershipSigner::sr25519(1).address();
+        let signature = OwnershipSigner::sr25519(2).sign(&challenge.nonce);
+        assert!(!challenge.verify_signature(&address, &signature));
+
+        let address = OwnershipSigner::ed25519(1).address();
+        let signature = OwnershipSigner::ed25519(2).sign(&challenge.nonce);
+        assert!(!challenge.verify_signature(&address, &signature));
+    }
+
+    #[test]
+    fn decode_address_checksum() {
+        let address = OwnershipSigner::sr25519(1).address();
+        assert!(address.public_key().is_ok());
+
+        // Modify the last character, which invalidates the checksum.
+        let mut modified = address.as_str().to_string();
+        let last = modified.pop().unwrap();
+        modified.push(if last == '1' { '2' } else { '1' });
+        assert!(ChainAddress::from(modified.as_str()).public_key().is_err());
+
+        assert!(ChainAddress::from("invalid").public_key().is_err());
+    }
+}
@@ -0,0 +1,353 @@
+use super::JsonResult;
+use crate::database::Database;
+use crate::primitives::{
+    normalize_fingerprint, ExternalMessage, ExternalMessageType, IdentityFieldValue, Timestamp,
+};
+use crate::Result;
+use actix::prelude::*;
+use actix_web::{web, HttpResponse};
+use pgp::line_writer::LineBreak;
+use pgp::normalize_lines::Normalized;
+use pgp::packet::SignatureType;
+use pgp::types::KeyTrait;
+use pgp::{Deserializable, SignedPublicKey, StandaloneSignature};
+
+const CLEARTEXT_HEADER: &str = "-----BEGIN PGP SIGNED MESSAGE-----";
+const SIGNATURE_HEADER: &str = "-----BEGIN PGP SIGNATURE-----";
+
+pub struct PgpChallengeVerifier {
+    db: Database,
+}
+
+impl Default for PgpChallengeVerifier {
+    fn default() -> Self {
+        panic!("PgpChallengeVerifier is not initialized");
+    }
+}
+
+impl PgpChallengeVerifier {
+    pub fn new(db: Database) -> Self {
+        PgpChallengeVerifier { db }
+    }
+}
+
+impl SystemService for PgpChallengeVerifier {}
+impl Supervised for PgpChallengeVerifier {}
+
+impl Actor for PgpChallengeVerifier {
+    type Context = Context<Self>;
+}
+
+impl Handler<VerifyPgpChallenge> for PgpChallengeVerifier {
+    type Result = ResponseActFuture<Self, JsonResult<bool>>;
+
+    fn handle(&mut self, msg: VerifyPgpChallenge, _ctx: &mut Self::Context) -> Self::Result {
+        let db = self.db.clone();
+
+        Box::pin(
+            async move {
+                debug!("Received PGP challenge for: {:?}", msg.entry);
+
+                let fingerprint = match &msg.entry {
+                    IdentityFieldValue::PGPFingerprint(fingerprint) => fingerprint.clone(),
+                    _ => return JsonResult::Err("Entry is not a PGP fingerprint".to_string()),
+                };
+
+                let signed = match msg.verify_signature(&fingerprint) {
+                    Ok(signed) => signed,
+                    Err(err) => {
+                        debug!("Invalid PGP challenge for {}: {:?}", fingerprint, err);
+                        return JsonResult::Err("Invalid public key or signature".to_string());
+                    }
+                };
+
+                db.verify_message(&ExternalMessage {
+                    origin: ExternalMessageType::PGPFingerprint(fingerprint),
+                    // Not relevant, the message is not fetched from a remote source.
+                    id: 0u32.into(),
+                    timestamp: Timestamp::now(),
+                    values: vec![signed.into()],
+                })
+                .await
+                .map(JsonResult::Ok)
+                .map_err(|err| error!("Failed to verify PGP challenge: {:?}", err))
+                .unwrap_or_else(|_| JsonResult::Err("Backend error, contact admin".to_string()))
+            }
+            .into_actor(self),
+        )
+    }
+}
+
+/// A copy of the expected message signed by the key of the PGP fingerprint.
+/// If `message` is specified, `signature` is a detached signature of it,
+/// otherwise `signature` is a clearsigned message.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Message)]
+#[rtype(result = "JsonResult<bool>")]
+pub struct VerifyPgpChallenge {
+    pub entry: IdentityFieldValue,
+    pub public_key: String,
+    pub signature: String,
+    pub message: Option<String>,
+}
+
+impl VerifyPgpChallenge {
+    /// Verifies the signature with the (armored) public key and returns the
+    /// signed text.
+    fn verify_signature(&self, fingerprint: &str) -> Result<String> {
+        let (key, _) = SignedPublicKey::from_string(self.public_key.trim())?;
+        key.verify()?;
+
+        if hex::encode_upper(key.fingerprint()) != normalize_fingerprint(fingerprint) {
+            return Err(anyhow!("public key does not match fingerprint"));
+        }
+
+        let (text, signature) = match &self.message {
+            Some(message) => (message.clone(), self.signature.trim().to_string()),
+            None => parse_cleartext(&self.signature)?,
+        };
+
+        let (signature, _) = StandaloneSignature::from_string(&signature)?;
+        let content = match signature.signature.typ() {
+            SignatureType::Binary => text.as_bytes().to_vec(),
+            SignatureType::Text => Normalized::new(text.bytes(), LineBreak::Crlf).collect(),
+            typ => return Err(anyhow!("unsupported signature type: {:?}", typ)),
+        };
+
+        // The message might have been signed by a subkey, but only by one
+        // which is flagged for signing (not e.g. an encryption subkey).
+        let verified = signature.verify(&key, &content).is_ok()
+            || key
+                .public_subkeys
+                .iter()
+                .filter(|subkey| subkey.signatures.iter().any(|sig| sig.key_flags().sign()))
+                .any(|subkey| signature.verify(subkey, &content).is_ok());
+
+        if verified {
+            Ok(text)
+        } else {
+            Err(anyhow!("invalid signature"))
+        }
+    }
+}
+
+/// Splits a clearsigned message into its (dash-unescaped) text and the
+/// armored signature.
+fn parse_cleartext(input: &str) -> Result<(String, String)> {
+    let input = input
+        .trim()
+        .strip_prefix(CLEARTEXT_HEADER)
+        .ok_or_else(|| anyhow!("not a clearsigned message"))?;
+
+    let (body, signature) = input
+        .split_once(&format!("\n{}", SIGNATURE_HEADER))
+        .ok_or_else(|| anyhow!("no signature in clearsigned message"))?;
+
+    // Skip the rest of the header line as well as the armor headers
+    // ("Hash: ..."), which are terminated by an empty line.
+    let mut lines = body.lines().skip(1);
+    for line in lines.by_ref() {
+        if line.trim().is_empty() {
+            break;
+        }
+    }
+
+    // Trailing whitespace is not part of the signed text.
+    let text = lines
+        .map(|line| line.strip_prefix("- ").unwrap_or(line).trim_end())
+        .collect::<Vec<&str>>()
+        .join("\r\n");
+
+    Ok((text, format!("{}{}", SIGNATURE_HEADER, signature)))
+}
+
+pub async fn verify_pgp_challenge(req: web::Json<VerifyPgpChallenge>) -> HttpResponse {
+    HttpResponse::Ok().json(
+        PgpChallengeVerifier::from_registry()
+            .send(req.into_inner())
+            .await
+            .unwrap(),
+    )
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use pgp::crypto::hash::HashAlgorithm;
+    use pgp::packet::{SignatureConfig, SignatureVersion, Subpacket};
+    use pgp::types::SecretKeyTrait;
+    use pgp::{KeyType, SecretKeyParamsBuilder, SignedSecretKey, SubkeyParamsBuilder};
+
+    pub struct PgpSigner {
+        key: SignedSecretKey,
+        // Sign with the subkey instead of the primary key.
+        use_subkey: bool,
+    }
+
+    impl PgpSigner {
+        pub fn new() -> Self {
+            Self::generate(false, false)
+        }
+        /// Signs with a subkey, which is only flagged for signing if
+        /// `can_sign` is set.
+        pub fn with_subkey(can_sign: bool) -> Self {
+            Self::generate(true, can_sign)
+        }
+        fn generate(use_subkey: bool, subkey_can_sign: bool) -> Self {
+            let key = SecretKeyParamsBuilder::default()
+                .key_type(KeyType::EdDSA)
+                .can_sign(true)
+                .primary_user_id("Alice <alice@email.com>".into())
+                .subkey(
+                    SubkeyParamsBuilder::default()
+                        .key_type(KeyType::EdDSA)
+                        .can_sign(subkey_can_sign)
+                        .build()
+                        .unwrap(),
+                )
+                .build()
+                .unwrap()
+                .generate()
+                .unwrap()
+                .sign(String::new)
+                .unwrap();
+
+            PgpSigner { key, use_subkey }
+        }
+        pub fn fingerprint(&self) -> String {
+            hex::encode(self.key.fingerprint())
+        }
+        pub fn public_key(&self) -> String {
+            self.key
+                .public_key()
+                .sign(&self.key, String::new)
+                .unwrap()
+                .to_armored_string(None)
+                .unwrap()
+        }
+        fn sign(&self, typ: SignatureType, content: &[u8]) -> String {
+            if self.use_subkey {
+                sign_with(&self.key.secret_subkeys[0], typ, content)
+            } else {
+                sign_with(&self.key, typ, content)
+            }
+        }
+        pub fn detached(&self, message: &str) -> String {
+            self.sign(SignatureType::Binary, message.as_bytes())
+        }
+        pub fn clearsigned(&self, message: &str) -> String {
+            let signature = self.sign(SignatureType::Text, message.as_bytes());
+            format!(
+                "{}\nHash: SHA256\n\n{}\n{}",
+                CLEARTEXT_HEADER, message, signature
+            )
+        }
+    }
+
+    fn sign_with(key: &impl SecretKeyTrait, typ: SignatureType, content: &[u8]) -> String {
+        let config = SignatureConfig::new_v4(
+            SignatureVersion::V4,
+            typ,
+            key.algorithm(),
+            HashAlgorithm::SHA2_256,
+            vec![Subpacket::Issuer(key.key_id())],
+            vec![],
+        );
+
+        StandaloneSignature::new(config.sign(key, String::new, content).unwrap())
+            .to_armored_string(None)
+            .unwrap()
+    }
+
+    fn request(
+        signer: &PgpSigner,
+        signature: String,
+        message: Option<String>,
+    ) -> VerifyPgpChallenge {
+        VerifyPgpChallenge {
+            entry: IdentityFieldValue::PGPFingerprint(signer.fingerprint()),
+            public_key: signer.public_key(),
+            signature,
+            message,
+        }
+    }
+
+    #[test]
+    fn verify_clearsigned_challenge() {
+        let signer = PgpSigner::new();
+        let fingerprint = signer.fingerprint();
+
+        let req = request(&signer, signer.clearsigned("challenge"), None);
+        assert_eq!(req.verify_signature(&fingerprint).unwrap(), "challenge");
+
+        // Fingerprint with a different format.
+        let formatted = format!("0x{}", fingerprint.to_uppercase());
+        assert_eq!(req.verify_signature(&formatted).unwrap(), "challenge");
+
+        // Other fingerprint.
+        assert!(req
+            .verify_signature("0000000000000000000000000000000000000000")
+            .is_err());
+
+        // Modified text.
+        let modified = signer
+            .clearsigned("challenge")
+            .replace("\nchallenge\n", "\nchallenge2\n");
+        let req = request(&signer, modified, None);
+        assert!(req.verify_signature(&fingerprint).is_err());
+
+        // Signed by another key.
+        let other = PgpSigner::new();
+        let req = request(&signer, other.clearsigned("challenge"), None);
+        assert!(req.verify_signature(&fingerprint).is_err());
+    }
+
+    #[test]
+    fn verify_detached_challenge() {
+        let signer = PgpSigner::new();
+        let fingerprint = signer.fingerprint();
+
+        let req = request(
+            &signer,
+            signer.detached("challenge"),
+            Some("challenge".to_string()),
+        );
+        assert_eq!(req.verify_signature(&fingerprint).unwrap(), "challenge");
+
+        // Modified text.
+        let req = request(
+            &signer,
+            signer.detached("challenge"),
+            Some("challenge2".to_string()),
+        );
+        assert!(req.verify_signature(&fingerprint).is_err());
+    }
+
+    #[test]
+    fn verify_subkey_signature() {
+        // Subkey flagged for signing.
+        let signer = PgpSigner::with_subkey(true);
+        let req = request(&signer, signer.clearsigned("challenge"), None);
+        assert_eq!(
+            req.verify_signature(&signer.fingerprint()).unwrap(),
+            "challenge"
+        );
+
+        // Subkey which is not flagged for signing.
+        let signer = PgpSigner::with_subkey(false);
+        let req = request(&signer, signer.clearsigned("challenge"), None);
+        assert!(req.verify_signature(&signer.fingerprint()).is_err());
+    }
+
+    #[test]
+    fn parse_cleartext_message() {
+        let input = format!(
+            "{}\r\nHash: SHA256\r\n\r\n- -dashed\r\ntrailing  \r\n{}\r\nabc\r\n-----END PGP SIGNATURE-----\r\n",
+            CLEARTEXT_HEADER, SIGNATURE_HEADER
+        );
+
+        let (text, signature) = parse_cleartext(&input).unwrap();
+        assert_eq!(text, "-dashed\r\ntrailing");
+        assert!(signature.starts_with(SIGNATURE_HEADER));
+        assert!(signature.ends_with("-----END PGP SIGNATURE-----"));
+    }
+}
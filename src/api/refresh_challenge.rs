@@ -0,0 +1,83 @@
+use super::JsonResult;
+use crate::database::Database;
+use crate::primitives::{IdentityContext, IdentityFieldValue};
+use actix::prelude::*;
+use actix_web::{web, HttpResponse};
+
+pub struct ChallengeRefresher {
+    db: Database,
+    // Whether identities without an ownership proof can be refreshed.
+    allow_unauthenticated: bool,
+}
+
+impl Default for ChallengeRefresher {
+    fn default() -> Self {
+        panic!("ChallengeRefresher is not initialized");
+    }
+}
+
+impl ChallengeRefresher {
+    pub fn new(db: Database, allow_unauthenticated: bool) -> Self {
+        ChallengeRefresher {
+            db,
+            allow_unauthenticated,
+        }
+    }
+}
+
+impl SystemService for ChallengeRefresher {}
+impl Supervised for ChallengeRefresher {}
+
+impl Actor for ChallengeRefresher {
+    type Context = Context<Self>;
+}
+
+impl Handler<RefreshChallenge> for ChallengeRefresher {
+    type Result = ResponseActFuture<Self, JsonResult<bool>>;
+
+    fn handle(&mut self, msg: RefreshChallenge, _ctx: &mut Self::Context) -> Self::Result {
+        let db = self.db.clone();
+        let allow_unauthenticated = self.allow_unauthenticated;
+
+        Box::pin(
+            async move {
+                debug!("Received challenge refresh request: {:?}", msg);
+                db.refresh_challenge(
+                    &msg.context,
+                    &msg.entry,
+                    msg.signature.as_deref(),
+                    allow_unauthenticated,
+                )
+                .await
+                .map(JsonResult::Ok)
+                .map_err(|err| error!("Failed to refresh challenge: {:?}", err))
+                .unwrap_or_else(|_| JsonResult::Err("Backend error, contact admin".to_string()))
+            }
+            .into_actor(self),
+        )
+    }
+}
+
+/// Replaces the unverified challenges of the field with new ones. Returns
+/// `false` if there was nothing to replace, if the current challenge was
+/// issued too recently or if the identity has no ownership proof (unless
+/// explicitly allowed).
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Message)]
+#[rtype(result = "JsonResult<bool>")]
+pub struct RefreshChallenge {
+    pub context: IdentityContext,
+    pub entry: IdentityFieldValue,
+    // The (hex encoded) signature of the ownership nonce, required if the
+    // identity requires an ownership proof.
+    #[serde(default)]
+    pub signature: Option<String>,
+}
+
+pub async fn refresh_challenge(req: web::Json<RefreshChallenge>) -> HttpResponse {
+    HttpResponse::Ok().json(
+        ChallengeRefresher::from_registry()
+            .send(req.into_inner())
+            .await
+            .unwrap(),
+    )
+}
@@ -1,4 +1,4 @@
-use system::{run, Result};
+use system::{run, run_mock_watcher, Result};
 use tracing::Level;
 
 #[actix::main]
@@ -8,6 +8,14 @@ async fn main() -> Result<()> {
         .with_env_filter("system")
         .init();
 
+    // Optionally runs a mock Watcher for local development, the (optional)
+    // second argument is the path to the script.
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() == Some("mock-watcher") {
+        tracing::info!("Starting mock Watcher");
+        return run_mock_watcher(args.next()).await;
+    }
+
     tracing::info!("Starting registrar service");
 
     run().await?;
@@ -1,8 +1,12 @@
 use crate::display_name::DisplayNameVerifier;
 use crate::primitives::{
-    ChainAddress, ChainName, IdentityContext, IdentityFieldValue, JudgementState, Timestamp,
+    ChainAddress, IdentityContext, IdentityFieldValue, JudgementState, OwnershipChallenge,
+    OwnershipProofMode, Timestamp,
+};
+use crate::{
+    ChallengeFormatConfig, Database, DisplayNameConfig, Result, SecondChallengeConfig,
+    WatcherConfig,
 };
-use crate::{Database, DisplayNameConfig, Result, WatcherConfig};
 use actix::io::SinkWrite;
 use actix::io::WriteHandler;
 use actix::prelude::*;
@@ -29,7 +33,11 @@ const PENDING_JUDGEMENTS_INTERVAL: u64 = 30;
 const DISPLAY_NAMES_INTERVAL: u64 = 30;
 #[cfg(not(test))]
 const JUDGEMENT_CANDIDATES_INTERVAL: u64 = 10;
-const RECONNECTION_TIMEOUT: u64 = 10;
+const CHALLENGE_EXPIRY_INTERVAL: u64 = 60;
+#[cfg(not(test))]
+const ENDPOINT_BACKOFF: u64 = 10;
+const ENDPOINT_MAX_BACKOFF: u64 = 600;
+const FAILBACK_INTERVAL: u64 = 60;
 
 #[cfg(test)]
 const PENDING_JUDGEMENTS_INTERVAL: u64 = 1;
@@ -37,31 +45,68 @@ const PENDING_JUDGEMENTS_INTERVAL: u64 = 1;
 const DISPLAY_NAMES_INTERVAL: u64 = 1;
 #[cfg(test)]
 const JUDGEMENT_CANDIDATES_INTERVAL: u64 = 1;
+#[cfg(test)]
+const ENDPOINT_BACKOFF: u64 = 1;
+
+// The version of the Watcher protocol implemented by the challenger. Watchers
+// which do not respond to the handshake are treated as the legacy version,
+// which only supports untyped acknowledgements.
+const PROTOCOL_VERSION: u32 = 2;
+const LEGACY_PROTOCOL_VERSION: u32 = 1;
 
 pub async fn run_connector(
     db: Database,
     watchers: Vec<WatcherConfig>,
     dn_config: DisplayNameConfig,
+    second_challenge: SecondChallengeConfig,
+    challenge_formats: ChallengeFormatConfig,
+    challenge_ttl: Option<u64>,
 ) -> Result<()> {
     if watchers.is_empty() {
         warn!("No watcher is configured. Cannot process any requests or issue judgments");
         return Ok(());
     }
 
+    for (idx, config) in watchers.iter().enumerate() {
+        if watchers[..idx].iter().any(|w| w.network == config.network) {
+            return Err(anyhow!(
+                "network {} is configured more than once",
+                config.network.as_str()
+            ));
+        }
+
+        if config.endpoints.is_empty() {
+            return Err(anyhow!(
+                "no watcher endpoint is configured for network {}",
+                config.network.as_str()
+            ));
+        }
+    }
+
     for config in watchers {
         let span = info_span!("connector_initialization");
         span.in_scope(|| {
             debug!(
                 network = config.network.as_str(),
-                endpoint = config.endpoint.as_str()
+                ss58_prefix = config.ss58_prefix,
+                endpoints = ?config.endpoints
             );
         });
 
         async {
             // Start Connector.
             let dn_verifier = DisplayNameVerifier::new(db.clone(), dn_config.clone());
-            let conn =
-                Connector::start(config.endpoint, config.network, db.clone(), dn_verifier).await?;
+            let mut endpoints = WatcherEndpoints::new(&config.endpoints);
+            let conn = Connector::start(
+                config,
+                &mut endpoints,
+                db.clone(),
+                dn_verifier,
+                second_challenge.clone(),
+                challenge_formats.clone(),
+                challenge_ttl,
+            )
+            .await?;
 
             info!("Connection initiated");
             info!("Sending pending judgements request to Watcher");
@@ -100,6 +145,67 @@ pub enum EventType {
     DisplayNamesRequest,
     #[serde(rename = "displayNamesResponse")]
     DisplayNamesResponse,
+    #[serde(rename = "handshake")]
+    Handshake,
+    #[serde(rename = "handshakeResponse")]
+    HandshakeResponse,
+    /// Events introduced by newer Watchers, which are ignored.
+    #[serde(other)]
+    Unknown,
+}
+
+/// Exchanged on connect, the Watcher responds with the version and the
+/// capabilities it supports.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Handshake {
+    pub version: u32,
+    #[serde(default)]
+    pub capabilities: Vec<Capability>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Capability {
+    /// Acknowledgements and errors contain a result code.
+    #[serde(rename = "typedAcks")]
+    TypedAcks,
+    /// Judgements contain a correlation ID, which is returned in the
+    /// acknowledgement or error.
+    #[serde(rename = "correlationIds")]
+    CorrelationIds,
+    #[serde(other)]
+    Unknown,
+}
+
+impl Handshake {
+    /// The protocol supported by the challenger.
+    pub fn supported() -> Self {
+        Handshake {
+            version: PROTOCOL_VERSION,
+            capabilities: vec![Capability::TypedAcks, Capability::CorrelationIds],
+        }
+    }
+    /// The protocol of Watchers which do not respond to the handshake.
+    pub fn legacy() -> Self {
+        Handshake {
+            version: LEGACY_PROTOCOL_VERSION,
+            capabilities: vec![],
+        }
+    }
+    /// The protocol supported by both sides.
+    pub fn negotiate(&self, other: &Handshake) -> Self {
+        Handshake {
+            version: self.version.min(other.version),
+            capabilities: self
+                .capabilities
+                .iter()
+                .filter(|cap| **cap != Capability::Unknown && other.supports(**cap))
+                .copied()
+                .collect(),
+        }
+    }
+    pub fn supports(&self, capability: Capability) -> bool {
+        self.capabilities.contains(&capability)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -107,9 +213,16 @@ pub struct JudgementResponse {
     pub address: ChainAddress,
     pub judgement: Judgement,
     pub verified: Vec<VerifiedEntry>,
+    // Only sent if supported by the Watcher.
+    #[serde(
+        default,
+        rename = "correlationId",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub correlation_id: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct VerifiedEntry {
     #[serde(rename = "accountTy")]
     pub account_ty: AccountType,
@@ -118,24 +231,101 @@ pub struct VerifiedEntry {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AckResponse {
+    // Free text, the only indicator of legacy Watchers.
+    #[serde(default)]
     result: String,
+    #[serde(default)]
+    code: Option<AckCode>,
     address: Option<ChainAddress>,
+    #[serde(default, rename = "correlationId")]
+    correlation_id: Option<String>,
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum AckCode {
+    #[serde(rename = "judgementGiven")]
+    JudgementGiven,
+    #[serde(other)]
+    Unknown,
+}
+
+impl AckResponse {
+    /// The acknowledgement sent by the Watcher once the judgement was given
+    /// on-chain.
+    pub fn judgement_given(address: ChainAddress, correlation_id: Option<String>) -> Self {
+        AckResponse {
+            result: "judgement given".to_string(),
+            code: Some(AckCode::JudgementGiven),
+            address: Some(address),
+            correlation_id,
+        }
+    }
+    /// Whether the judgement was given on-chain.
+    fn is_judgement_given(&self) -> bool {
+        match self.code {
+            Some(code) => code == AckCode::JudgementGiven,
+            None => self.result.to_lowercase().contains("judgement given"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    #[serde(default)]
+    code: Option<String>,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    address: Option<ChainAddress>,
+    #[serde(default, rename = "correlationId")]
+    correlation_id: Option<String>,
+}
+
+/// The on-chain judgements a registrar can provide (`FeePaid` is reserved by
+/// the chain itself).
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
 pub enum Judgement {
+    #[serde(rename = "unknown")]
+    Unknown,
     #[serde(rename = "reasonable")]
     Reasonable,
+    #[serde(rename = "knownGood")]
+    KnownGood,
+    #[serde(rename = "outOfDate")]
+    OutOfDate,
+    #[serde(rename = "lowQuality")]
+    LowQuality,
     #[serde(rename = "erroneous")]
     Erroneous,
 }
 
+impl Judgement {
+    /// Whether the judgement flags the identity as invalid.
+    pub fn is_negative(&self) -> bool {
+        matches!(
+            self,
+            Judgement::OutOfDate | Judgement::LowQuality | Judgement::Erroneous
+        )
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JudgementRequest {
     pub address: ChainAddress,
     pub accounts: HashMap<AccountType, String>,
 }
 
+impl JudgementRequest {
+    /// Whether the address was encoded for the network with the given SS58
+    /// prefix.
+    fn matches_prefix(&self, ss58_prefix: u16) -> bool {
+        self.address
+            .ss58_prefix()
+            .map(|prefix| prefix == ss58_prefix)
+            .unwrap_or(false)
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct DisplayNameEntry {
     pub context: IdentityContext,
@@ -169,10 +359,30 @@ fn try_decode_hex(display_name: &mut String) {
     }
 }
 
+/// Parses each entry of a batch sent by the Watcher individually. Invalid
+/// entries (e.g. with a malformed address) are skipped, so those do not
+/// prevent the rest of the batch from being processed.
+fn parse_batch<T: serde::de::DeserializeOwned>(data: serde_json::Value) -> Result<Vec<T>> {
+    let entries: Vec<serde_json::Value> = serde_json::from_value(data)?;
+
+    Ok(entries
+        .into_iter()
+        .filter_map(|entry| match serde_json::from_value(entry.clone()) {
+            Ok(parsed) => Some(parsed),
+            Err(err) => {
+                warn!("Skipping invalid entry from Watcher {}: {}", entry, err);
+                None
+            }
+        })
+        .collect())
+}
+
 #[derive(Debug, Clone, Message)]
 #[rtype(result = "crate::Result<()>")]
 pub enum WatcherMessage {
+    Handshake(Handshake),
     Ack(AckResponse),
+    Error(ErrorResponse),
     NewJudgementRequest(JudgementRequest),
     PendingJudgementsRequests(Vec<JudgementRequest>),
     ActiveDisplayNames(Vec<DisplayNameEntryRaw>),
@@ -181,7 +391,9 @@ pub enum WatcherMessage {
 #[derive(Debug, Clone, Message)]
 #[rtype(result = "crate::Result<()>")]
 pub enum ClientCommand {
-    ProvideJudgement(JudgementState),
+    Handshake,
+    // The state and the correlation ID of the submission.
+    ProvideJudgement(Box<JudgementState>, String),
     RequestPendingJudgements,
     RequestDisplayNames,
 }
@@ -192,34 +404,140 @@ struct Connector {
     sink: Option<SinkWrite<Message, SplitSink<Framed<BoxedSocket, Codec>, Message>>>,
     db: Database,
     dn_verifier: DisplayNameVerifier,
-    endpoint: String,
-    network: ChainName,
+    second_challenge: SecondChallengeConfig,
+    challenge_formats: ChallengeFormatConfig,
+    challenge_ttl: Option<u64>,
+    watcher: WatcherConfig,
+    // The health of the configured endpoints and the index of the one this
+    // connection uses.
+    endpoints: WatcherEndpoints,
+    active: usize,
+    // Set if the connection is replaced by one to a preferred endpoint.
+    failing_back: bool,
     outgoing: UnboundedSender<ClientCommand>,
     inserted_states: Arc<RwLock<Vec<JudgementState>>>,
     // Tracks the last message received from the Watcher. If a certain treshold
     // was exceeded, the Connector attempts to reconnect.
     last_watcher_msg: Timestamp,
+    // The negotiated protocol, legacy until the Watcher responds to the
+    // handshake.
+    protocol: Handshake,
+}
+
+/// The endpoints of a Watcher, ordered by priority, and their health.
+#[derive(Debug, Clone)]
+struct WatcherEndpoints {
+    endpoints: Vec<EndpointHealth>,
+}
+
+#[derive(Debug, Clone)]
+struct EndpointHealth {
+    url: String,
+    // Consecutive failures, reset on a successful connection.
+    failures: u32,
+    // The endpoint is skipped until then.
+    next_attempt: Timestamp,
+}
+
+impl WatcherEndpoints {
+    fn new(urls: &[String]) -> Self {
+        WatcherEndpoints {
+            endpoints: urls
+                .iter()
+                .map(|url| EndpointHealth {
+                    url: url.clone(),
+                    failures: 0,
+                    next_attempt: Timestamp::now(),
+                })
+                .collect(),
+        }
+    }
+    fn url(&self, idx: usize) -> &str {
+        self.endpoints
+            .get(idx)
+            .map(|endpoint| endpoint.url.as_str())
+            .unwrap_or_default()
+    }
+    fn is_available(&self, idx: usize) -> bool {
+        self.endpoints[idx].next_attempt.raw() <= Timestamp::now().raw()
+    }
+    fn record_success(&mut self, idx: usize) {
+        if let Some(endpoint) = self.endpoints.get_mut(idx) {
+            endpoint.failures = 0;
+            endpoint.next_attempt = Timestamp::now();
+        }
+    }
+    fn record_failure(&mut self, idx: usize) {
+        if let Some(endpoint) = self.endpoints.get_mut(idx) {
+            endpoint.failures += 1;
+            endpoint.next_attempt = Timestamp::with_offset(endpoint_backoff(endpoint.failures));
+        }
+    }
+    /// Seconds until the next endpoint is available.
+    fn next_retry(&self) -> u64 {
+        let now = Timestamp::now().raw();
+
+        self.endpoints
+            .iter()
+            .map(|endpoint| endpoint.next_attempt.raw().saturating_sub(now))
+            .min()
+            .unwrap_or(ENDPOINT_BACKOFF)
+            .max(1)
+    }
+    /// Connects to the available endpoint with the highest priority, skipping
+    /// those which are backing off.
+    async fn connect(&mut self) -> Result<(usize, Framed<BoxedSocket, Codec>)> {
+        for idx in 0..self.endpoints.len() {
+            if !self.is_available(idx) {
+                continue;
+            }
+
+            match connect_endpoint(self.url(idx)).await {
+                Ok(framed) => {
+                    self.record_success(idx);
+                    return Ok((idx, framed));
+                }
+                Err(err) => {
+                    warn!("{:?}", err);
+                    self.record_failure(idx);
+                }
+            }
+        }
+
+        Err(anyhow!("no Watcher endpoint is reachable"))
+    }
+}
+
+// The delay doubles with each consecutive failure.
+fn endpoint_backoff(failures: u32) -> u64 {
+    ENDPOINT_BACKOFF
+        .saturating_mul(2u64.saturating_pow(failures.saturating_sub(1)))
+        .min(ENDPOINT_MAX_BACKOFF)
+}
+
+async fn connect_endpoint(url: &str) -> Result<Framed<BoxedSocket, Codec>> {
+    let (_, framed) = Client::new()
+        .ws(url)
+        .max_frame_size(5_000_000)
+        .connect()
+        .await
+        .map_err(|err| anyhow!("failed to initiate client connector to {}: {:?}", url, err))?;
+
+    Ok(framed)
 }
 
 impl Connector {
     async fn start(
-        endpoint: String,
-        network: ChainName,
+        watcher: WatcherConfig,
+        endpoints: &mut WatcherEndpoints,
         db: Database,
         dn_verifier: DisplayNameVerifier,
+        second_challenge: SecondChallengeConfig,
+        challenge_formats: ChallengeFormatConfig,
+        challenge_ttl: Option<u64>,
     ) -> Result<Addr<Connector>> {
-        let (_, framed) = Client::new()
-            .ws(&endpoint)
-            .max_frame_size(5_000_000)
-            .connect()
-            .await
-            .map_err(|err| {
-                anyhow!(
-                    "failed to initiate client connector to {}: {:?}",
-                    endpoint,
-                    err
-                )
-            })?;
+        let (active, framed) = endpoints.connect().await?;
+        let endpoints = endpoints.clone();
 
         // Create throw-away channels (`outgoing` in `Connector` is only used in tests.)
         let (outgoing, _recv) = mpsc::unbounded_channel();
@@ -232,32 +550,105 @@ impl Connector {
                 sink: Some(SinkWrite::new(sink, ctx)),
                 db,
                 dn_verifier,
-                endpoint,
-                network,
+                second_challenge,
+                challenge_formats,
+                challenge_ttl,
+                watcher,
+                endpoints,
+                active,
+                failing_back: false,
                 outgoing,
                 inserted_states: Default::default(),
                 last_watcher_msg: Timestamp::now(),
+                protocol: Handshake::legacy(),
             }
         });
 
         Ok(actor)
     }
-    // Process any tangling submissions, meaning any verified requests that were
-    // submitted to the Watcher but the issued extrinsic was not direclty
-    // confirmed back. This usually does not happen, but can.
-    fn start_dangling_judgements_task(&self, ctx: &mut Context<Self>) {
-        info!("Starting dangling judgement pruning task");
+    fn endpoint(&self) -> &str {
+        self.endpoints.url(self.active)
+    }
+    // Check whether a preferred endpoint recovered and, if so, replace the
+    // connection.
+    fn start_failback_task(&self, ctx: &mut Context<Self>) {
+        if self.endpoints.endpoints.len() < 2 {
+            return;
+        }
+
+        info!("Starting endpoint failback background task");
 
-        ctx.run_interval(Duration::new(60, 0), |act, _ctx| {
-            let db = act.db.clone();
+        ctx.run_interval(Duration::new(FAILBACK_INTERVAL, 0), |act, ctx| {
+            let preferred: Vec<(usize, String)> = (0..act.active)
+                .filter(|idx| act.endpoints.is_available(*idx))
+                .map(|idx| (idx, act.endpoints.url(idx).to_string()))
+                .collect();
 
-            actix::spawn(async move {
-                if let Err(err) = db.process_dangling_judgement_states().await {
-                    error!("Error when pruning dangling judgements: {:?}", err);
+            if preferred.is_empty() {
+                return;
+            }
+
+            ctx.spawn(
+                async move {
+                    let mut failed = vec![];
+                    for (idx, url) in preferred {
+                        // The probe connection is dropped right away, nothing
+                        // is submitted over it.
+                        if connect_endpoint(&url).await.is_ok() {
+                            return (Some(idx), failed);
+                        }
+
+                        failed.push(idx);
+                    }
+
+                    (None, failed)
                 }
-            });
+                .into_actor(act)
+                .map(|(recovered, failed), act, ctx| {
+                    for idx in failed {
+                        act.endpoints.record_failure(idx);
+                    }
+
+                    if let Some(idx) = recovered {
+                        info!(
+                            "Watcher endpoint {} recovered, failing back",
+                            act.endpoints.url(idx)
+                        );
+                        act.failing_back = true;
+                        ctx.stop();
+                    }
+                }),
+            );
         });
     }
+    // Replace the challenges which exceeded the configured time-to-live, so
+    // challenges that leaked at some point cannot be used forever.
+    fn start_challenge_expiry_task(&self, ctx: &mut Context<Self>) {
+        let ttl = match self.challenge_ttl {
+            Some(ttl) => ttl,
+            None => return,
+        };
+
+        info!("Starting challenge expiry background task");
+
+        ctx.run_interval(
+            Duration::new(CHALLENGE_EXPIRY_INTERVAL, 0),
+            move |act, _ctx| {
+                let db = act.db.clone();
+                let network = act.watcher.network.clone();
+
+                actix::spawn(async move {
+                    match db.regenerate_expired_challenges(&network, ttl).await {
+                        Ok(count) if count > 0 => {
+                            debug!("Regenerated {} expired challenges", count)
+                        }
+                        Ok(_) => {}
+                        Err(err) => error!("Error when regenerating challenges: {:?}", err),
+                    }
+                });
+            },
+        );
+    }
     // Request pending judgements every couple of seconds.
     fn start_pending_judgements_task(&self, ctx: &mut Context<Self>) {
         info!("Starting pending judgement requester background task");
@@ -278,27 +669,48 @@ impl Connector {
             ctx.address().do_send(ClientCommand::RequestDisplayNames)
         });
     }
-    // Look for verified identities and submit those to the Watcher.
+    // Look for verified identities and submit those to the Watcher. Those are
+    // resent (with a backoff) until the Watcher confirms the judgement.
     fn start_judgement_candidates_task(&self, ctx: &mut Context<Self>) {
         info!("Starting judgement candidate submitter background task");
 
         let db = self.db.clone();
         let addr = ctx.address();
-        let network = self.network;
+        let network = self.watcher.network.clone();
 
         ctx.run_interval(
             Duration::new(JUDGEMENT_CANDIDATES_INTERVAL, 0),
             move |_act, _ctx| {
                 let db = db.clone();
                 let addr = addr.clone();
+                let network = network.clone();
 
                 actix::spawn(async move {
                     // Provide judgments for the specific network.
-                    match db.fetch_judgement_candidates(network).await {
+                    match db.fetch_judgement_candidates(&network).await {
                         Ok(completed) => {
                             for state in completed {
+                                let correlation_id = match db
+                                    .register_judgement_attempt(&state)
+                                    .await
+                                {
+                                    Ok(Some(correlation_id)) => correlation_id,
+                                    Ok(None) => continue,
+                                    Err(err) => {
+                                        error!("Failed to register judgement attempt: {:?}", err);
+                                        continue;
+                                    }
+                                };
+
+                                if let Err(err) = db.insert_judgement_audit(&state).await {
+                                    error!("Failed to record judgement audit: {:?}", err);
+                                }
+
                                 info!("Notifying Watcher about judgement: {:?}", state.context);
-                                addr.do_send(ClientCommand::ProvideJudgement(state));
+                                addr.do_send(ClientCommand::ProvideJudgement(
+                                    Box::new(state),
+                                    correlation_id,
+                                ));
                             }
                         }
                         Err(err) => {
@@ -319,14 +731,18 @@ impl Actor for Connector {
 
         span.in_scope(|| {
             debug!(
-                network = self.network.as_str(),
-                endpoint = self.endpoint.as_str()
+                network = self.watcher.network.as_str(),
+                endpoint = self.endpoint()
             );
 
+            // Negotiate the protocol before anything else is sent.
+            ctx.address().do_send(ClientCommand::Handshake);
+
             self.start_pending_judgements_task(ctx);
-            self.start_dangling_judgements_task(ctx);
             self.start_active_display_names_task(ctx);
             self.start_judgement_candidates_task(ctx);
+            self.start_challenge_expiry_task(ctx);
+            self.start_failback_task(ctx);
         });
     }
 
@@ -334,15 +750,24 @@ impl Actor for Connector {
         let span = warn_span!("watcher_connection_drop");
         span.in_scope(|| {
             debug!(
-                network = self.network.as_str(),
-                endpoint = self.endpoint.as_str()
+                network = self.watcher.network.as_str(),
+                endpoint = self.endpoint()
             );
         });
 
-        let endpoint = self.endpoint.clone();
-        let network = self.network;
+        // A dropped connection counts as a failure of the endpoint, so the
+        // next endpoint is used while it backs off.
+        if !self.failing_back {
+            self.endpoints.record_failure(self.active);
+        }
+
+        let watcher = self.watcher.clone();
+        let mut endpoints = self.endpoints.clone();
         let db = self.db.clone();
         let dn_verifier = self.dn_verifier.clone();
+        let second_challenge = self.second_challenge.clone();
+        let challenge_formats = self.challenge_formats.clone();
+        let challenge_ttl = self.challenge_ttl;
 
         actix::spawn(
             async move {
@@ -350,9 +775,17 @@ impl Actor for Connector {
 
                 let mut counter = 0;
                 loop {
-                    if Connector::start(endpoint.clone(), network, db.clone(), dn_verifier.clone())
-                        .await
-                        .is_err()
+                    if Connector::start(
+                        watcher.clone(),
+                        &mut endpoints,
+                        db.clone(),
+                        dn_verifier.clone(),
+                        second_challenge.clone(),
+                        challenge_formats.clone(),
+                        challenge_ttl,
+                    )
+                    .await
+                    .is_err()
                     {
                         warn!("Reconnection failed, retrying...");
 
@@ -361,7 +794,7 @@ impl Actor for Connector {
                             error!("Cannot reconnect to Watcher after {} attempts", counter);
                         }
 
-                        sleep(Duration::from_secs(RECONNECTION_TIMEOUT)).await;
+                        sleep(Duration::from_secs(endpoints.next_retry())).await;
                     } else {
                         info!("Reconnected to Watcher!");
                         break;
@@ -385,10 +818,43 @@ impl Handler<ClientCommand> for Connector {
         // NOTE: make sure no async code comes after this.
         let _guard = span.enter();
         debug!(
-            network = self.network.as_str(),
-            endpoint = self.endpoint.as_str()
+            network = self.watcher.network.as_str(),
+            endpoint = self.endpoint()
         );
 
+        // Only the active connection submits anything. Judgements dropped by
+        // a connection which is being replaced are resent via the outbox.
+        if ctx.state() != ActorState::Running {
+            debug!("Skipping message to Watcher, connection is being replaced");
+            return Ok(());
+        }
+
+        // In dry-run mode, judgements are only logged and recorded locally.
+        if self.watcher.dry_run {
+            if let ClientCommand::ProvideJudgement(state, correlation_id) = msg {
+                let response = JudgementResponse {
+                    address: state.context.address.clone(),
+                    judgement: state.judgement(),
+                    verified: state.as_verified_entries(),
+                    correlation_id: Some(correlation_id),
+                };
+
+                info!(
+                    "Dry-run, not submitting judgement: {}",
+                    serde_json::to_string(&response).unwrap()
+                );
+
+                let db = self.db.clone();
+                actix::spawn(async move {
+                    if let Err(err) = db.record_simulated_judgement(&state, &response).await {
+                        error!("Failed to record simulated judgement: {:?}", err);
+                    }
+                });
+
+                return Ok(());
+            }
+        }
+
         // If the sink (outgoing WS stream) is not configured (i.e. when
         // testing), send the client command to the channel.
         if self.sink.is_none() {
@@ -413,20 +879,39 @@ impl Handler<ClientCommand> for Connector {
         }
 
         match msg {
-            ClientCommand::ProvideJudgement(state) => {
+            ClientCommand::Handshake => {
+                debug!("Sending handshake over websocket stream");
+
+                sink.write(Message::Text(
+                    serde_json::to_string(&ResponseMessage {
+                        event: EventType::Handshake,
+                        data: Handshake::supported(),
+                    })
+                    .unwrap()
+                    .into(),
+                ))
+                .map_err(|err| anyhow!("failed to send handshake: {:?}", err))?;
+            }
+            ClientCommand::ProvideJudgement(state, correlation_id) => {
                 debug!(
                     "Providing judgement over websocket stream: {:?}",
                     state.context
                 );
+                let judgement = state.judgement();
                 let verified = state.as_verified_entries();
+                let correlation_id = self
+                    .protocol
+                    .supports(Capability::CorrelationIds)
+                    .then_some(correlation_id);
 
                 sink.write(Message::Text(
                     serde_json::to_string(&ResponseMessage {
                         event: EventType::JudgementResult,
                         data: JudgementResponse {
                             address: state.context.address,
-                            judgement: Judgement::Reasonable,
+                            judgement,
                             verified,
+                            correlation_id,
                         },
                     })
                     .unwrap()
@@ -472,11 +957,15 @@ impl Handler<WatcherMessage> for Connector {
 
     fn handle(&mut self, msg: WatcherMessage, _ctx: &mut Context<Self>) -> Self::Result {
         /// Handle a judgement request.
+        #[allow(clippy::too_many_arguments)]
         async fn process_request(
             db: &Database,
             id: IdentityContext,
             mut accounts: HashMap<AccountType, String>,
             dn_verifier: &DisplayNameVerifier,
+            second_challenge: &SecondChallengeConfig,
+            challenge_formats: &ChallengeFormatConfig,
+            ownership_proof: Option<OwnershipProofMode>,
             // Only used in testing.
             inserted_states: &Arc<RwLock<Vec<JudgementState>>>,
         ) -> Result<()> {
@@ -496,7 +985,13 @@ impl Handler<WatcherMessage> for Connector {
             }
 
             // Create judgement state and prepare to insert into database.
-            let state = JudgementState::new(id, accounts.into_iter().map(|a| a.into()).collect());
+            let mut state = JudgementState::new(
+                id,
+                accounts.into_iter().map(|a| a.into()).collect(),
+                second_challenge,
+                challenge_formats,
+            );
+            state.ownership = ownership_proof.map(OwnershipChallenge::new);
 
             // Add the judgement state that's about to get inserted into the
             // local queue which is then fetched from the unit tests.
@@ -518,54 +1013,124 @@ impl Handler<WatcherMessage> for Connector {
         // Update timestamp
         self.last_watcher_msg = Timestamp::now();
 
-        let network = self.network;
+        if let WatcherMessage::Handshake(handshake) = &msg {
+            self.protocol = Handshake::supported().negotiate(handshake);
+            info!(
+                "Negotiated protocol version {} with capabilities {:?}",
+                self.protocol.version, self.protocol.capabilities
+            );
+        }
+
+        let network = self.watcher.network.clone();
+        let ss58_prefix = self.watcher.ss58_prefix;
         let db = self.db.clone();
         let dn_verifier = self.dn_verifier.clone();
+        let second_challenge = self.second_challenge.clone();
+        let challenge_formats = self.challenge_formats.clone();
+        let ownership_proof = self.watcher.ownership_proof;
         let inserted_states = Arc::clone(&self.inserted_states);
 
         Box::pin(
             async move {
                 match msg {
+                    // Handled above.
+                    WatcherMessage::Handshake(_) => {}
                     WatcherMessage::Ack(data) => {
-                        if data.result.to_lowercase().contains("judgement given") {
-                            // Create identity context.
-                            let address =
-                                data.address
-                                .ok_or_else(|| {
-                                anyhow!(
-                                    "no address specified in 'judgement given' response from Watcher"
-                                )
-                            })?;
-
-                            let context = IdentityContext::new(address, network);
+                        if data.is_judgement_given() {
+                            // The correlation ID identifies the submission,
+                            // otherwise fall back to the current state of the
+                            // address.
+                            let submission = match &data.correlation_id {
+                                Some(id) => db.fetch_submission(id).await?,
+                                None => None,
+                            };
+
+                            let (context, completion_timestamp) = match submission {
+                                Some(submission) => {
+                                    (submission.context, submission.completion_timestamp)
+                                }
+                                None => {
+                                    let address = data.address.ok_or_else(|| {
+                                        anyhow!(
+                                            "no address specified in 'judgement given' response from Watcher"
+                                        )
+                                    })?;
+
+                                    let context = IdentityContext::new(address, network);
+                                    match db.fetch_judgement_state(&context).await? {
+                                        Some(state) => (context, state.completion_timestamp),
+                                        None => {
+                                            warn!("Received acknowledgement for unknown identity {:?}", context);
+                                            return Ok(());
+                                        }
+                                    }
+                                }
+                            };
 
                             info!("Marking {:?} as judged", context);
-                            db.set_judged(&context).await?;
+                            db.set_judged(&context, completion_timestamp, false)
+                                .await?;
+                        }
+                    }
+                    WatcherMessage::Error(data) => {
+                        error!("Received error from Watcher: {:?}", data);
+
+                        // Keep track of why a submission failed.
+                        if let Some(id) = &data.correlation_id {
+                            db.record_submission_error(id, &data.message).await?;
                         }
                     }
                     WatcherMessage::NewJudgementRequest(data) => {
+                        if !data.matches_prefix(ss58_prefix) {
+                            warn!("Ignoring judgement request of {:?}, the address does not belong to {}", data.address, network.as_str());
+                            return Ok(());
+                        }
+
                         let id = IdentityContext::new(data.address, network);
-                        process_request(&db, id, data.accounts, &dn_verifier, &inserted_states).await?;
+                        process_request(&db, id, data.accounts, &dn_verifier, &second_challenge, &challenge_formats, ownership_proof, &inserted_states).await?;
                     }
                     WatcherMessage::PendingJudgementsRequests(data) => {
                         // Convert data.
                         let data: Vec<(IdentityContext, HashMap<AccountType, String>)> = data
                             .into_iter()
+                            .filter(|req| {
+                                let matches = req.matches_prefix(ss58_prefix);
+                                if !matches {
+                                    warn!("Ignoring pending judgement of {:?}, the address does not belong to {}", req.address, network.as_str());
+                                }
+
+                                matches
+                            })
                             .map(|req| (
-                                IdentityContext::new(req.address, network),
+                                IdentityContext::new(req.address, network.clone()),
                                 req.accounts
                             ))
                             .collect();
 
+                        let pending: Vec<ChainAddress> = data
+                            .iter()
+                            .map(|(context, _)| context.address.clone())
+                            .collect();
+
                         for (context, accounts) in data {
-                            process_request(&db, context, accounts, &dn_verifier, &inserted_states).await?;
+                            process_request(&db, context, accounts, &dn_verifier, &second_challenge, &challenge_formats, ownership_proof, &inserted_states).await?;
+                        }
+
+                        // Archive the identities which are no longer pending
+                        // on-chain. An empty list is not trusted, since it
+                        // might be the result of an error on the Watcher.
+                        if !pending.is_empty() {
+                            let count = db.archive_abandoned_states(&network, &pending).await?;
+                            if count > 0 {
+                                info!("Archived {} abandoned identities", count);
+                            }
                         }
                     }
                     WatcherMessage::ActiveDisplayNames(data) => {
                         for mut name in data {
                             name.try_decode_hex();
 
-                            let context = IdentityContext::new(name.address, network);
+                            let context = IdentityContext::new(name.address, network.clone());
                             let entry = DisplayNameEntry {
                                 context,
                                 display_name: name.display_name,
@@ -611,7 +1176,20 @@ impl StreamHandler<std::result::Result<Frame, WsProtocolError>> for Connector {
                     conn.send(WatcherMessage::Ack(data)).await??;
                 }
                 EventType::Error => {
-                    error!("Received error from Watcher: {:?}", parsed.data);
+                    // Errors of legacy Watchers are not structured.
+                    match serde_json::from_value::<ErrorResponse>(parsed.data.clone()) {
+                        Ok(data) => conn.send(WatcherMessage::Error(data)).await??,
+                        Err(_) => error!("Received error from Watcher: {:?}", parsed.data),
+                    }
+                }
+                EventType::HandshakeResponse => {
+                    debug!("Received handshake from Watcher: {:?}", parsed.data);
+
+                    let data: Handshake = serde_json::from_value(parsed.data)?;
+                    conn.send(WatcherMessage::Handshake(data)).await??;
+                }
+                EventType::Unknown => {
+                    debug!("Ignoring unknown event from Watcher: {:?}", parsed.data);
                 }
                 EventType::NewJudgementRequest => {
                     debug!(
@@ -626,14 +1204,14 @@ impl StreamHandler<std::result::Result<Frame, WsProtocolError>> for Connector {
                 EventType::PendingJudgementsResponse => {
                     debug!("Received pending judgments from Watcher: {:?}", parsed.data);
 
-                    let data: Vec<JudgementRequest> = serde_json::from_value(parsed.data)?;
+                    let data: Vec<JudgementRequest> = parse_batch(parsed.data)?;
                     conn.send(WatcherMessage::PendingJudgementsRequests(data))
                         .await??;
                 }
                 EventType::DisplayNamesResponse => {
                     debug!("Received display names from the Watcher");
 
-                    let data: Vec<DisplayNameEntryRaw> = serde_json::from_value(parsed.data)?;
+                    let data: Vec<DisplayNameEntryRaw> = parse_batch(parsed.data)?;
                     conn.send(WatcherMessage::ActiveDisplayNames(data))
                         .await??;
                 }
@@ -648,8 +1226,8 @@ impl StreamHandler<std::result::Result<Frame, WsProtocolError>> for Connector {
         let span = debug_span!("handling_websocket_message");
         span.in_scope(|| {
             debug!(
-                network = self.network.as_str(),
-                endpoint = self.endpoint.as_str()
+                network = self.watcher.network.as_str(),
+                endpoint = self.endpoint()
             );
 
             let addr = ctx.address();
@@ -698,7 +1276,7 @@ impl From<(AccountType, String)> for IdentityFieldValue {
             AccountType::Web => IdentityFieldValue::Web(value),
             AccountType::Twitter => IdentityFieldValue::Twitter(value.to_lowercase()),
             AccountType::Matrix => IdentityFieldValue::Matrix(value),
-            AccountType::PGPFingerprint => IdentityFieldValue::PGPFingerprint(()),
+            AccountType::PGPFingerprint => IdentityFieldValue::PGPFingerprint(value),
             AccountType::Image => IdentityFieldValue::Image(()),
             AccountType::Additional => IdentityFieldValue::Additional(()),
         }
@@ -708,15 +1286,15 @@ impl From<(AccountType, String)> for IdentityFieldValue {
 #[cfg(test)]
 pub mod tests {
     use super::*;
-    use crate::{Database, DisplayNameConfig};
+    use crate::mock_watcher::tests::start_mock_watcher;
+    use crate::primitives::ChainName;
+    use crate::{Database, DisplayNameConfig, SecondChallengeConfig};
     use tokio::sync::mpsc::UnboundedReceiver;
 
     impl JudgementRequest {
         pub fn alice() -> Self {
             JudgementRequest {
-                address: "1a2YiGNu1UUhJtihq8961c7FZtWGQuWDVMWTNBKJdmpGhZP"
-                    .to_string()
-                    .into(),
+                address: IdentityContext::alice().address,
                 accounts: HashMap::from([
                     (AccountType::DisplayName, "Alice".to_string()),
                     (AccountType::Email, "alice@email.com".to_string()),
@@ -727,9 +1305,7 @@ pub mod tests {
         }
         pub fn bob() -> Self {
             JudgementRequest {
-                address: "1b3NhsSEqWSQwS6nPGKgCrSjv9Kp13CnhraLV5Coyd8ooXB"
-                    .to_string()
-                    .into(),
+                address: IdentityContext::bob().address,
                 accounts: HashMap::from([
                     (AccountType::DisplayName, "Bob".to_string()),
                     (AccountType::Email, "bob@email.com".to_string()),
@@ -744,6 +1320,21 @@ pub mod tests {
         pub fn new_judgement_request(req: JudgementRequest) -> Self {
             WatcherMessage::NewJudgementRequest(req)
         }
+        pub fn new_judgement_given(address: ChainAddress) -> Self {
+            WatcherMessage::Ack(AckResponse {
+                code: None,
+                ..AckResponse::judgement_given(address, None)
+            })
+        }
+        /// A typed acknowledgement, which only contains the correlation ID.
+        pub fn new_judgement_given_with_id(correlation_id: String) -> Self {
+            WatcherMessage::Ack(AckResponse {
+                result: String::new(),
+                code: Some(AckCode::JudgementGiven),
+                address: None,
+                correlation_id: Some(correlation_id),
+            })
+        }
     }
 
     pub struct ConnectorMocker {
@@ -755,6 +1346,29 @@ pub mod tests {
 
     impl ConnectorMocker {
         pub fn new(db: Database) -> Self {
+            Self::with_second_challenge(db, SecondChallengeConfig::default())
+        }
+        pub fn with_second_challenge(
+            db: Database,
+            second_challenge: SecondChallengeConfig,
+        ) -> Self {
+            Self::with_config(db, second_challenge, test_watcher())
+        }
+        pub fn with_ownership_proof(db: Database, ownership_proof: OwnershipProofMode) -> Self {
+            let mut watcher = test_watcher();
+            watcher.ownership_proof = Some(ownership_proof);
+            Self::with_config(db, SecondChallengeConfig::default(), watcher)
+        }
+        pub fn with_dry_run(db: Database) -> Self {
+            let mut watcher = test_watcher();
+            watcher.dry_run = true;
+            Self::with_config(db, SecondChallengeConfig::default(), watcher)
+        }
+        fn with_config(
+            db: Database,
+            second_challenge: SecondChallengeConfig,
+            watcher: WatcherConfig,
+        ) -> Self {
             let dn_config = DisplayNameConfig {
                 enabled: false,
                 limit: 0.85,
@@ -762,7 +1376,7 @@ pub mod tests {
 
             let dn_verifier = DisplayNameVerifier::new(db.clone(), dn_config);
             let (addr, queue, inserted_states) =
-                Connector::start_testing(ChainName::Polkadot, db, dn_verifier);
+                Connector::start_testing(watcher, db, dn_verifier, second_challenge);
 
             ConnectorMocker {
                 queue,
@@ -786,7 +1400,8 @@ pub mod tests {
 
             while let Ok(msg) = self.queue.try_recv() {
                 match msg {
-                    ClientCommand::ProvideJudgement(_) => counter.provide_judgement += 1,
+                    ClientCommand::Handshake => counter.handshake += 1,
+                    ClientCommand::ProvideJudgement(..) => counter.provide_judgement += 1,
                     ClientCommand::RequestPendingJudgements => {
                         counter.request_pending_judgements += 1
                     }
@@ -800,8 +1415,19 @@ pub mod tests {
         }
     }
 
+    fn test_watcher() -> WatcherConfig {
+        WatcherConfig {
+            network: ChainName::from("polkadot"),
+            ss58_prefix: 0,
+            endpoints: vec![],
+            ownership_proof: None,
+            dry_run: false,
+        }
+    }
+
     #[derive(Default)]
     pub struct OutgoingCounter {
+        pub handshake: usize,
         pub provide_judgement: usize,
         pub request_pending_judgements: usize,
         pub request_display_names: usize,
@@ -809,9 +1435,10 @@ pub mod tests {
 
     impl Connector {
         fn start_testing(
-            network: ChainName,
+            watcher: WatcherConfig,
             db: Database,
             dn_verifier: DisplayNameVerifier,
+            second_challenge: SecondChallengeConfig,
         ) -> (
             Addr<Connector>,
             UnboundedReceiver<ClientCommand>,
@@ -826,15 +1453,172 @@ pub mod tests {
                 sink: None,
                 db,
                 dn_verifier,
-                endpoint: "".to_string(),
-                network,
+                second_challenge,
+                challenge_formats: Default::default(),
+                challenge_ttl: None,
+                watcher,
+                endpoints: WatcherEndpoints::new(&[]),
+                active: 0,
+                failing_back: false,
                 outgoing,
                 inserted_states: Arc::clone(&inserted_states),
                 last_watcher_msg: Timestamp::now(),
+                protocol: Handshake::legacy(),
             }
             .start();
 
             (addr, recv, inserted_states)
         }
     }
+
+    #[test]
+    fn parse_watcher_endpoints() {
+        let watcher: WatcherConfig = serde_yaml::from_str(
+            r#"
+            network: polkadot
+            ss58_prefix: 0
+            endpoint: ws://localhost:8000
+            "#,
+        )
+        .unwrap();
+        assert_eq!(watcher.endpoints, vec!["ws://localhost:8000"]);
+
+        let watcher: WatcherConfig = serde_yaml::from_str(
+            r#"
+            network: polkadot
+            ss58_prefix: 0
+            endpoints:
+              - ws://localhost:8000
+              - ws://localhost:8001
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            watcher.endpoints,
+            vec!["ws://localhost:8000", "ws://localhost:8001"]
+        );
+    }
+
+    #[test]
+    fn endpoint_backoff_increases() {
+        assert_eq!(endpoint_backoff(1), ENDPOINT_BACKOFF);
+        assert_eq!(endpoint_backoff(2), ENDPOINT_BACKOFF * 2);
+        assert_eq!(endpoint_backoff(3), ENDPOINT_BACKOFF * 4);
+        assert_eq!(endpoint_backoff(100), ENDPOINT_MAX_BACKOFF);
+    }
+
+    #[actix::test]
+    async fn fail_over_to_next_endpoint() {
+        let srv = start_mock_watcher();
+        let mut endpoints = WatcherEndpoints::new(&[
+            // Nothing is listening on the primary endpoint.
+            "ws://127.0.0.1:1".to_string(),
+            srv.url("/"),
+        ]);
+
+        let (active, _) = endpoints.connect().await.unwrap();
+        assert_eq!(active, 1);
+        assert!(!endpoints.is_available(0));
+        assert_eq!(endpoints.endpoints[0].failures, 1);
+
+        // The primary endpoint is skipped while backing off.
+        let (active, _) = endpoints.connect().await.unwrap();
+        assert_eq!(active, 1);
+        assert_eq!(endpoints.endpoints[0].failures, 1);
+
+        // The primary endpoint is retried after the backoff.
+        sleep(Duration::from_secs(endpoints.next_retry() + 1)).await;
+        let (active, _) = endpoints.connect().await.unwrap();
+        assert_eq!(active, 1);
+        assert_eq!(endpoints.endpoints[0].failures, 2);
+        assert_eq!(endpoints.endpoints[1].failures, 0);
+
+        // Fails if no endpoint is reachable.
+        let mut endpoints = WatcherEndpoints::new(&["ws://127.0.0.1:1".to_string()]);
+        assert!(endpoints.connect().await.is_err());
+    }
+
+    #[test]
+    fn negotiate_handshake() {
+        let watcher: Handshake = serde_json::from_str(
+            r#"{"version":3,"capabilities":["correlationIds","batchedJudgements"]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            watcher.capabilities,
+            vec![Capability::CorrelationIds, Capability::Unknown]
+        );
+
+        let protocol = Handshake::supported().negotiate(&watcher);
+        assert_eq!(protocol.version, PROTOCOL_VERSION);
+        assert!(protocol.supports(Capability::CorrelationIds));
+        assert!(!protocol.supports(Capability::TypedAcks));
+
+        let protocol = Handshake::supported().negotiate(&Handshake::legacy());
+        assert_eq!(protocol, Handshake::legacy());
+    }
+
+    #[test]
+    fn parse_watcher_events() {
+        // Legacy acknowledgement.
+        let msg: ResponseMessage<AckResponse> = serde_json::from_str(&format!(
+            r#"{{"event":"ack","data":{{"result":"Judgement given","address":"{}"}}}}"#,
+            IdentityContext::alice().address.as_str()
+        ))
+        .unwrap();
+        assert_eq!(msg.event, EventType::Ack);
+        assert!(msg.data.is_judgement_given());
+
+        let msg: AckResponse =
+            serde_json::from_str(r#"{"result":"Message received","address":null}"#).unwrap();
+        assert!(!msg.is_judgement_given());
+
+        // Typed acknowledgements take precedence over the text.
+        let msg: AckResponse = serde_json::from_str(
+            r#"{"code":"judgementGiven","correlationId":"abc","address":null}"#,
+        )
+        .unwrap();
+        assert!(msg.is_judgement_given());
+        assert_eq!(msg.correlation_id, Some("abc".to_string()));
+
+        let msg: AckResponse =
+            serde_json::from_str(r#"{"result":"judgement given","code":"queued","address":null}"#)
+                .unwrap();
+        assert!(!msg.is_judgement_given());
+
+        // Unknown events are not rejected.
+        let msg: ResponseMessage<serde_json::Value> =
+            serde_json::from_str(r#"{"event":"statistics","data":{}}"#).unwrap();
+        assert_eq!(msg.event, EventType::Unknown);
+    }
+
+    #[test]
+    fn skip_invalid_batch_entries() {
+        let alice = IdentityContext::alice().address;
+
+        let data = serde_json::json!([
+            { "address": alice.as_str(), "displayName": "Alice" },
+            { "address": "1a2YiGNu1UUhJtihq8961c7FZtWGQuWDVMWTNBKJdmpGhZQ", "displayName": "Eve" },
+            { "address": alice.as_str() },
+        ]);
+        let entries: Vec<DisplayNameEntryRaw> = parse_batch(data).unwrap();
+        assert_eq!(
+            entries,
+            vec![DisplayNameEntryRaw {
+                address: alice.clone(),
+                display_name: "Alice".to_string(),
+            }]
+        );
+
+        let data = serde_json::json!([
+            { "address": "invalid", "accounts": {} },
+            { "address": alice.as_str(), "accounts": { "email": "alice@email.com" } },
+        ]);
+        let requests: Vec<JudgementRequest> = parse_batch(data).unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].address, alice);
+
+        // The batch itself must still be a list.
+        assert!(parse_batch::<JudgementRequest>(serde_json::json!({})).is_err());
+    }
 }
@@ -1,25 +1,74 @@
 use crate::adapters::admin::RawFieldName;
+use crate::adapters::twitter::TwitterId;
 use crate::api::VerifyChallenge;
-use crate::connector::DisplayNameEntry;
+use crate::connector::{DisplayNameEntry, Judgement, JudgementResponse};
 use crate::primitives::{
-    ChainName, ChallengeType, Event, ExpectedMessage, ExternalMessage, IdentityContext,
-    IdentityFieldValue, JudgementState, NotificationMessage, Timestamp,
+    ChainAddress, ChainName, ChallengeType, Event, ExpectedMessage, ExternalMessage,
+    ExternalMessageType, IdentityContext, IdentityFieldValue, JudgementAuditEntry, JudgementState,
+    JudgementSubmission, MessageId, NotificationMessage, Rejection, SubmissionStatus, Timestamp,
 };
-use crate::Result;
+use crate::{JudgementPolicy, LockoutConfig, Result};
 use bson::{doc, from_document, to_bson, to_document, Bson, Document};
 use futures::StreamExt;
-use mongodb::options::{TransactionOptions, UpdateOptions};
-use mongodb::{Client, ClientSession, Database as MongoDb};
+use mongodb::options::{
+    FindOneAndUpdateOptions, FindOneOptions, FindOptions, IndexOptions, ReturnDocument,
+    TransactionOptions, UpdateOptions,
+};
+use mongodb::{Client, ClientSession, Database as MongoDb, IndexModel};
 use rand::{thread_rng, Rng};
 use serde::Serialize;
 use std::collections::HashMap;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
 const IDENTITY_COLLECTION: &str = "identities";
 const EVENT_COLLECTION: &str = "event_log";
 const DISPLAY_NAMES: &str = "display_names";
-
-const DANGLING_THRESHOLD: u64 = 3600; // one hour
+const CHALLENGE_DELIVERIES: &str = "challenge_deliveries";
+const PROCESSED_MESSAGES: &str = "processed_messages";
+const TWITTER_HANDLES: &str = "twitter_handles";
+const JUDGEMENT_AUDIT: &str = "judgement_audit";
+const ARCHIVED_IDENTITIES: &str = "archived_identities";
+const SIMULATED_JUDGEMENTS: &str = "simulated_judgements";
+const JUDGEMENT_OUTBOX: &str = "judgement_outbox";
+
+// Sending the challenge to a Matrix account is retried up to this many times.
+const CHALLENGE_DELIVERY_MAX_ATTEMPTS: u32 = 5;
+
+// Deliveries are tracked per challenge, which are replaced or verified long
+// before. A challenge still pending after that is simply sent again.
+const CHALLENGE_DELIVERY_TTL: u64 = 3600 * 24 * 31; // 31 days
+
+// Judgements are resent with an exponential backoff until the Watcher
+// confirms those, up to the maximum number of attempts.
+#[cfg(not(test))]
+const JUDGEMENT_RETRY_BACKOFF: u64 = 120; // two minutes
+#[cfg(test)]
+const JUDGEMENT_RETRY_BACKOFF: u64 = 1;
+#[cfg(not(test))]
+const JUDGEMENT_MAX_ATTEMPTS: u32 = 6;
+#[cfg(test)]
+const JUDGEMENT_MAX_ATTEMPTS: u32 = 3;
+
+// Users can only request a new challenge for a field once the current one
+// was issued at least this many seconds ago.
+#[cfg(not(test))]
+const CHALLENGE_REFRESH_COOLDOWN: u64 = 300; // five minutes
+#[cfg(test)]
+const CHALLENGE_REFRESH_COOLDOWN: u64 = 2;
+
+// Identities inserted recently might not be part of the pending judgements of
+// the Watcher yet, so those are not archived.
+#[cfg(not(test))]
+const ARCHIVE_GRACE_PERIOD: u64 = 300; // five minutes
+#[cfg(test)]
+const ARCHIVE_GRACE_PERIOD: u64 = 0;
+
+// Must exceed the period in which adapters fetch messages (e.g. Twitter lists
+// the direct messages of the last 30 days).
+const PROCESSED_MESSAGE_TTL: u64 = 3600 * 24 * 31; // 31 days
+
+// Twitter handles can be renamed, so those are looked up again eventually.
+const TWITTER_HANDLE_TTL: u64 = 3600 * 24 * 7; // 7 days
 
 /// Convenience trait. Converts a value to BSON.
 trait ToBson {
@@ -56,6 +105,10 @@ impl EventCursor {
 pub struct Database {
     client: Client,
     db: MongoDb,
+    lockout: Option<LockoutConfig>,
+    judgement_policies: HashMap<ChainName, JudgementPolicy>,
+    // The configured networks and their SS58 prefixes.
+    networks: Vec<(ChainName, u16)>,
 }
 
 impl Database {
@@ -63,7 +116,130 @@ impl Database {
         let client = Client::with_uri_str(uri).await?;
         let db = client.database(db);
 
-        Ok(Database { client, db })
+        // Processed messages are removed once expired.
+        db.collection::<Document>(PROCESSED_MESSAGES)
+            .create_indexes(
+                vec![
+                    IndexModel::builder()
+                        .keys(doc! { "adapter": 1, "message_id": 1 })
+                        .options(IndexOptions::builder().unique(true).build())
+                        .build(),
+                    IndexModel::builder()
+                        .keys(doc! { "expires_at": 1 })
+                        .options(
+                            IndexOptions::builder()
+                                .expire_after(Duration::from_secs(0))
+                                .build(),
+                        )
+                        .build(),
+                ],
+                None,
+            )
+            .await?;
+
+        // Challenge deliveries are removed once expired.
+        db.collection::<Document>(CHALLENGE_DELIVERIES)
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "expires_at": 1 })
+                    .options(
+                        IndexOptions::builder()
+                            .expire_after(Duration::from_secs(0))
+                            .build(),
+                    )
+                    .build(),
+                None,
+            )
+            .await?;
+
+        // Looked up Twitter handles are removed once expired.
+        db.collection::<Document>(TWITTER_HANDLES)
+            .create_indexes(
+                vec![
+                    IndexModel::builder()
+                        .keys(doc! { "twitter_id": 1 })
+                        .options(IndexOptions::builder().unique(true).build())
+                        .build(),
+                    IndexModel::builder()
+                        .keys(doc! { "expires_at": 1 })
+                        .options(
+                            IndexOptions::builder()
+                                .expire_after(Duration::from_secs(0))
+                                .build(),
+                        )
+                        .build(),
+                ],
+                None,
+            )
+            .await?;
+
+        db.collection::<Document>(JUDGEMENT_AUDIT)
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "context.address": 1, "timestamp": 1 })
+                    .build(),
+                None,
+            )
+            .await?;
+
+        db.collection::<Document>(JUDGEMENT_OUTBOX)
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "context": 1, "completion_timestamp": 1 })
+                    .build(),
+                None,
+            )
+            .await?;
+
+        db.collection::<Document>(JUDGEMENT_OUTBOX)
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "correlation_ids": 1 })
+                    .build(),
+                None,
+            )
+            .await?;
+
+        Ok(Database {
+            client,
+            db,
+            lockout: None,
+            judgement_policies: HashMap::new(),
+            networks: vec![],
+        })
+    }
+    /// Locks fields after repeated failed verification attempts.
+    pub fn with_lockout(mut self, lockout: LockoutConfig) -> Self {
+        self.lockout = Some(lockout);
+        self
+    }
+    /// Decides which fields must be verified before an identity of the
+    /// chain is considered fully verified.
+    pub fn with_judgement_policies(
+        mut self,
+        policies: HashMap<ChainName, JudgementPolicy>,
+    ) -> Self {
+        self.judgement_policies = policies;
+        self
+    }
+    /// The configured networks, used to determine the network of an address
+    /// based on its SS58 prefix.
+    pub fn with_networks(mut self, networks: Vec<(ChainName, u16)>) -> Self {
+        self.networks = networks;
+        self
+    }
+    /// The configured networks which use the SS58 prefix of the address.
+    /// Multiple networks might share the same prefix (e.g. Polkadot and its
+    /// People chain).
+    pub fn address_networks(&self, address: &ChainAddress) -> Result<Vec<ChainName>> {
+        let prefix = address.ss58_prefix()?;
+
+        Ok(self
+            .networks
+            .iter()
+            .filter(|(_, ss58_prefix)| *ss58_prefix == prefix)
+            .map(|(network, _)| network.clone())
+            .collect())
     }
     async fn start_transaction(&self) -> Result<ClientSession> {
         let mut options = TransactionOptions::default();
@@ -201,6 +377,8 @@ impl Database {
         &self,
         context: &IdentityContext,
         field: &RawFieldName,
+        // The handle of the admin.
+        admin: &str,
         // Whether it should check if the idenity has been fully verified.
         full_check: bool,
         provided_session: Option<&mut ClientSession>,
@@ -219,21 +397,27 @@ impl Database {
         let coll = self.db.collection::<JudgementState>(IDENTITY_COLLECTION);
 
         // Set the appropriate types for verification.
-        let update = match field {
-            // For "ChallengeType::ExpectedMessage".
-            RawFieldName::Twitter | RawFieldName::Matrix => {
+        let mut update = match field {
+            // For "ChallengeType::ExpectedMessage". The second challenge, if
+            // any, is set below.
+            RawFieldName::Email
+            | RawFieldName::Twitter
+            | RawFieldName::Matrix
+            | RawFieldName::PGPFingerprint => {
                 doc! {
                     "$set": {
                         "fields.$.challenge.content.expected.is_verified": true,
                     }
                 }
             }
-            // For "ChallengeType::ExpectedMessage" (with secondary verification).
-            RawFieldName::Email => {
+            // For "ChallengeType::ExpectedMessage". Web fields of older
+            // identities might still be stored as "ChallengeType::Unsupported",
+            // so set both, the irrelevant one is ignored on deserialization.
+            RawFieldName::Web => {
                 doc! {
                     "$set": {
                         "fields.$.challenge.content.expected.is_verified": true,
-                        "fields.$.challenge.content.second.is_verified": true,
+                        "fields.$.challenge.content.is_verified": true,
                     }
                 }
             }
@@ -246,7 +430,7 @@ impl Database {
                 }
             }
             // For "ChallengeType::Unsupported".
-            RawFieldName::LegalName | RawFieldName::Web => {
+            RawFieldName::LegalName | RawFieldName::Image | RawFieldName::Additional => {
                 doc! {
                     "$set": {
                         "fields.$.challenge.content.is_verified": true,
@@ -260,12 +444,17 @@ impl Database {
             }
         };
 
+        // Required by `FieldPolicy::ManualReview`.
+        let set = update.get_document_mut("$set")?;
+        set.insert("fields.$.manually_verified", true);
+        set.insert("fields.$.verified_by", admin);
+
         // Update field.
         let res = coll
             .update_one_with_session(
                 doc! {
                     "context": context.to_bson()?,
-                    "fields.value.type": field.to_string(),
+                    "fields.value.type": field.value_type(),
                 },
                 update,
                 None,
@@ -277,6 +466,33 @@ impl Database {
             return Ok(None);
         }
 
+        // Only set the second challenge if the field has one, MongoDB cannot
+        // create fields inside of a `null` value.
+        if matches!(
+            field,
+            RawFieldName::Email | RawFieldName::Twitter | RawFieldName::Matrix
+        ) {
+            coll.update_one_with_session(
+                doc! {
+                    "context": context.to_bson()?,
+                    "fields": {
+                        "$elemMatch": {
+                            "value.type": field.value_type(),
+                            "challenge.content.second": { "$ne": null },
+                        }
+                    },
+                },
+                doc! {
+                    "$set": {
+                        "fields.$.challenge.content.second.is_verified": true,
+                    }
+                },
+                None,
+                session,
+            )
+            .await?;
+        }
+
         // Create event.
         if full_check {
             self.insert_event(
@@ -313,10 +529,37 @@ impl Database {
 
         Ok(Some(()))
     }
-    pub async fn verify_message(&self, message: &ExternalMessage) -> Result<()> {
+    /// Records on the matching fields that the sender of a message could not
+    /// be authenticated. The message itself is still processed.
+    pub async fn flag_sender_auth_failure(
+        &self,
+        origin: &ExternalMessageType,
+        reason: &str,
+    ) -> Result<()> {
+        let coll = self.db.collection::<Document>(IDENTITY_COLLECTION);
+
+        coll.update_many(
+            doc! {
+                "fields.value": origin.to_bson()?,
+            },
+            doc! {
+                "$set": {
+                    "fields.$.sender_auth_failure": reason,
+                }
+            },
+            None,
+        )
+        .await?;
+
+        Ok(())
+    }
+    /// Returns whether the message verified any of the matching fields.
+    pub async fn verify_message(&self, message: &ExternalMessage) -> Result<bool> {
         let mut session = self.start_transaction().await?;
         let coll = self.db.collection(IDENTITY_COLLECTION);
 
+        let mut verified = false;
+
         // Fetch the current field state based on the message origin.
         let mut cursor = coll
             .find_with_session(
@@ -343,6 +586,12 @@ impl Database {
             let context = id_state.context.clone();
             let field_value = field_state.value.clone();
 
+            // Locked fields do not accept any attempts.
+            if field_state.is_locked() {
+                debug!("Ignoring message for locked field: {:?}", field_value);
+                continue;
+            }
+
             let challenge = &mut field_state.challenge;
             if !challenge.is_verified() {
                 match challenge {
@@ -353,6 +602,8 @@ impl Database {
                         // Only proceed if the expected challenge has not been verified yet.
                         if !expected.is_verified {
                             if expected.verify_message(message) {
+                                verified = true;
+
                                 // Update field state. Be more specific with the query in order
                                 // to verify the correct field (in theory, there could be
                                 // multiple pending requests with the same external account
@@ -362,9 +613,20 @@ impl Database {
                                         "context": context.to_bson()?,
                                         "fields.value": message.origin.to_bson()?,
                                     },
-                                    doc! {
-                                        "$set": {
-                                            "fields.$.challenge.content.expected.is_verified": true,
+                                    if second.is_some() {
+                                        // The second challenge is only sent now, so it
+                                        // expires relative to this point in time.
+                                        doc! {
+                                            "$set": {
+                                                "fields.$.challenge.content.expected.is_verified": true,
+                                                "fields.$.challenge.content.second.issued_at": Timestamp::now().to_bson()?,
+                                            }
+                                        }
+                                    } else {
+                                        doc! {
+                                            "$set": {
+                                                "fields.$.challenge.content.expected.is_verified": true,
+                                            }
                                         }
                                     },
                                     None,
@@ -392,6 +654,12 @@ impl Database {
                                     .await?;
                                 }
                             } else {
+                                field_state.failed_attempts += 1;
+                                let locked = self
+                                    .lockout
+                                    .map(|policy| field_state.register_failure(&policy))
+                                    .unwrap_or(false);
+
                                 // Update field state.
                                 coll.update_many_with_session(
                                     doc! {
@@ -399,8 +667,8 @@ impl Database {
                                         "fields.value": message.origin.to_bson()?,
                                     },
                                     doc! {
-                                        "$inc": {
-                                            "fields.$.failed_attempts": 1isize.to_bson()?,
+                                        "$set": {
+                                            "fields.$": field_state.to_bson()?,
                                         }
                                     },
                                     None,
@@ -411,11 +679,22 @@ impl Database {
                                 self.insert_event(
                                     NotificationMessage::FieldVerificationFailed {
                                         context: context.clone(),
-                                        field: field_value,
+                                        field: field_value.clone(),
                                     },
                                     &mut session,
                                 )
                                 .await?;
+
+                                if locked {
+                                    self.insert_event(
+                                        NotificationMessage::FieldLocked {
+                                            context: context.clone(),
+                                            field: field_value,
+                                        },
+                                        &mut session,
+                                    )
+                                    .await?;
+                                }
                             }
                         }
                     }
@@ -433,7 +712,7 @@ impl Database {
 
         session.commit_transaction().await?;
 
-        Ok(())
+        Ok(verified)
     }
     /// Check if all fields have been verified.
     async fn process_fully_verified(
@@ -443,7 +722,13 @@ impl Database {
     ) -> Result<()> {
         let coll = self.db.collection::<JudgementState>(IDENTITY_COLLECTION);
 
-        if state.check_full_verification() {
+        let policy = self
+            .judgement_policies
+            .get(&state.context.chain)
+            .cloned()
+            .unwrap_or_default();
+
+        if state.check_full_verification_with(&policy) {
             // Create a timed delay for issuing judgments. Between 30 seconds to
             // 5 minutes. This is used to prevent timing attacks where a user
             // updates the identity right before the judgement is issued.
@@ -531,6 +816,15 @@ impl Database {
             let context = state.context.clone();
             let field_value = field_state.value.clone();
 
+            // Locked fields do not accept any attempts.
+            if field_state.is_locked() {
+                debug!(
+                    "Ignoring second challenge for locked field: {:?}",
+                    field_value
+                );
+                continue;
+            }
+
             match &mut field_state.challenge {
                 ChallengeType::ExpectedMessage {
                     expected: _,
@@ -543,14 +837,14 @@ impl Database {
                     }
 
                     let second = second.as_mut().unwrap();
-                    if request.challenge.contains(&second.value) {
+                    if second.is_contained_in(&request.challenge) {
                         second.set_verified();
                         verified = true;
 
                         coll.update_one_with_session(
                             doc! {
                                 "fields.value": request.entry.to_bson()?,
-                                "fields.challenge.content.second.value": request.challenge.to_bson()?,
+                                "fields.challenge.content.second.value": second.value.to_bson()?,
                             },
                             doc! {
                                 "$set": {
@@ -579,6 +873,36 @@ impl Database {
                             &mut session,
                         )
                         .await?;
+
+                        if let Some(policy) = self.lockout {
+                            let locked = field_state.register_failure(&policy);
+
+                            coll.update_one_with_session(
+                                doc! {
+                                    "context": context.to_bson()?,
+                                    "fields.value": request.entry.to_bson()?,
+                                },
+                                doc! {
+                                    "$set": {
+                                        "fields.$": field_state.to_bson()?,
+                                    }
+                                },
+                                None,
+                                &mut session,
+                            )
+                            .await?;
+
+                            if locked {
+                                self.insert_event(
+                                    NotificationMessage::FieldLocked {
+                                        context: context.clone(),
+                                        field: field_value.clone(),
+                                    },
+                                    &mut session,
+                                )
+                                .await?;
+                            }
+                        }
                     }
                 }
                 _ => {
@@ -689,7 +1013,7 @@ impl Database {
             .retain(|_, timestamp| timestamp.raw() > current - 10);
 
         // Sort by id, ascending.
-        events.sort_by(|a, b| a.id.cmp(&b.id));
+        events.sort_by_key(|wrapper| wrapper.id);
 
         Ok(events
             .into_iter()
@@ -719,151 +1043,142 @@ impl Database {
             Ok(None)
         }
     }
-    pub async fn fetch_judgement_candidates(
-        &self,
-        network: ChainName,
-    ) -> Result<Vec<JudgementState>> {
+    /// Fetches the values of all web fields which have not been verified yet.
+    pub async fn fetch_unverified_web_fields(&self) -> Result<Vec<String>> {
         let coll = self.db.collection::<JudgementState>(IDENTITY_COLLECTION);
 
         let mut cursor = coll
             .find(
                 doc! {
-                    "context.chain": network.as_str().to_bson()?,
-                    "is_fully_verified": true,
+                    "fields": {
+                        "$elemMatch": {
+                            "value.type": "web",
+                            "challenge.content.expected.is_verified": false,
+                        }
+                    },
                     "judgement_submitted": false,
-                    "issue_judgement_at": {
-                        "$lt": Timestamp::now().to_bson()?,
-                    }
                 },
                 None,
             )
             .await?;
 
-        let mut completed = vec![];
+        let mut domains = vec![];
         while let Some(state) = cursor.next().await {
-            completed.push(state?);
+            for field in state?.fields {
+                if let IdentityFieldValue::Web(domain) = field.value {
+                    if !field.challenge.is_verified() && !domains.contains(&domain) {
+                        domains.push(domain);
+                    }
+                }
+            }
         }
 
-        Ok(completed)
+        Ok(domains)
     }
-    // (Warning) This fully verifies the identity without having to verify
-    // individual fields.
-    pub async fn full_manual_verification(&self, context: &IdentityContext) -> Result<bool> {
-        let mut session = self.start_transaction().await?;
+    /// Fetches the unverified Matrix challenges which have not been sent to
+    /// the user yet.
+    pub async fn fetch_undelivered_matrix_challenges(
+        &self,
+    ) -> Result<Vec<(String, ExpectedMessage)>> {
         let coll = self.db.collection::<JudgementState>(IDENTITY_COLLECTION);
 
-        // Create a timed delay for issuing judgments. Between 30 seconds to
-        // 5 minutes. This is used to prevent timing attacks where a user
-        // updates the identity right before the judgement is issued.
-        let now = Timestamp::now();
-        let offset = thread_rng().gen_range(30..300);
-        let issue_at = Timestamp::with_offset(offset);
-
-        let res = coll
-            .update_one_with_session(
-                doc! {
-                    "context": context.to_bson()?,
-                },
+        let mut cursor = coll
+            .find(
                 doc! {
-                    "$set": {
-                        "is_fully_verified": true,
-                        "judgement_submitted": false,
-                        "completion_timestamp": now.to_bson()?,
-                        "issue_judgement_at": issue_at.to_bson()?,
-                    }
+                    "fields": {
+                        "$elemMatch": {
+                            "value.type": "matrix",
+                            "challenge.content.expected.is_verified": false,
+                        }
+                    },
+                    "judgement_submitted": false,
+                    // Challenges are not revealed before the ownership of
+                    // the address was proven, if required.
+                    "$or": [
+                        { "ownership": null },
+                        { "ownership.is_verified": true },
+                        { "ownership.mode": "before_judgement" },
+                    ],
                 },
                 None,
-                &mut session,
             )
             .await?;
 
-        // Create event.
-        if res.modified_count == 1 {
-            // Verify all possible fields. Unused fields are silently ignored.
-            let _ = self
-                .verify_manually(context, &RawFieldName::LegalName, false, Some(&mut session))
-                .await?;
-            let _ = self
-                .verify_manually(
-                    context,
-                    &RawFieldName::DisplayName,
-                    false,
-                    Some(&mut session),
-                )
-                .await?;
-            let _ = self
-                .verify_manually(context, &RawFieldName::Email, false, Some(&mut session))
-                .await?;
-            let _ = self
-                .verify_manually(context, &RawFieldName::Web, false, Some(&mut session))
-                .await?;
-            let _ = self
-                .verify_manually(context, &RawFieldName::Twitter, false, Some(&mut session))
-                .await?;
-            let _ = self
-                .verify_manually(context, &RawFieldName::Matrix, false, Some(&mut session))
-                .await?;
-
-            self.insert_event(
-                NotificationMessage::FullManualVerification {
-                    context: context.clone(),
-                },
-                &mut session,
-            )
-            .await?;
+        let mut pending = vec![];
+        while let Some(state) = cursor.next().await {
+            for field in state?.fields {
+                if let (
+                    IdentityFieldValue::Matrix(handle),
+                    ChallengeType::ExpectedMessage {
+                        expected,
+                        second: _,
+                    },
+                ) = (field.value, field.challenge)
+                {
+                    if !expected.is_verified {
+                        pending.push((handle, expected));
+                    }
+                }
+            }
+        }
 
-            session.commit_transaction().await?;
-            Ok(true)
-        } else {
-            session.commit_transaction().await?;
-            Ok(false)
+        if pending.is_empty() {
+            return Ok(pending);
         }
-    }
-    pub async fn set_judged(&self, context: &IdentityContext) -> Result<()> {
-        let mut session = self.start_transaction().await?;
-        let coll = self.db.collection::<JudgementState>(IDENTITY_COLLECTION);
 
-        let res = coll
-            .update_one_with_session(
-                doc! {
-                    "context": context.to_bson()?,
-                    "judgement_submitted": false,
-                },
+        // Filter out the challenges which were already delivered or which
+        // failed too often. Older entries did not track failed attempts.
+        let challenges = pending
+            .iter()
+            .map(|(_, expected)| expected.value.as_str())
+            .collect::<Vec<&str>>();
+
+        let mut cursor = self
+            .db
+            .collection::<Document>(CHALLENGE_DELIVERIES)
+            .find(
                 doc! {
-                    "$set": {
-                        "judgement_submitted": true,
-                    }
+                    "challenge": {
+                        "$in": challenges.to_bson()?,
+                    },
+                    "$or": [
+                        { "delivered": true },
+                        { "delivered": { "$exists": false } },
+                        { "attempts": { "$gte": CHALLENGE_DELIVERY_MAX_ATTEMPTS } },
+                    ],
                 },
                 None,
-                &mut session,
             )
             .await?;
 
-        // Create event.
-        if res.modified_count == 1 {
-            self.insert_event(
-                NotificationMessage::JudgementProvided {
-                    context: context.clone(),
-                },
-                &mut session,
-            )
-            .await?;
+        let mut delivered = vec![];
+        while let Some(doc) = cursor.next().await {
+            delivered.push(doc?.get_str("challenge")?.to_string());
         }
 
-        session.commit_transaction().await?;
-
-        Ok(())
+        Ok(pending
+            .into_iter()
+            .filter(|(_, expected)| !delivered.contains(&expected.value))
+            .collect())
     }
-    pub async fn insert_display_name(&self, name: &DisplayNameEntry) -> Result<()> {
-        let coll = self.db.collection::<DisplayNameEntry>(DISPLAY_NAMES);
+    pub async fn set_challenge_delivered(&self, challenge: &ExpectedMessage) -> Result<()> {
+        let coll = self.db.collection::<Document>(CHALLENGE_DELIVERIES);
 
         coll.update_one(
             doc! {
-                "display_name": name.display_name.to_bson()?,
-                "context": name.context.to_bson()?,
+                "challenge": challenge.value.to_bson()?,
             },
             doc! {
-                "$setOnInsert": name.to_bson()?,
+                "$set": {
+                    "delivered": true,
+                    "timestamp": Timestamp::now().to_bson()?,
+                    "expires_at": bson::DateTime::from_system_time(
+                        SystemTime::now() + Duration::from_secs(CHALLENGE_DELIVERY_TTL)
+                    ),
+                },
+                "$setOnInsert": {
+                    "challenge": challenge.value.to_bson()?,
+                }
             },
             {
                 let mut opt = UpdateOptions::default();
@@ -875,37 +1190,787 @@ impl Database {
 
         Ok(())
     }
-    pub async fn fetch_display_names(&self, chain: ChainName) -> Result<Vec<DisplayNameEntry>> {
-        let coll = self.db.collection::<DisplayNameEntry>(DISPLAY_NAMES);
+    /// Records a failed attempt to send the challenge. Returns `false` if the
+    /// maximum number of attempts was reached, after which the challenge is
+    /// no longer sent.
+    pub async fn set_challenge_delivery_failed(&self, challenge: &ExpectedMessage) -> Result<bool> {
+        let coll = self.db.collection::<Document>(CHALLENGE_DELIVERIES);
 
-        let mut cursor = coll
-            .find(
+        let doc = coll
+            .find_one_and_update(
                 doc! {
-                    "context.chain": chain.to_bson()?,
+                    "challenge": challenge.value.to_bson()?,
+                },
+                doc! {
+                    "$inc": {
+                        "attempts": 1,
+                    },
+                    "$set": {
+                        "timestamp": Timestamp::now().to_bson()?,
+                        "expires_at": bson::DateTime::from_system_time(
+                            SystemTime::now() + Duration::from_secs(CHALLENGE_DELIVERY_TTL)
+                        ),
+                    },
+                    "$setOnInsert": {
+                        "challenge": challenge.value.to_bson()?,
+                        "delivered": false,
+                    }
+                },
+                {
+                    let mut opt = FindOneAndUpdateOptions::default();
+                    opt.upsert = Some(true);
+                    opt.return_document = Some(ReturnDocument::After);
+                    Some(opt)
                 },
-                None,
             )
-            .await?;
-
-        let mut names = vec![];
-        while let Some(doc) = cursor.next().await {
-            names.push(doc?);
-        }
+            .await?
+            .ok_or_else(|| anyhow!("failed to track challenge delivery"))?;
 
-        Ok(names)
+        let attempts = doc.get_i32("attempts").unwrap_or_default() as u32;
+        Ok(attempts < CHALLENGE_DELIVERY_MAX_ATTEMPTS)
     }
-    pub async fn set_display_name_valid(&self, state: &JudgementState) -> Result<()> {
-        let mut session = self.start_transaction().await?;
-        let coll = self.db.collection::<()>(IDENTITY_COLLECTION);
+    /// Whether the message was already processed by the adapter, e.g. before
+    /// a restart.
+    pub async fn is_message_processed(&self, adapter: &str, id: MessageId) -> Result<bool> {
+        let coll = self.db.collection::<Document>(PROCESSED_MESSAGES);
 
-        coll.update_one_with_session(
+        Ok(coll
+            .find_one(
+                doc! {
+                    "adapter": adapter,
+                    "message_id": id.to_bson()?,
+                },
+                None,
+            )
+            .await?
+            .is_some())
+    }
+    pub async fn set_message_processed(&self, adapter: &str, id: MessageId) -> Result<()> {
+        let coll = self.db.collection::<Document>(PROCESSED_MESSAGES);
+
+        coll.update_one(
+            doc! {
+                "adapter": adapter,
+                "message_id": id.to_bson()?,
+            },
+            doc! {
+                "$setOnInsert": {
+                    "adapter": adapter,
+                    "message_id": id.to_bson()?,
+                    "expires_at": bson::DateTime::from_system_time(
+                        SystemTime::now() + Duration::from_secs(PROCESSED_MESSAGE_TTL)
+                    ),
+                }
+            },
+            {
+                let mut opt = UpdateOptions::default();
+                opt.upsert = Some(true);
+                Some(opt)
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+    /// Fetches the handles of the given Twitter Ids which have been looked up
+    /// before, e.g. prior to a restart.
+    pub async fn fetch_twitter_handles(
+        &self,
+        twitter_ids: &[&TwitterId],
+    ) -> Result<HashMap<TwitterId, String>> {
+        let coll = self.db.collection::<Document>(TWITTER_HANDLES);
+
+        // The Ids are stored as strings, those might exceed the range of a
+        // BSON integer.
+        let ids: Vec<String> = twitter_ids
+            .iter()
+            .map(|id| id.as_u64().to_string())
+            .collect();
+
+        let mut cursor = coll
+            .find(doc! { "twitter_id": { "$in": ids } }, None)
+            .await?;
+
+        let mut handles = HashMap::new();
+        while let Some(doc) = cursor.next().await {
+            let doc = doc?;
+            let id: u64 = doc.get_str("twitter_id")?.parse()?;
+            handles.insert(TwitterId::from(id), doc.get_str("handle")?.to_string());
+        }
+
+        Ok(handles)
+    }
+    /// Fetches the Twitter Id of the given handle, if looked up before.
+    pub async fn fetch_twitter_id(&self, handle: &str) -> Result<Option<TwitterId>> {
+        let coll = self.db.collection::<Document>(TWITTER_HANDLES);
+
+        coll.find_one(doc! { "handle": handle }, None)
+            .await?
+            .map(|doc| Ok(TwitterId::from(doc.get_str("twitter_id")?.parse::<u64>()?)))
+            .transpose()
+    }
+    pub async fn set_twitter_handles(&self, handles: &HashMap<TwitterId, String>) -> Result<()> {
+        let coll = self.db.collection::<Document>(TWITTER_HANDLES);
+
+        for (id, handle) in handles {
+            coll.update_one(
+                doc! {
+                    "twitter_id": id.as_u64().to_string(),
+                },
+                doc! {
+                    "$set": {
+                        "handle": handle,
+                        "expires_at": bson::DateTime::from_system_time(
+                            SystemTime::now() + Duration::from_secs(TWITTER_HANDLE_TTL)
+                        ),
+                    }
+                },
+                {
+                    let mut opt = UpdateOptions::default();
+                    opt.upsert = Some(true);
+                    Some(opt)
+                },
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+    /// Records the judgement of the identity in the (append-only) audit log.
+    /// The same judgement is only recorded once, even if sent repeatedly.
+    pub async fn insert_judgement_audit(&self, state: &JudgementState) -> Result<()> {
+        let coll = self.db.collection::<JudgementAuditEntry>(JUDGEMENT_AUDIT);
+
+        let entry = JudgementAuditEntry::new(state);
+
+        coll.update_one(
+            doc! {
+                "context": entry.context.to_bson()?,
+                "judgement": entry.judgement.to_bson()?,
+                "completion_timestamp": entry.completion_timestamp.to_bson()?,
+            },
+            doc! {
+                "$setOnInsert": entry.to_document()?,
+            },
+            {
+                let mut opt = UpdateOptions::default();
+                opt.upsert = Some(true);
+                Some(opt)
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+    /// Fetches the audit log of the address (of any chain), oldest first.
+    pub async fn fetch_judgement_audit(
+        &self,
+        address: &ChainAddress,
+    ) -> Result<Vec<JudgementAuditEntry>> {
+        let coll = self.db.collection::<JudgementAuditEntry>(JUDGEMENT_AUDIT);
+
+        let mut cursor = coll
+            .find(
+                doc! {
+                    "context.address": address.to_bson()?,
+                },
+                {
+                    let mut opt = FindOptions::default();
+                    opt.sort = Some(doc! { "timestamp": 1 });
+                    Some(opt)
+                },
+            )
+            .await?;
+
+        let mut entries = vec![];
+        while let Some(entry) = cursor.next().await {
+            entries.push(entry?);
+        }
+
+        Ok(entries)
+    }
+    pub async fn fetch_judgement_candidates(
+        &self,
+        network: &ChainName,
+    ) -> Result<Vec<JudgementState>> {
+        let coll = self.db.collection::<JudgementState>(IDENTITY_COLLECTION);
+
+        let mut cursor = coll
+            .find(
+                doc! {
+                    "context.chain": network.as_str().to_bson()?,
+                    "$or": [
+                        { "is_fully_verified": true },
+                        { "rejection": { "$ne": null } },
+                    ],
+                    "judgement_submitted": false,
+                    "issue_judgement_at": {
+                        "$lt": Timestamp::now().to_bson()?,
+                    }
+                },
+                None,
+            )
+            .await?;
+
+        let mut completed = vec![];
+        while let Some(state) = cursor.next().await {
+            completed.push(state?);
+        }
+
+        Ok(completed)
+    }
+    // (Warning) This fully verifies the identity without having to verify
+    // individual fields.
+    pub async fn full_manual_verification(
+        &self,
+        context: &IdentityContext,
+        admin: &str,
+    ) -> Result<bool> {
+        let mut session = self.start_transaction().await?;
+        let coll = self.db.collection::<JudgementState>(IDENTITY_COLLECTION);
+
+        // Create a timed delay for issuing judgments. Between 30 seconds to
+        // 5 minutes. This is used to prevent timing attacks where a user
+        // updates the identity right before the judgement is issued.
+        let now = Timestamp::now();
+        let offset = thread_rng().gen_range(30..300);
+        let issue_at = Timestamp::with_offset(offset);
+
+        let res = coll
+            .update_one_with_session(
+                doc! {
+                    "context": context.to_bson()?,
+                },
+                doc! {
+                    "$set": {
+                        "is_fully_verified": true,
+                        "judgement_submitted": false,
+                        "completion_timestamp": now.to_bson()?,
+                        "issue_judgement_at": issue_at.to_bson()?,
+                        // Overrides any previous rejection.
+                        "rejection": null,
+                    }
+                },
+                None,
+                &mut session,
+            )
+            .await?;
+
+        // Create event.
+        if res.modified_count == 1 {
+            // Verify all possible fields. Unused fields are silently ignored.
+            let _ = self
+                .verify_manually(
+                    context,
+                    &RawFieldName::LegalName,
+                    admin,
+                    false,
+                    Some(&mut session),
+                )
+                .await?;
+            let _ = self
+                .verify_manually(
+                    context,
+                    &RawFieldName::DisplayName,
+                    admin,
+                    false,
+                    Some(&mut session),
+                )
+                .await?;
+            let _ = self
+                .verify_manually(
+                    context,
+                    &RawFieldName::Email,
+                    admin,
+                    false,
+                    Some(&mut session),
+                )
+                .await?;
+            let _ = self
+                .verify_manually(
+                    context,
+                    &RawFieldName::Web,
+                    admin,
+                    false,
+                    Some(&mut session),
+                )
+                .await?;
+            let _ = self
+                .verify_manually(
+                    context,
+                    &RawFieldName::Twitter,
+                    admin,
+                    false,
+                    Some(&mut session),
+                )
+                .await?;
+            let _ = self
+                .verify_manually(
+                    context,
+                    &RawFieldName::Matrix,
+                    admin,
+                    false,
+                    Some(&mut session),
+                )
+                .await?;
+            let _ = self
+                .verify_manually(
+                    context,
+                    &RawFieldName::PGPFingerprint,
+                    admin,
+                    false,
+                    Some(&mut session),
+                )
+                .await?;
+            let _ = self
+                .verify_manually(
+                    context,
+                    &RawFieldName::Image,
+                    admin,
+                    false,
+                    Some(&mut session),
+                )
+                .await?;
+            let _ = self
+                .verify_manually(
+                    context,
+                    &RawFieldName::Additional,
+                    admin,
+                    false,
+                    Some(&mut session),
+                )
+                .await?;
+
+            self.insert_event(
+                NotificationMessage::FullManualVerification {
+                    context: context.clone(),
+                },
+                &mut session,
+            )
+            .await?;
+
+            session.commit_transaction().await?;
+            Ok(true)
+        } else {
+            session.commit_transaction().await?;
+            Ok(false)
+        }
+    }
+    /// Rejects the identity with the given (negative) judgement, which is
+    /// submitted to the Watcher instead of `Reasonable`. Returns `false` if
+    /// no pending identity was found.
+    pub async fn reject_identity(
+        &self,
+        context: &IdentityContext,
+        judgement: Judgement,
+        reason: &str,
+    ) -> Result<bool> {
+        if !judgement.is_negative() {
+            return Err(anyhow!("{:?} is not a negative judgement", judgement));
+        }
+
+        let mut session = self.start_transaction().await?;
+        let coll = self.db.collection::<JudgementState>(IDENTITY_COLLECTION);
+
+        let now = Timestamp::now();
+        let rejection = Rejection {
+            judgement,
+            reason: reason.to_string(),
+            timestamp: now,
+        };
+
+        let res = coll
+            .update_one_with_session(
+                doc! {
+                    "context": context.to_bson()?,
+                    "judgement_submitted": false,
+                },
+                doc! {
+                    "$set": {
+                        "is_fully_verified": false,
+                        "rejection": rejection.to_bson()?,
+                        "completion_timestamp": now.to_bson()?,
+                        // Negative judgements are issued immediately.
+                        "issue_judgement_at": now.to_bson()?,
+                    }
+                },
+                None,
+                &mut session,
+            )
+            .await?;
+
+        if res.matched_count == 0 {
+            return Ok(false);
+        }
+
+        self.insert_event(
+            NotificationMessage::IdentityRejected {
+                context: context.clone(),
+            },
+            &mut session,
+        )
+        .await?;
+
+        session.commit_transaction().await?;
+
+        Ok(true)
+    }
+    /// Stores the judgement which would have been submitted in dry-run mode
+    /// and marks the identity as (simulated) judged.
+    pub async fn record_simulated_judgement(
+        &self,
+        state: &JudgementState,
+        response: &JudgementResponse,
+    ) -> Result<()> {
+        let coll = self.db.collection::<Document>(SIMULATED_JUDGEMENTS);
+
+        coll.insert_one(
+            doc! {
+                "context": state.context.to_bson()?,
+                "response": response.to_bson()?,
+                "timestamp": Timestamp::now().to_bson()?,
+            },
+            None,
+        )
+        .await?;
+
+        self.set_judged(&state.context, state.completion_timestamp, true)
+            .await
+    }
+    #[cfg(test)]
+    pub async fn fetch_simulated_judgements(
+        &self,
+        context: &IdentityContext,
+    ) -> Result<Vec<JudgementResponse>> {
+        let coll = self.db.collection::<Document>(SIMULATED_JUDGEMENTS);
+
+        let mut cursor = coll
+            .find(
+                doc! {
+                    "context": context.to_bson()?,
+                },
+                None,
+            )
+            .await?;
+
+        let mut responses = vec![];
+        while let Some(doc) = cursor.next().await {
+            let doc = doc?;
+            responses.push(from_document(doc.get_document("response")?.clone())?);
+        }
+
+        Ok(responses)
+    }
+    /// Marks the judgement of the identity state with the given completion
+    /// timestamp as given. Only that submission is acknowledged, and the
+    /// identity is only marked as judged if it is still in that state (i.e.
+    /// a late acknowledgement of a previous submission has no effect on the
+    /// current one).
+    pub async fn set_judged(
+        &self,
+        context: &IdentityContext,
+        completion_timestamp: Option<Timestamp>,
+        simulated: bool,
+    ) -> Result<()> {
+        let mut session = self.start_transaction().await?;
+        let coll = self.db.collection::<JudgementState>(IDENTITY_COLLECTION);
+
+        let res = coll
+            .update_one_with_session(
+                doc! {
+                    "context": context.to_bson()?,
+                    "completion_timestamp": completion_timestamp.to_bson()?,
+                    "judgement_submitted": false,
+                },
+                doc! {
+                    "$set": {
+                        "judgement_submitted": true,
+                        "judgement_simulated": simulated,
+                    }
+                },
+                None,
+                &mut session,
+            )
+            .await?;
+
+        // Stop resending the judgement.
+        self.db
+            .collection::<JudgementSubmission>(JUDGEMENT_OUTBOX)
+            .update_one_with_session(
+                doc! {
+                    "context": context.to_bson()?,
+                    "completion_timestamp": completion_timestamp.to_bson()?,
+                    "status": SubmissionStatus::Pending.to_bson()?,
+                },
+                doc! {
+                    "$set": {
+                        "status": SubmissionStatus::Acknowledged.to_bson()?,
+                    }
+                },
+                None,
+                &mut session,
+            )
+            .await?;
+
+        // Create event.
+        if res.modified_count == 1 {
+            self.insert_event(
+                NotificationMessage::JudgementProvided {
+                    context: context.clone(),
+                },
+                &mut session,
+            )
+            .await?;
+        }
+
+        session.commit_transaction().await?;
+
+        Ok(())
+    }
+    pub async fn insert_display_name(&self, name: &DisplayNameEntry) -> Result<()> {
+        let coll = self.db.collection::<DisplayNameEntry>(DISPLAY_NAMES);
+
+        coll.update_one(
+            doc! {
+                "display_name": name.display_name.to_bson()?,
+                "context": name.context.to_bson()?,
+            },
+            doc! {
+                "$setOnInsert": name.to_bson()?,
+            },
+            {
+                let mut opt = UpdateOptions::default();
+                opt.upsert = Some(true);
+                Some(opt)
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+    pub async fn fetch_display_names(&self, chain: &ChainName) -> Result<Vec<DisplayNameEntry>> {
+        let coll = self.db.collection::<DisplayNameEntry>(DISPLAY_NAMES);
+
+        let mut cursor = coll
+            .find(
+                doc! {
+                    "context.chain": chain.to_bson()?,
+                },
+                None,
+            )
+            .await?;
+
+        let mut names = vec![];
+        while let Some(doc) = cursor.next().await {
+            names.push(doc?);
+        }
+
+        Ok(names)
+    }
+    pub async fn set_display_name_valid(&self, state: &JudgementState) -> Result<()> {
+        let mut session = self.start_transaction().await?;
+        let coll = self.db.collection::<()>(IDENTITY_COLLECTION);
+
+        coll.update_one_with_session(
+            doc! {
+                "context": state.context.to_bson()?,
+                "fields.value.type": "display_name",
+            },
+            doc! {
+                "$set": {
+                    "fields.$.challenge.content.passed": true,
+                }
+            },
+            None,
+            &mut session,
+        )
+        .await?;
+
+        // Create event
+        self.insert_event(
+            NotificationMessage::FieldVerified {
+                context: state.context.clone(),
+                field: state
+                    .fields
+                    .iter()
+                    .find(|field| matches!(field.value, IdentityFieldValue::DisplayName(_)))
+                    .map(|field| field.value.clone())
+                    .expect("Failed to retrieve display name. This is a bug"),
+            },
+            &mut session,
+        )
+        .await?;
+
+        self.process_fully_verified(state, &mut session).await?;
+
+        session.commit_transaction().await?;
+
+        Ok(())
+    }
+    pub async fn insert_display_name_violations(
+        &self,
+        context: &IdentityContext,
+        violations: &Vec<DisplayNameEntry>,
+    ) -> Result<()> {
+        let coll = self.db.collection::<()>(IDENTITY_COLLECTION);
+
+        coll.update_one(
+            doc! {
+                "context": context.to_bson()?,
+                "fields.value.type": "display_name",
+            },
+            doc! {
+                "$set": {
+                    "fields.$.challenge.content.passed": false,
+                    "fields.$.challenge.content.violations": violations.to_bson()?
+                }
+            },
+            None,
+        )
+        .await?;
+
+        Ok(())
+    }
+    async fn insert_event<T: Into<Event>>(
+        &self,
+        event: T,
+        session: &mut ClientSession,
+    ) -> Result<()> {
+        let coll = self.db.collection(EVENT_COLLECTION);
+
+        let event = <T as Into<Event>>::into(event);
+        coll.insert_one_with_session(event.to_bson()?, None, session)
+            .await?;
+
+        Ok(())
+    }
+    /// Unlocks the matching fields of the identity, if locked. Returns `None`
+    /// if the identity was not found.
+    pub async fn unlock_field(
+        &self,
+        context: &IdentityContext,
+        field: &RawFieldName,
+    ) -> Result<Option<()>> {
+        let mut session = self.start_transaction().await?;
+        let coll = self.db.collection::<JudgementState>(IDENTITY_COLLECTION);
+
+        let state = coll
+            .find_one_with_session(
+                doc! {
+                    "context": context.to_bson()?,
+                },
+                None,
+                &mut session,
+            )
+            .await?;
+
+        let mut state = match state {
+            Some(state) => state,
+            None => return Ok(None),
+        };
+
+        let mut unlocked = vec![];
+        for f in &mut state.fields {
+            if f.value.matches_raw_name(field) && f.unlock() {
+                unlocked.push(f.clone());
+            }
+        }
+
+        if unlocked.is_empty() {
+            return Ok(Some(()));
+        }
+
+        coll.update_one_with_session(
+            doc! {
+                "context": context.to_bson()?,
+            },
+            doc! {
+                "$set": {
+                    "fields": state.fields.to_bson()?,
+                }
+            },
+            None,
+            &mut session,
+        )
+        .await?;
+
+        for f in unlocked {
+            self.insert_event(
+                NotificationMessage::FieldUnlocked {
+                    context: context.clone(),
+                    field: f.value.clone(),
+                },
+                &mut session,
+            )
+            .await?;
+
+            // The second challenge was replaced when the field got locked,
+            // so the new one must be sent to the user.
+            if f.is_awaiting_second_challenge() {
+                self.insert_event(
+                    NotificationMessage::AwaitingSecondChallenge {
+                        context: context.clone(),
+                        field: f.value,
+                    },
+                    &mut session,
+                )
+                .await?;
+            }
+        }
+
+        session.commit_transaction().await?;
+
+        Ok(Some(()))
+    }
+    /// Verifies the signature of the ownership nonce of the identity. Returns
+    /// `false` if the signature is invalid or no proof is required.
+    pub async fn verify_ownership(
+        &self,
+        context: &IdentityContext,
+        signature: &str,
+    ) -> Result<bool> {
+        let mut session = self.start_transaction().await?;
+        let coll = self.db.collection::<JudgementState>(IDENTITY_COLLECTION);
+
+        let state = coll
+            .find_one_with_session(
+                doc! {
+                    "context": context.to_bson()?,
+                    "judgement_submitted": false,
+                },
+                None,
+                &mut session,
+            )
+            .await?;
+
+        let mut state = match state {
+            Some(state) => state,
+            None => return Ok(false),
+        };
+
+        let ownership = match state.ownership.as_mut() {
+            Some(ownership) => ownership,
+            None => return Ok(false),
+        };
+
+        if ownership.is_verified {
+            return Ok(true);
+        }
+
+        if !ownership.verify_signature(&context.address, signature) {
+            debug!("Invalid ownership signature for {:?}", context);
+            return Ok(false);
+        }
+
+        ownership.is_verified = true;
+
+        coll.update_one_with_session(
             doc! {
-                "context": state.context.to_bson()?,
-                "fields.value.type": "display_name",
+                "context": context.to_bson()?,
             },
             doc! {
                 "$set": {
-                    "fields.$.challenge.content.passed": true,
+                    "ownership.is_verified": true,
                 }
             },
             None,
@@ -913,95 +1978,464 @@ impl Database {
         )
         .await?;
 
-        // Create event
         self.insert_event(
-            NotificationMessage::FieldVerified {
-                context: state.context.clone(),
-                field: state
-                    .fields
-                    .iter()
-                    .find(|field| matches!(field.value, IdentityFieldValue::DisplayName(_)))
-                    .map(|field| field.value.clone())
-                    .expect("Failed to retrieve display name. This is a bug"),
+            NotificationMessage::OwnershipVerified {
+                context: context.clone(),
             },
             &mut session,
         )
         .await?;
 
-        self.process_fully_verified(state, &mut session).await?;
+        // Check if the identity is fully verified.
+        self.process_fully_verified(&state, &mut session).await?;
 
         session.commit_transaction().await?;
 
-        Ok(())
+        Ok(true)
     }
-    pub async fn insert_display_name_violations(
+    /// Replaces the unverified challenges of the given field with new ones,
+    /// on request of the user. The signature of the ownership nonce must be
+    /// provided; identities without an ownership proof can only be refreshed
+    /// if `allow_unauthenticated` is set. Returns `false` if there was nothing
+    /// to replace, the proof is missing or invalid, or the current challenge
+    /// was issued too recently.
+    pub async fn refresh_challenge(
         &self,
         context: &IdentityContext,
-        violations: &Vec<DisplayNameEntry>,
+        field: &IdentityFieldValue,
+        signature: Option<&str>,
+        allow_unauthenticated: bool,
+    ) -> Result<bool> {
+        let mut session = self.start_transaction().await?;
+        let coll = self.db.collection::<JudgementState>(IDENTITY_COLLECTION);
+
+        let state = coll
+            .find_one_with_session(
+                doc! {
+                    "context": context.to_bson()?,
+                    "fields.value": field.to_bson()?,
+                    "judgement_submitted": false,
+                },
+                None,
+                &mut session,
+            )
+            .await?;
+
+        let mut state = match state {
+            Some(state) => state,
+            None => return Ok(false),
+        };
+
+        let is_owner = match &state.ownership {
+            Some(ownership) => signature
+                .map(|signature| ownership.verify_signature(&context.address, signature))
+                .unwrap_or(false),
+            None => allow_unauthenticated,
+        };
+
+        if !is_owner {
+            debug!(
+                "Missing or invalid ownership proof for refresh of {:?}",
+                context
+            );
+            return Ok(false);
+        }
+
+        let threshold = Timestamp::from(
+            Timestamp::now()
+                .raw()
+                .saturating_sub(CHALLENGE_REFRESH_COOLDOWN),
+        );
+        let fallback = state.inserted_timestamp;
+
+        let regenerated = state
+            .fields
+            .iter_mut()
+            .find(|f| &f.value == field)
+            // A pending second challenge was already sent to the account, so
+            // it is only replaced once expired.
+            .filter(|f| !f.is_awaiting_second_challenge())
+            .filter(|f| f.has_expired_challenge(threshold, fallback))
+            .map(|f| f.regenerate_challenge())
+            .unwrap_or(false);
+
+        if !regenerated {
+            return Ok(false);
+        }
+
+        self.update_regenerated_fields(&state, vec![field.clone()], &mut session)
+            .await?;
+
+        session.commit_transaction().await?;
+
+        Ok(true)
+    }
+    /// Replaces the unverified challenges of the given network which were
+    /// issued more than `ttl` seconds ago. Returns the number of replaced
+    /// challenges (per field).
+    pub async fn regenerate_expired_challenges(
+        &self,
+        network: &ChainName,
+        ttl: u64,
+    ) -> Result<usize> {
+        let mut session = self.start_transaction().await?;
+        let coll = self.db.collection::<JudgementState>(IDENTITY_COLLECTION);
+
+        let threshold = Timestamp::from(Timestamp::now().raw().saturating_sub(ttl));
+
+        let mut cursor = coll
+            .find_with_session(
+                doc! {
+                    "context.chain": network.as_str().to_bson()?,
+                    "is_fully_verified": false,
+                    "judgement_submitted": false,
+                },
+                None,
+                &mut session,
+            )
+            .await?;
+
+        let mut expired = vec![];
+        while let Some(state) = cursor.next(&mut session).await {
+            let mut state = state?;
+            let fallback = state.inserted_timestamp;
+
+            let mut fields = vec![];
+            for field in &mut state.fields {
+                if field.has_expired_challenge(threshold, fallback) && field.regenerate_challenge()
+                {
+                    fields.push(field.value.clone());
+                }
+            }
+
+            if !fields.is_empty() {
+                expired.push((state, fields));
+            }
+        }
+
+        let mut count = 0;
+        for (state, fields) in expired {
+            count += fields.len();
+            self.update_regenerated_fields(&state, fields, &mut session)
+                .await?;
+        }
+
+        session.commit_transaction().await?;
+
+        Ok(count)
+    }
+    async fn update_regenerated_fields(
+        &self,
+        state: &JudgementState,
+        regenerated: Vec<IdentityFieldValue>,
+        session: &mut ClientSession,
     ) -> Result<()> {
-        let coll = self.db.collection::<()>(IDENTITY_COLLECTION);
+        let coll = self.db.collection::<JudgementState>(IDENTITY_COLLECTION);
 
-        coll.update_one(
+        coll.update_one_with_session(
             doc! {
-                "context": context.to_bson()?,
-                "fields.value.type": "display_name",
+                "context": state.context.to_bson()?,
             },
             doc! {
                 "$set": {
-                    "fields.$.challenge.content.passed": false,
-                    "fields.$.challenge.content.violations": violations.to_bson()?
+                    "fields": state.fields.to_bson()?,
                 }
             },
             None,
+            session,
         )
         .await?;
 
+        for field in regenerated {
+            self.insert_event(
+                NotificationMessage::ChallengeRegenerated {
+                    context: state.context.clone(),
+                    field: field.clone(),
+                },
+                session,
+            )
+            .await?;
+
+            // Results in the new second challenge being sent to the user.
+            let awaiting_second = state
+                .fields
+                .iter()
+                .any(|f| f.value == field && f.is_awaiting_second_challenge());
+
+            if awaiting_second {
+                self.insert_event(
+                    NotificationMessage::AwaitingSecondChallenge {
+                        context: state.context.clone(),
+                        field,
+                    },
+                    session,
+                )
+                .await?;
+            }
+        }
+
         Ok(())
     }
-    async fn insert_event<T: Into<Event>>(
+    /// Archives the unverified identities of the network which are no longer
+    /// pending on-chain (e.g. the request was cancelled or the identity was
+    /// cleared). Returns the number of archived identities.
+    pub async fn archive_abandoned_states(
         &self,
-        event: T,
-        session: &mut ClientSession,
-    ) -> Result<()> {
-        let coll = self.db.collection(EVENT_COLLECTION);
+        network: &ChainName,
+        pending: &[ChainAddress],
+    ) -> Result<usize> {
+        let mut session = self.start_transaction().await?;
+        let coll = self.db.collection::<Document>(IDENTITY_COLLECTION);
+        let archive = self.db.collection::<Document>(ARCHIVED_IDENTITIES);
 
-        let event = <T as Into<Event>>::into(event);
-        coll.insert_one_with_session(event.to_bson()?, None, session)
+        let threshold =
+            Timestamp::from(Timestamp::now().raw().saturating_sub(ARCHIVE_GRACE_PERIOD));
+
+        // Fully verified or rejected identities are about to be judged, so
+        // those are left to the Watcher.
+        let mut cursor = coll
+            .find_with_session(
+                doc! {
+                    "context.chain": network.as_str().to_bson()?,
+                    "context.address": {
+                        "$nin": pending.to_bson()?,
+                    },
+                    "is_fully_verified": false,
+                    "judgement_submitted": false,
+                    "rejection": null,
+                    "inserted_timestamp": {
+                        "$lt": threshold.to_bson()?,
+                    },
+                },
+                None,
+                &mut session,
+            )
             .await?;
 
-        Ok(())
+        let mut abandoned = vec![];
+        while let Some(doc) = cursor.next(&mut session).await {
+            abandoned.push(doc?);
+        }
+
+        let abandoned_count = abandoned.len();
+        for mut doc in abandoned {
+            let state: JudgementState = from_document(doc.clone())?;
+
+            coll.delete_one_with_session(
+                doc! {
+                    "_id": doc.get("_id").cloned().unwrap_or(Bson::Null),
+                },
+                None,
+                &mut session,
+            )
+            .await?;
+
+            doc.remove("_id");
+            doc.insert("archived_at", Timestamp::now().to_bson()?);
+            archive
+                .insert_one_with_session(doc, None, &mut session)
+                .await?;
+
+            self.insert_event(
+                NotificationMessage::IdentityRemoved {
+                    context: state.context,
+                },
+                &mut session,
+            )
+            .await?;
+        }
+
+        session.commit_transaction().await?;
+
+        Ok(abandoned_count)
     }
-    /// Removes all dangling judgements after the `DANGLING_THRESHOLD` threshold
-    /// has been reached. See `crate::connector::start_dangling_judgements_task`
-    /// for more information.
-    pub async fn process_dangling_judgement_states(&self) -> Result<()> {
-        let coll = self.db.collection::<()>(IDENTITY_COLLECTION);
+    /// Fetches the most recently archived state of the identity.
+    pub async fn fetch_archived_judgement_state(
+        &self,
+        context: &IdentityContext,
+    ) -> Result<Option<JudgementState>> {
+        let coll = self.db.collection::<JudgementState>(ARCHIVED_IDENTITIES);
+
+        Ok(coll
+            .find_one(
+                doc! {
+                    "context": context.to_bson()?,
+                },
+                {
+                    let mut opt = FindOneOptions::default();
+                    opt.sort = Some(doc! { "archived_at": -1 });
+                    Some(opt)
+                },
+            )
+            .await?)
+    }
+    /// Records an attempt to submit the judgement of the (completed) state
+    /// to the Watcher and returns the correlation ID of the attempt. Returns
+    /// `None` if the judgement should not be sent (yet), either because of
+    /// the backoff or because the maximum number of attempts was reached, in
+    /// which case the submission is marked as failed.
+    pub async fn register_judgement_attempt(
+        &self,
+        state: &JudgementState,
+    ) -> Result<Option<String>> {
+        let mut session = self.start_transaction().await?;
+        let coll = self.db.collection::<JudgementSubmission>(JUDGEMENT_OUTBOX);
 
-        let threshold = (Timestamp::now().raw() - DANGLING_THRESHOLD).to_bson()?;
+        let filter = doc! {
+            "context": state.context.to_bson()?,
+            "completion_timestamp": state.completion_timestamp.to_bson()?,
+        };
 
-        let res = coll
-            .update_many(
+        let now = Timestamp::now();
+        let correlation_id = hex::encode(thread_rng().gen::<[u8; 16]>());
+
+        let send = match coll
+            .find_one_with_session(filter.clone(), None, &mut session)
+            .await?
+        {
+            None => {
+                let submission = JudgementSubmission::new(
+                    state,
+                    Timestamp::with_offset(retry_backoff(1)),
+                    correlation_id.clone(),
+                );
+                coll.insert_one_with_session(submission, None, &mut session)
+                    .await?;
+
+                true
+            }
+            Some(submission) if submission.status != SubmissionStatus::Pending => false,
+            Some(submission) if submission.next_attempt.raw() > now.raw() => false,
+            Some(submission) if submission.attempts >= JUDGEMENT_MAX_ATTEMPTS => {
+                coll.update_one_with_session(
+                    filter,
+                    doc! {
+                        "$set": {
+                            "status": SubmissionStatus::Failed.to_bson()?,
+                        }
+                    },
+                    None,
+                    &mut session,
+                )
+                .await?;
+
+                self.insert_event(
+                    NotificationMessage::JudgementSubmissionFailed {
+                        context: state.context.clone(),
+                    },
+                    &mut session,
+                )
+                .await?;
+
+                false
+            }
+            Some(submission) => {
+                let attempts = submission.attempts + 1;
+
+                coll.update_one_with_session(
+                    filter,
+                    doc! {
+                        "$set": {
+                            "attempts": attempts,
+                            "last_attempt": now.to_bson()?,
+                            "next_attempt": Timestamp::with_offset(retry_backoff(attempts)).to_bson()?,
+                        },
+                        "$push": {
+                            "correlation_ids": correlation_id.as_str(),
+                        }
+                    },
+                    None,
+                    &mut session,
+                )
+                .await?;
+
+                true
+            }
+        };
+
+        session.commit_transaction().await?;
+
+        Ok(send.then_some(correlation_id))
+    }
+    /// Finds the submission with the given correlation ID.
+    pub async fn fetch_submission(
+        &self,
+        correlation_id: &str,
+    ) -> Result<Option<JudgementSubmission>> {
+        let coll = self.db.collection::<JudgementSubmission>(JUDGEMENT_OUTBOX);
+
+        let submission = coll
+            .find_one(
                 doc! {
-                    "is_fully_verified": true,
-                    "judgement_submitted": false,
-                    "completion_timestamp": {
-                        "$lt": threshold,
-                    }
+                    "correlation_ids": correlation_id,
                 },
+                None,
+            )
+            .await?;
+
+        Ok(submission)
+    }
+    pub async fn record_submission_error(&self, correlation_id: &str, error: &str) -> Result<()> {
+        let coll = self.db.collection::<JudgementSubmission>(JUDGEMENT_OUTBOX);
+
+        coll.update_one(
+            doc! {
+                "correlation_ids": correlation_id,
+            },
+            doc! {
+                "$set": {
+                    "last_error": error,
+                }
+            },
+            None,
+        )
+        .await?;
+
+        Ok(())
+    }
+    /// Fetches the failed submissions the admins were not notified about yet.
+    pub async fn fetch_unreported_failed_submissions(&self) -> Result<Vec<JudgementSubmission>> {
+        let coll = self.db.collection::<JudgementSubmission>(JUDGEMENT_OUTBOX);
+
+        let mut cursor = coll
+            .find(
                 doc! {
-                    "$set": {
-                        "judgement_submitted": true
-                    }
+                    "status": SubmissionStatus::Failed.to_bson()?,
+                    "reported": false,
                 },
                 None,
             )
             .await?;
 
-        let count = res.modified_count;
-        if count > 0 {
-            debug!("Disabled {} tangling identities", count);
+        let mut submissions = vec![];
+        while let Some(submission) = cursor.next().await {
+            submissions.push(submission?);
         }
 
+        Ok(submissions)
+    }
+    pub async fn set_submission_reported(&self, submission: &JudgementSubmission) -> Result<()> {
+        let coll = self.db.collection::<JudgementSubmission>(JUDGEMENT_OUTBOX);
+
+        coll.update_one(
+            doc! {
+                "context": submission.context.to_bson()?,
+                "completion_timestamp": submission.completion_timestamp.to_bson()?,
+            },
+            doc! {
+                "$set": {
+                    "reported": true,
+                }
+            },
+            None,
+        )
+        .await?;
+
         Ok(())
     }
 }
+
+/// The delay before the next attempt, doubled after every attempt.
+fn retry_backoff(attempts: u32) -> u64 {
+    JUDGEMENT_RETRY_BACKOFF * 2u64.pow(attempts.saturating_sub(1))
+}
@@ -19,7 +19,7 @@ impl DisplayNameVerifier {
     pub async fn check_similarities(
         &self,
         name: &str,
-        chain: ChainName,
+        chain: &ChainName,
         // Skip comparison for this account, usually for the issuer itself
         // (required when re-requesting judgement).
         skip: Option<&IdentityContext>,
@@ -59,7 +59,7 @@ impl DisplayNameVerifier {
         };
 
         let violations = self
-            .check_similarities(name, state.context.chain, Some(&state.context))
+            .check_similarities(name, &state.context.chain, Some(&state.context))
             .await?;
 
         if !violations.is_empty() {
@@ -122,5 +122,5 @@ fn jaro_words(left: &str, right: &str, delimiter: &[&str]) -> f64 {
         total += temp;
     }
 
-    total as f64 / left_words.len().max(right_words.len()) as f64
+    total / left_words.len().max(right_words.len()) as f64
 }
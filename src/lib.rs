@@ -9,7 +9,9 @@ extern crate async_trait;
 
 use actix::clock::sleep;
 use adapters::matrix::MatrixHandle;
-use primitives::ChainName;
+use connector::AccountType;
+use primitives::{ChainName, ChallengeFormat, OwnershipProofMode};
+use std::collections::HashMap;
 use std::fs;
 use std::time::Duration;
 
@@ -19,6 +21,7 @@ use adapters::run_adapters;
 use api::run_rest_api_server;
 use connector::run_connector;
 use database::Database;
+pub use mock_watcher::run_mock_watcher;
 use notifier::run_session_notifier;
 
 mod adapters;
@@ -26,14 +29,20 @@ mod api;
 mod connector;
 mod database;
 mod display_name;
+mod mock_watcher;
 mod notifier;
 mod primitives;
 #[cfg(test)]
 mod tests;
+mod wordlist;
 
 #[derive(Debug, Deserialize)]
 pub struct Config {
     pub db: DatabaseConfig,
+    /// Disabled if not specified.
+    pub lockout: Option<LockoutConfig>,
+    /// All fields are required if not specified for a chain.
+    pub judgement_policy: Option<HashMap<ChainName, JudgementPolicy>>,
     pub instance: InstanceType,
 }
 
@@ -59,11 +68,56 @@ pub struct DatabaseConfig {
     pub name: String,
 }
 
+/// Locks a field after `max_attempts` failed verification attempts within
+/// `window` seconds.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct LockoutConfig {
+    pub max_attempts: usize,
+    pub window: u64,
+    /// Fields are unlocked automatically after this many seconds. If not
+    /// specified, fields stay locked until unlocked by an admin.
+    pub cooldown: Option<u64>,
+}
+
+/// How a field type is treated when deciding whether an identity is fully
+/// verified.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldPolicy {
+    #[default]
+    Required,
+    /// The field does not need to be verified.
+    Ignored,
+    /// The field must be verified by an admin, even if verified otherwise.
+    ManualReview,
+}
+
+/// The policies of the field types of a chain. Field types which are not
+/// specified are required.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct JudgementPolicy {
+    pub fields: HashMap<AccountType, FieldPolicy>,
+}
+
+impl JudgementPolicy {
+    pub fn field_policy(&self, ty: &AccountType) -> FieldPolicy {
+        self.fields.get(ty).copied().unwrap_or_default()
+    }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub struct NotifierConfig {
     pub api_address: String,
     pub display_name: DisplayNameConfig,
+    /// Allow refreshing the challenges of identities without an ownership
+    /// proof via `/api/refresh_challenge`. Anyone who knows the address can
+    /// then replace the challenges of such an identity (once per cooldown),
+    /// which interrupts verifications in progress.
+    #[serde(default)]
+    pub unauthenticated_challenge_refresh: bool,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -73,13 +127,54 @@ pub struct AdapterConfig {
     pub matrix: MatrixConfig,
     pub twitter: TwitterConfig,
     pub email: EmailConfig,
+    #[serde(default)]
+    pub web: WebConfig,
     pub display_name: DisplayNameConfig,
+    #[serde(default)]
+    pub second_challenge: SecondChallengeConfig,
+    /// Unverified challenges are replaced after this many seconds. Disabled
+    /// if not specified.
+    pub challenge_ttl: Option<u64>,
+    pub challenge_format: Option<ChallengeFormatConfig>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct WatcherConfig {
+    /// The name of the network, e.g. `polkadot`, `kusama` or `westend`.
     pub network: ChainName,
-    pub endpoint: String,
+    /// The SS58 address prefix of the network.
+    pub ss58_prefix: u16,
+    /// The endpoints of the Watcher, ordered by priority. If an endpoint is
+    /// unreachable, the next one is used until the preferred endpoint
+    /// recovers. A single `endpoint` is accepted, too.
+    #[serde(alias = "endpoint", deserialize_with = "one_or_many")]
+    pub endpoints: Vec<String>,
+    /// Require a signature from the on-chain account before revealing the
+    /// challenges or before issuing a judgement. Disabled if not specified.
+    pub ownership_proof: Option<OwnershipProofMode>,
+    /// Only log and store the judgements locally instead of submitting them
+    /// to the Watcher. Useful for testing a deployment against a live chain.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+fn one_or_many<'de, D>(deserializer: D) -> std::result::Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(
+        match <OneOrMany as serde::Deserialize>::deserialize(deserializer)? {
+            OneOrMany::One(endpoint) => vec![endpoint],
+            OneOrMany::Many(endpoints) => endpoints,
+        },
+    )
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -97,19 +192,48 @@ pub struct MatrixConfig {
     pub password: String,
     pub db_path: String,
     pub admins: Option<Vec<MatrixHandle>>,
+    /// The room in which admins are notified about judgements that could not
+    /// be submitted to the Watcher.
+    pub admin_room: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub struct TwitterConfig {
     pub enabled: bool,
-    pub api_key: String,
-    pub api_secret: String,
-    pub token: String,
-    pub token_secret: String,
+    #[serde(flatten)]
+    pub api: TwitterApiConfig,
+    /// Defaults to `https://api.twitter.com`.
+    pub base_url: Option<String>,
+    /// Defaults to `direct_messages` only.
+    pub modes: Option<Vec<TwitterMode>>,
     pub request_interval: u64,
 }
 
+/// How users can send the challenge to the registrar account.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TwitterMode {
+    DirectMessages,
+    /// Public tweets mentioning the registrar account.
+    Tweets,
+}
+
+/// The API version is determined by the specified credentials.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum TwitterApiConfig {
+    /// API v1.1, authenticated with OAuth 1.0a.
+    V1 {
+        api_key: String,
+        api_secret: String,
+        token: String,
+        token_secret: String,
+    },
+    /// API v2, authenticated with an OAuth 2.0 (user context) access token.
+    V2 { bearer_token: String },
+}
+
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub struct EmailConfig {
@@ -119,9 +243,109 @@ pub struct EmailConfig {
     pub inbox: String,
     pub user: String,
     pub password: String,
+    /// Wait for new messages via IMAP IDLE instead of polling every
+    /// `request_interval` seconds. Defaults to `false`.
+    pub idle: Option<bool>,
+    /// Defaults to `gmail`.
+    pub provider: Option<EmailProvider>,
+    /// Defaults to 993 (`tls`) or 143 (`starttls`).
+    pub imap_port: Option<u16>,
+    /// Defaults to `tls`.
+    pub imap_security: Option<EmailSecurity>,
+    /// Defaults to 465 (`tls`) or 587 (`starttls`).
+    pub smtp_port: Option<u16>,
+    /// Defaults to `tls`.
+    pub smtp_security: Option<EmailSecurity>,
+    /// Authentication of the sender, disabled if not specified.
+    pub sender_auth: Option<SenderAuthConfig>,
+    pub request_interval: u64,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailProvider {
+    /// Uses the Gmail specific search syntax.
+    Gmail,
+    /// Uses the standardized IMAP search queries, supported by most servers.
+    Standard,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct SenderAuthConfig {
+    pub policy: SenderAuthPolicy,
+    /// Trust the `Authentication-Results` header added by this server (e.g.
+    /// `mx.google.com`), if present. Otherwise, DKIM signatures are verified.
+    pub trusted_authserv_id: Option<String>,
+}
+
+/// What to do with messages whose sender could not be authenticated.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SenderAuthPolicy {
+    /// Record the failure on the field, but process the message anyway.
+    Flag,
+    /// Log the failure and drop the message.
+    Reject,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailSecurity {
+    /// Implicit TLS.
+    Tls,
+    Starttls,
+}
+
+/// Polling of the DNS TXT records and `.well-known` files of web fields.
+/// Disabled if not specified.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case", default)]
+pub struct WebConfig {
+    pub enabled: bool,
     pub request_interval: u64,
 }
 
+impl Default for WebConfig {
+    fn default() -> Self {
+        WebConfig {
+            enabled: false,
+            request_interval: 60,
+        }
+    }
+}
+
+/// Whether a second challenge is sent to the account of the given field type,
+/// which must then be submitted via the web interface. Defaults to email only.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case", default)]
+pub struct SecondChallengeConfig {
+    pub email: bool,
+    pub twitter: bool,
+    pub matrix: bool,
+}
+
+impl Default for SecondChallengeConfig {
+    fn default() -> Self {
+        SecondChallengeConfig {
+            email: true,
+            twitter: false,
+            matrix: false,
+        }
+    }
+}
+
+/// The format of the challenges per field type. Defaults to `hex`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct ChallengeFormatConfig {
+    pub email: Option<ChallengeFormat>,
+    pub twitter: Option<ChallengeFormat>,
+    pub matrix: Option<ChallengeFormat>,
+    pub web: Option<ChallengeFormat>,
+    pub pgp_fingerprint: Option<ChallengeFormat>,
+}
+
 fn open_config() -> Result<Config> {
     // Open config file.
     let content = fs::read_to_string("config.yaml")
@@ -139,9 +363,26 @@ fn open_config() -> Result<Config> {
 
 async fn config_adapter_listener(db: Database, config: AdapterConfig) -> Result<()> {
     let watchers = config.watcher.clone();
+    let db = db.with_networks(
+        watchers
+            .iter()
+            .map(|watcher| (watcher.network.clone(), watcher.ss58_prefix))
+            .collect(),
+    );
     let dn_config = config.display_name.clone();
+    let second_challenge = config.second_challenge.clone();
+    let challenge_formats = config.challenge_format.clone().unwrap_or_default();
+    let challenge_ttl = config.challenge_ttl;
     run_adapters(config.clone(), db.clone()).await?;
-    run_connector(db, watchers, dn_config).await
+    run_connector(
+        db,
+        watchers,
+        dn_config,
+        second_challenge,
+        challenge_formats,
+        challenge_ttl,
+    )
+    .await
 }
 
 async fn config_session_notifier(db: Database, not_config: NotifierConfig) -> Result<()> {
@@ -157,9 +398,28 @@ pub async fn run() -> Result<()> {
     let (db_config, instance) = (root.db, root.instance);
 
     info!("Initializing connection to database");
-    let db = Database::new(&db_config.uri, &db_config.name).await?;
+    let mut db = Database::new(&db_config.uri, &db_config.name).await?;
     db.connectivity_check().await?;
 
+    if let Some(lockout) = root.lockout {
+        info!(
+            "Locking fields after {} failed attempts",
+            lockout.max_attempts
+        );
+        db = db.with_lockout(lockout);
+    }
+
+    if let Some(policies) = root.judgement_policy {
+        for (chain, policy) in &policies {
+            info!(
+                "Judgement policy for {}: {:?}",
+                chain.as_str(),
+                policy.fields
+            );
+        }
+        db = db.with_judgement_policies(policies);
+    }
+
     match instance {
         InstanceType::AdapterListener(config) => {
             info!("Starting adapter listener instance");
@@ -0,0 +1,450 @@
+use crate::connector::{
+    AckResponse, DisplayNameEntryRaw, EventType, Handshake, JudgementRequest, JudgementResponse,
+    ResponseMessage,
+};
+use crate::Result;
+use actix::prelude::*;
+use actix_broker::{Broker, BrokerSubscribe, SystemBroker};
+use actix_web::{web, App, Error as ActixError, HttpRequest, HttpResponse, HttpServer};
+use actix_web_actors::ws;
+use serde::Serialize;
+use std::fs;
+use std::io::BufRead;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::sleep;
+
+const DEFAULT_LISTEN_ADDRESS: &str = "127.0.0.1:8000";
+
+/// The script of the mock Watcher. Every value is optional.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct MockWatcherScript {
+    /// The address to listen on, to be used as the `endpoint` of a watcher.
+    #[serde(default = "default_listen_address")]
+    pub listen: String,
+    /// Whether received judgements are acknowledged as given on-chain.
+    #[serde(default)]
+    pub ack: bool,
+    /// Returned on `pendingJudgementsRequest`.
+    #[serde(default)]
+    pub pending: Vec<JudgementRequest>,
+    /// Returned on `displayNamesRequest`.
+    #[serde(default)]
+    pub display_names: Vec<DisplayNameEntryRaw>,
+    /// Sent as `newJudgementRequest` once the delay (in seconds, since
+    /// startup) has passed.
+    #[serde(default)]
+    pub requests: Vec<ScriptedRequest>,
+}
+
+impl Default for MockWatcherScript {
+    fn default() -> Self {
+        MockWatcherScript {
+            listen: default_listen_address(),
+            ack: false,
+            pending: vec![],
+            display_names: vec![],
+            requests: vec![],
+        }
+    }
+}
+
+fn default_listen_address() -> String {
+    DEFAULT_LISTEN_ADDRESS.to_string()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ScriptedRequest {
+    #[serde(default)]
+    pub delay: u64,
+    #[serde(flatten)]
+    pub request: JudgementRequest,
+}
+
+/// What the mock Watcher considers to be on-chain.
+struct MockState {
+    pending: Vec<JudgementRequest>,
+    display_names: Vec<DisplayNameEntryRaw>,
+    ack: bool,
+}
+
+impl MockState {
+    // Replaces the pending request of the same address, if any.
+    fn insert_request(&mut self, req: JudgementRequest) {
+        self.pending
+            .retain(|pending| pending.address != req.address);
+        self.pending.push(req);
+    }
+}
+
+/// A raw message which is sent to every connected challenger.
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "()")]
+struct BroadcastMessage(String);
+
+pub async fn run_mock_watcher(script: Option<String>) -> Result<()> {
+    let script = match script {
+        Some(path) => {
+            let content = fs::read_to_string(&path)
+                .map_err(|err| anyhow!("Failed to open script at '{}': {:?}", path, err))?;
+
+            serde_yaml::from_str::<MockWatcherScript>(&content)
+                .map_err(|err| anyhow!("Failed to parse script: {:?}", err))?
+        }
+        None => MockWatcherScript::default(),
+    };
+
+    let listen = script.listen;
+    let state = Arc::new(Mutex::new(MockState {
+        pending: script.pending,
+        display_names: script.display_names,
+        ack: script.ack,
+    }));
+
+    for scripted in script.requests {
+        let state = Arc::clone(&state);
+
+        actix::spawn(async move {
+            sleep(Duration::from_secs(scripted.delay)).await;
+            send_judgement_request(&state, scripted.request);
+        });
+    }
+
+    // Read interactive input from stdin. The blocking reads happen on a
+    // separate thread.
+    let (sender, mut recv) = mpsc::unbounded_channel();
+    thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    let input_state = Arc::clone(&state);
+    actix::spawn(async move {
+        while let Some(line) = recv.recv().await {
+            if let Err(err) = process_input(&input_state, &line) {
+                error!("Invalid input: {:?}", err);
+            }
+        }
+    });
+
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(web::Data::from(Arc::clone(&state)))
+            .default_service(web::to(mock_watcher_route))
+    })
+    .bind(listen.as_str())?;
+
+    info!("Mock Watcher listening on ws://{}", listen);
+    server.run().await?;
+
+    Ok(())
+}
+
+/// Processes a line of interactive input, which is either a full Watcher
+/// event (`{"event": ..., "data": ...}`) or a judgement request.
+fn process_input(state: &Mutex<MockState>, line: &str) -> Result<()> {
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok(());
+    }
+
+    if let Ok(msg) = serde_json::from_str::<ResponseMessage<serde_json::Value>>(line) {
+        if msg.event == EventType::NewJudgementRequest {
+            send_judgement_request(state, serde_json::from_value(msg.data)?);
+        } else {
+            Broker::<SystemBroker>::issue_async(BroadcastMessage(line.to_string()));
+        }
+
+        return Ok(());
+    }
+
+    let req = serde_json::from_str::<JudgementRequest>(line)
+        .map_err(|err| anyhow!("expected a Watcher event or a judgement request: {}", err))?;
+
+    send_judgement_request(state, req);
+
+    Ok(())
+}
+
+fn send_judgement_request(state: &Mutex<MockState>, req: JudgementRequest) {
+    info!("Sending judgement request of {}", req.address.as_str());
+
+    let msg = serde_json::to_string(&ResponseMessage {
+        event: EventType::NewJudgementRequest,
+        data: &req,
+    })
+    .unwrap();
+
+    state.lock().unwrap().insert_request(req);
+    Broker::<SystemBroker>::issue_async(BroadcastMessage(msg));
+}
+
+async fn mock_watcher_route(
+    req: HttpRequest,
+    stream: web::Payload,
+    state: web::Data<Mutex<MockState>>,
+) -> std::result::Result<HttpResponse, ActixError> {
+    ws::start(
+        MockWatcherSession {
+            state: state.into_inner(),
+        },
+        &req,
+        stream,
+    )
+}
+
+/// The websocket session of a connected challenger.
+struct MockWatcherSession {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockWatcherSession {
+    fn send<T: Serialize>(ctx: &mut ws::WebsocketContext<Self>, event: EventType, data: T) {
+        match serde_json::to_string(&ResponseMessage { event, data }) {
+            Ok(msg) => ctx.text(msg),
+            Err(err) => error!("Failed to serialize message: {:?}", err),
+        }
+    }
+    fn process_event(&mut self, text: &str, ctx: &mut ws::WebsocketContext<Self>) -> Result<()> {
+        let msg: ResponseMessage<serde_json::Value> = serde_json::from_str(text)?;
+
+        match msg.event {
+            EventType::Handshake => {
+                let handshake: Handshake = serde_json::from_value(msg.data)?;
+                debug!("Received handshake: {:?}", handshake);
+
+                Self::send(ctx, EventType::HandshakeResponse, Handshake::supported());
+            }
+            EventType::PendingJudgementsRequest => {
+                let state = self.state.lock().unwrap();
+                Self::send(ctx, EventType::PendingJudgementsResponse, &state.pending);
+            }
+            EventType::DisplayNamesRequest => {
+                let state = self.state.lock().unwrap();
+                Self::send(ctx, EventType::DisplayNamesResponse, &state.display_names);
+            }
+            EventType::JudgementResult => {
+                let judgement: JudgementResponse = serde_json::from_value(msg.data)?;
+                println!("{}", text);
+
+                let mut state = self.state.lock().unwrap();
+                if state.ack {
+                    state
+                        .pending
+                        .retain(|pending| pending.address != judgement.address);
+
+                    Self::send(
+                        ctx,
+                        EventType::Ack,
+                        AckResponse::judgement_given(judgement.address, judgement.correlation_id),
+                    );
+                }
+            }
+            _ => warn!("Ignoring unexpected event: {:?}", msg.event),
+        }
+
+        Ok(())
+    }
+}
+
+impl Actor for MockWatcherSession {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        info!("Challenger connected");
+        self.subscribe_system_async::<BroadcastMessage>(ctx);
+    }
+    fn stopped(&mut self, _ctx: &mut Self::Context) {
+        info!("Challenger disconnected");
+    }
+}
+
+impl StreamHandler<std::result::Result<ws::Message, ws::ProtocolError>> for MockWatcherSession {
+    fn handle(
+        &mut self,
+        msg: std::result::Result<ws::Message, ws::ProtocolError>,
+        ctx: &mut Self::Context,
+    ) {
+        let msg = if let Ok(msg) = msg {
+            msg
+        } else {
+            ctx.stop();
+            return;
+        };
+
+        match msg {
+            ws::Message::Text(text) => {
+                if let Err(err) = self.process_event(&text, ctx) {
+                    error!("Failed to process message from challenger: {:?}", err);
+                }
+            }
+            ws::Message::Ping(b) => {
+                ctx.pong(&b);
+            }
+            ws::Message::Close(reason) => {
+                ctx.close(reason);
+                ctx.stop();
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Handler<BroadcastMessage> for MockWatcherSession {
+    type Result = ();
+
+    fn handle(&mut self, msg: BroadcastMessage, ctx: &mut Self::Context) -> Self::Result {
+        ctx.text(msg.0);
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::connector::AccountType;
+    use crate::primitives::IdentityContext;
+    use actix_test::{start, TestServer};
+    use futures::{SinkExt, StreamExt};
+
+    fn mock_state() -> Arc<Mutex<MockState>> {
+        Arc::new(Mutex::new(MockState {
+            pending: vec![JudgementRequest::alice()],
+            display_names: vec![],
+            ack: true,
+        }))
+    }
+
+    /// Serves the mock Watcher, with Alice's judgement request pending.
+    pub fn start_mock_watcher() -> TestServer {
+        let state = mock_state();
+
+        start(move || {
+            App::new()
+                .app_data(web::Data::from(Arc::clone(&state)))
+                .default_service(web::to(mock_watcher_route))
+        })
+    }
+
+    #[test]
+    fn parse_mock_watcher_script() {
+        let script: MockWatcherScript = serde_yaml::from_str(&format!(
+            r#"
+            ack: true
+            display_names:
+              - address: {alice}
+                display_name: Alice
+            requests:
+              - delay: 5
+                address: {alice}
+                accounts:
+                  email: alice@email.com
+            "#,
+            alice = IdentityContext::alice().address.as_str()
+        ))
+        .unwrap();
+
+        assert_eq!(script.listen, DEFAULT_LISTEN_ADDRESS);
+        assert!(script.ack);
+        assert!(script.pending.is_empty());
+        assert_eq!(script.display_names[0].display_name, "Alice");
+        assert_eq!(script.requests[0].delay, 5);
+        assert_eq!(
+            script.requests[0].request.accounts[&AccountType::Email],
+            "alice@email.com"
+        );
+    }
+
+    #[actix::test]
+    async fn process_interactive_input() {
+        let state = mock_state();
+        let bob = serde_json::to_string(&JudgementRequest::bob()).unwrap();
+
+        // Plain judgement requests and full events are accepted.
+        process_input(&state, &bob).unwrap();
+        process_input(
+            &state,
+            &format!(r#"{{"event":"newJudgementRequest","data":{}}}"#, bob),
+        )
+        .unwrap();
+        process_input(&state, "").unwrap();
+        assert_eq!(state.lock().unwrap().pending.len(), 2);
+
+        assert!(process_input(&state, r#"{"address":"invalid","accounts":{}}"#).is_err());
+        assert!(process_input(&state, "judgement").is_err());
+    }
+
+    #[actix::test]
+    async fn serve_watcher_events() {
+        let mut srv = start_mock_watcher();
+
+        let mut stream = srv.ws_at("/").await.unwrap();
+
+        async fn request(
+            stream: &mut actix_codec::Framed<
+                impl actix_codec::AsyncRead + actix_codec::AsyncWrite + Unpin,
+                awc::ws::Codec,
+            >,
+            msg: String,
+        ) -> ResponseMessage<serde_json::Value> {
+            stream
+                .send(awc::ws::Message::Text(msg.into()))
+                .await
+                .unwrap();
+
+            match stream.next().await.unwrap().unwrap() {
+                awc::ws::Frame::Text(text) => serde_json::from_slice(&text).unwrap(),
+                frame => panic!("unexpected frame: {:?}", frame),
+            }
+        }
+
+        let resp = request(
+            &mut stream,
+            serde_json::to_string(&ResponseMessage {
+                event: EventType::Handshake,
+                data: Handshake::legacy(),
+            })
+            .unwrap(),
+        )
+        .await;
+        assert_eq!(resp.event, EventType::HandshakeResponse);
+
+        let resp = request(
+            &mut stream,
+            r#"{"event":"pendingJudgementsRequest","data":null}"#.to_string(),
+        )
+        .await;
+        assert_eq!(resp.event, EventType::PendingJudgementsResponse);
+        assert_eq!(resp.data.as_array().unwrap().len(), 1);
+
+        // The judgement is acknowledged and no longer pending.
+        let resp = request(
+            &mut stream,
+            format!(
+                r#"{{"event":"judgementResult","data":{{"address":"{}","judgement":"reasonable","verified":[],"correlationId":"abc"}}}}"#,
+                IdentityContext::alice().address.as_str()
+            ),
+        )
+        .await;
+        assert_eq!(resp.event, EventType::Ack);
+        assert_eq!(resp.data["code"], "judgementGiven");
+        assert_eq!(resp.data["correlationId"], "abc");
+
+        let resp = request(
+            &mut stream,
+            r#"{"event":"pendingJudgementsRequest","data":null}"#.to_string(),
+        )
+        .await;
+        assert!(resp.data.as_array().unwrap().is_empty());
+    }
+}
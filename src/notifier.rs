@@ -19,12 +19,19 @@ pub async fn run_session_notifier(mut db: Database, server: Addr<LookupServer>)
             let state = match cache.get(event.context()) {
                 Some(state) => state.clone(),
                 None => {
-                    let state = db
-                        .fetch_judgement_state(event.context())
-                        .await?
-                        .ok_or_else(|| {
-                            anyhow!("No identity state found for context: {:?}", event.context())
-                        })?;
+                    // Removed identities are only found in the archive.
+                    let state = match db.fetch_judgement_state(event.context()).await? {
+                        Some(state) => state,
+                        None => db
+                            .fetch_archived_judgement_state(event.context())
+                            .await?
+                            .ok_or_else(|| {
+                                anyhow!(
+                                    "No identity state found for context: {:?}",
+                                    event.context()
+                                )
+                            })?,
+                    };
 
                     cache.insert(event.context().clone(), state.clone());
 
@@ -1,7 +1,14 @@
 use crate::adapters::admin::RawFieldName;
-use crate::connector::{AccountType, DisplayNameEntry, VerifiedEntry};
+use crate::connector::{AccountType, DisplayNameEntry, Judgement, VerifiedEntry};
+use crate::wordlist::WORDLIST;
+use crate::{
+    ChallengeFormatConfig, FieldPolicy, JudgementPolicy, LockoutConfig, Result,
+    SecondChallengeConfig,
+};
 use actix::Message;
 use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::str::FromStr;
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -19,35 +26,106 @@ impl IdentityContext {
     }
 }
 
+/// An SS58 encoded address. Addresses are validated when parsed or
+/// deserialized, including the checksum.
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
+#[serde(try_from = "String")]
 pub struct ChainAddress(String);
 
 impl ChainAddress {
     pub fn as_str(&self) -> &str {
         self.0.as_str()
     }
+    /// Decodes the (SS58 encoded) address into the network prefix and the
+    /// public key of the account.
+    pub fn decode(&self) -> Result<(u16, [u8; 32])> {
+        use blake2::{Blake2b512, Digest};
+
+        let decoded = bs58::decode(self.as_str())
+            .into_vec()
+            .map_err(|err| anyhow!("invalid base58 encoding of address: {:?}", err))?;
+
+        // One or two bytes of network prefix, followed by the public key and
+        // a two byte checksum.
+        let (prefix, prefix_len) = match (decoded.len(), decoded.first()) {
+            (35, Some(&b0)) if b0 < 64 => (b0 as u16, 1),
+            (36, Some(&b0)) if (64..128).contains(&b0) => {
+                let b1 = decoded[1];
+                let lower = ((b0 & 0b0011_1111) << 2) | (b1 >> 6);
+                let upper = b1 & 0b0011_1111;
+
+                (lower as u16 | ((upper as u16) << 8), 2)
+            }
+            (35, _) | (36, _) => return Err(anyhow!("address has an invalid prefix")),
+            _ => return Err(anyhow!("address has an invalid length")),
+        };
+
+        let (payload, checksum) = decoded.split_at(decoded.len() - 2);
+        let hash = Blake2b512::new()
+            .chain_update(b"SS58PRE")
+            .chain_update(payload)
+            .finalize();
+
+        if &hash[..2] != checksum {
+            return Err(anyhow!("address has an invalid checksum"));
+        }
+
+        let mut public_key = [0; 32];
+        public_key.copy_from_slice(&payload[prefix_len..]);
+
+        Ok((prefix, public_key))
+    }
+    /// The SS58 prefix, which identifies the network of the address.
+    pub fn ss58_prefix(&self) -> Result<u16> {
+        self.decode().map(|(prefix, _)| prefix)
+    }
+    pub fn public_key(&self) -> Result<[u8; 32]> {
+        self.decode().map(|(_, public_key)| public_key)
+    }
 }
 
-impl From<String> for ChainAddress {
-    fn from(v: String) -> Self {
-        ChainAddress(v)
+impl FromStr for ChainAddress {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        ChainAddress::try_from(s.to_string())
     }
 }
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
-pub enum ChainName {
-    Polkadot,
-    Kusama,
+impl TryFrom<String> for ChainAddress {
+    type Error = anyhow::Error;
+
+    fn try_from(v: String) -> Result<Self> {
+        let address = ChainAddress(v);
+        address
+            .decode()
+            .map_err(|err| anyhow!("invalid address {}: {}", address.as_str(), err))?;
+
+        Ok(address)
+    }
 }
 
+/// The name of a configured network, e.g. `polkadot`, `kusama` or `westend`.
+/// Stored as a plain string, just like the former `Polkadot`/`Kusama` enum.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ChainName(String);
+
 impl ChainName {
     pub fn as_str(&self) -> &str {
-        match self {
-            ChainName::Polkadot => "polkadot",
-            ChainName::Kusama => "kusama",
-        }
+        self.0.as_str()
+    }
+}
+
+impl From<String> for ChainName {
+    fn from(v: String) -> Self {
+        ChainName(v)
+    }
+}
+
+impl From<&str> for ChainName {
+    fn from(v: &str) -> Self {
+        ChainName(v.to_string())
     }
 }
 
@@ -57,17 +135,60 @@ pub struct IdentityField {
     pub value: IdentityFieldValue,
     pub challenge: ChallengeType,
     pub failed_attempts: usize,
+    // The failed attempts within the window of the lockout policy.
+    #[serde(default)]
+    pub recent_failures: Vec<Timestamp>,
+    #[serde(default)]
+    pub lock: Option<FieldLock>,
+    // Set if verified by an admin, required by `FieldPolicy::ManualReview`.
+    #[serde(default)]
+    pub manually_verified: bool,
+    // The handle of the admin who verified the field manually.
+    #[serde(default)]
+    pub verified_by: Option<String>,
+    // Why the sender of a message for the field could not be authenticated,
+    // if flagged by the email adapter.
+    #[serde(default)]
+    pub sender_auth_failure: Option<String>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct FieldLock {
+    pub locked_at: Timestamp,
+    // Locked until unlocked by an admin if not set.
+    pub expires_at: Option<Timestamp>,
 }
 
 impl IdentityField {
-    pub fn new(val: IdentityFieldValue) -> Self {
+    pub fn new(
+        val: IdentityFieldValue,
+        second_challenge: &SecondChallengeConfig,
+        challenge_formats: &ChallengeFormatConfig,
+    ) -> Self {
         use IdentityFieldValue::*;
 
+        let format = match val {
+            Email(_) => challenge_formats.email,
+            Twitter(_) => challenge_formats.twitter,
+            Matrix(_) => challenge_formats.matrix,
+            Web(_) => challenge_formats.web,
+            PGPFingerprint(_) => challenge_formats.pgp_fingerprint,
+            _ => None,
+        }
+        .unwrap_or_default();
+
+        let second = |enabled: bool| {
+            if enabled {
+                Some(ExpectedMessage::with_format(format))
+            } else {
+                None
+            }
+        };
+
         let challenge = {
             match val {
                 LegalName(_) => ChallengeType::Unsupported { is_verified: None },
-                Web(_) => ChallengeType::Unsupported { is_verified: None },
-                PGPFingerprint(_) => ChallengeType::Unsupported { is_verified: None },
                 Image(_) => ChallengeType::Unsupported { is_verified: None },
                 Additional(_) => ChallengeType::Unsupported { is_verified: None },
                 DisplayName(_) => ChallengeType::DisplayNameCheck {
@@ -75,15 +196,23 @@ impl IdentityField {
                     violations: vec![],
                 },
                 Email(_) => ChallengeType::ExpectedMessage {
-                    expected: ExpectedMessage::random(),
-                    second: Some(ExpectedMessage::random()),
+                    expected: ExpectedMessage::with_format(format),
+                    second: second(second_challenge.email),
                 },
                 Twitter(_) => ChallengeType::ExpectedMessage {
-                    expected: ExpectedMessage::random(),
+                    expected: ExpectedMessage::with_format(format),
+                    second: second(second_challenge.twitter),
+                },
+                Web(_) => ChallengeType::ExpectedMessage {
+                    expected: ExpectedMessage::with_format(format),
                     second: None,
                 },
                 Matrix(_) => ChallengeType::ExpectedMessage {
-                    expected: ExpectedMessage::random(),
+                    expected: ExpectedMessage::with_format(format),
+                    second: second(second_challenge.matrix),
+                },
+                PGPFingerprint(_) => ChallengeType::ExpectedMessage {
+                    expected: ExpectedMessage::with_format(format),
                     second: None,
                 },
             }
@@ -93,6 +222,118 @@ impl IdentityField {
             value: val,
             challenge,
             failed_attempts: 0,
+            recent_failures: vec![],
+            lock: None,
+            manually_verified: false,
+            verified_by: None,
+            sender_auth_failure: None,
+        }
+    }
+    pub fn verification_method(&self) -> VerificationMethod {
+        if self.manually_verified {
+            return VerificationMethod::Manual;
+        }
+
+        if !self.challenge.is_verified() {
+            return VerificationMethod::Unverified;
+        }
+
+        match (&self.value, &self.challenge) {
+            (_, ChallengeType::DisplayNameCheck { .. }) => VerificationMethod::DisplayNameCheck,
+            // Only set by admins.
+            (_, ChallengeType::Unsupported { .. }) => VerificationMethod::Manual,
+            (IdentityFieldValue::Web(_), _) => VerificationMethod::Website,
+            (IdentityFieldValue::PGPFingerprint(_), _) => VerificationMethod::PgpSignature,
+            _ => VerificationMethod::Message,
+        }
+    }
+    pub fn is_locked(&self) -> bool {
+        match &self.lock {
+            Some(lock) => lock
+                .expires_at
+                .map(|expires_at| Timestamp::now().raw() < expires_at.raw())
+                .unwrap_or(true),
+            None => false,
+        }
+    }
+    /// Tracks a failed attempt. If the limit of the policy was reached, the
+    /// field is locked and its challenge is replaced. Returns whether the
+    /// field was locked.
+    pub fn register_failure(&mut self, policy: &LockoutConfig) -> bool {
+        let now = Timestamp::now();
+        let window_start = now.raw().saturating_sub(policy.window);
+
+        // Any (expired) lock is no longer relevant.
+        self.lock = None;
+        self.recent_failures
+            .retain(|timestamp| timestamp.raw() >= window_start);
+        self.recent_failures.push(now);
+
+        if self.recent_failures.len() < policy.max_attempts {
+            return false;
+        }
+
+        self.recent_failures.clear();
+        self.lock = Some(FieldLock {
+            locked_at: now,
+            expires_at: policy
+                .cooldown
+                .map(|cooldown| Timestamp::from(now.raw() + cooldown)),
+        });
+        self.regenerate_challenge();
+
+        true
+    }
+    /// Returns `false` if the field was not locked.
+    pub fn unlock(&mut self) -> bool {
+        self.recent_failures.clear();
+        self.lock.take().is_some()
+    }
+    /// Whether any of the unverified challenges was issued before the given
+    /// threshold. Challenges without a timestamp are considered to be issued
+    /// at `fallback`.
+    pub fn has_expired_challenge(&self, threshold: Timestamp, fallback: Timestamp) -> bool {
+        let is_expired = |message: &ExpectedMessage| {
+            !message.is_verified && message.issued_at.unwrap_or(fallback).raw() < threshold.raw()
+        };
+
+        match &self.challenge {
+            ChallengeType::ExpectedMessage { expected, second } => {
+                is_expired(expected) || second.as_ref().map(is_expired).unwrap_or(false)
+            }
+            _ => false,
+        }
+    }
+    /// Replaces the unverified challenges with new ones. Returns `false` if
+    /// there was nothing to replace.
+    pub fn regenerate_challenge(&mut self) -> bool {
+        let mut regenerated = false;
+
+        if let ChallengeType::ExpectedMessage { expected, second } = &mut self.challenge {
+            if !expected.is_verified {
+                *expected = ExpectedMessage::with_format(expected.format);
+                regenerated = true;
+            }
+
+            if let Some(second) = second {
+                if !second.is_verified {
+                    *second = ExpectedMessage::with_format(second.format);
+                    regenerated = true;
+                }
+            }
+        }
+
+        regenerated
+    }
+    /// Whether the first challenge was verified and the second challenge is
+    /// still pending.
+    pub fn is_awaiting_second_challenge(&self) -> bool {
+        match &self.challenge {
+            ChallengeType::ExpectedMessage {
+                expected,
+                second: Some(second),
+            } => expected.is_verified && !second.is_verified,
+            _ => false,
         }
     }
 }
@@ -138,21 +379,28 @@ impl ChallengeType {
 pub struct ExpectedMessage {
     pub value: String,
     pub is_verified: bool,
+    // Older entries did not track when the challenge was issued.
+    #[serde(default)]
+    pub issued_at: Option<Timestamp>,
+    #[serde(default)]
+    pub format: ChallengeFormat,
 }
 
 impl ExpectedMessage {
     pub fn random() -> Self {
-        use rand::{thread_rng, Rng};
-
-        let random: [u8; 16] = thread_rng().gen();
+        Self::with_format(ChallengeFormat::Hex)
+    }
+    pub fn with_format(format: ChallengeFormat) -> Self {
         ExpectedMessage {
-            value: hex::encode(random),
+            value: format.generate(),
             is_verified: false,
+            issued_at: Some(Timestamp::now()),
+            format,
         }
     }
     pub fn verify_message(&mut self, message: &ExternalMessage) -> bool {
         for value in &message.values {
-            if value.0.contains(&self.value) {
+            if self.is_contained_in(&value.0) {
                 self.set_verified();
                 return true;
             }
@@ -160,6 +408,13 @@ impl ExpectedMessage {
 
         false
     }
+    /// Whether the text contains the challenge. Case, whitespace and
+    /// separators are ignored, since users tend to mistype those or clients
+    /// wrap the text.
+    pub fn is_contained_in(&self, text: &str) -> bool {
+        let challenge = normalize_challenge(&self.value);
+        !challenge.is_empty() && normalize_challenge(text).contains(&challenge)
+    }
     pub fn set_verified(&mut self) {
         self.is_verified = true;
     }
@@ -169,6 +424,75 @@ impl ExpectedMessage {
     }
 }
 
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChallengeFormat {
+    /// 32 hex characters (128 bits), e.g. `8c2b0e9f...`.
+    #[default]
+    Hex,
+    /// Sixteen words of `crate::wordlist::WORDLIST` (8 bits each, 128 bits
+    /// in total), e.g. `maple otter ...`.
+    Words,
+    /// 24 base32 characters (120 bits) in groups of four, e.g.
+    /// `K7QX-M2RA-...`.
+    Base32,
+}
+
+impl ChallengeFormat {
+    fn generate(&self) -> String {
+        use rand::{thread_rng, Rng};
+
+        const BASE32_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+        let mut rng = thread_rng();
+        match self {
+            ChallengeFormat::Hex => {
+                let random: [u8; 16] = rng.gen();
+                hex::encode(random)
+            }
+            ChallengeFormat::Words => (0..16)
+                .map(|_| WORDLIST[rng.gen_range(0..WORDLIST.len())])
+                .collect::<Vec<&str>>()
+                .join(" "),
+            ChallengeFormat::Base32 => (0..6)
+                .map(|_| {
+                    (0..4)
+                        .map(|_| BASE32_ALPHABET[rng.gen_range(0..BASE32_ALPHABET.len())] as char)
+                        .collect::<String>()
+                })
+                .collect::<Vec<String>>()
+                .join("-"),
+        }
+    }
+}
+
+/// Whether the text could be a challenge of any `ChallengeFormat`, i.e. it
+/// is short, plain text and of the expected length once normalized.
+pub fn is_challenge_shaped(text: &str) -> bool {
+    // Hex (32), base32 (24) or up to sixteen words of three to eight
+    // characters. Older word challenges consist of six words.
+    const MIN_LENGTH: usize = 16;
+    const MAX_LENGTH: usize = 128;
+    // Generous for separators and whitespace.
+    const MAX_RAW_LENGTH: usize = 256;
+
+    let text = text.trim();
+    if text.len() > MAX_RAW_LENGTH || text.contains('<') {
+        return false;
+    }
+
+    let len = normalize_challenge(text).len();
+    (MIN_LENGTH..=MAX_LENGTH).contains(&len)
+}
+
+/// Only keeps the (lowercase) alphanumeric characters.
+fn normalize_challenge(val: &str) -> String {
+    val.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case", tag = "type", content = "value")]
 pub enum IdentityFieldValue {
@@ -178,7 +502,9 @@ pub enum IdentityFieldValue {
     Web(String),
     Twitter(String),
     Matrix(String),
-    PGPFingerprint(()),
+    // Older entries did not store the fingerprint.
+    #[serde(deserialize_with = "deserialize_fingerprint")]
+    PGPFingerprint(String),
     Image(()),
     Additional(()),
 }
@@ -192,7 +518,9 @@ impl IdentityFieldValue {
             IdentityFieldValue::Web(val) => (AccountType::Web, val.to_string()),
             IdentityFieldValue::Twitter(val) => (AccountType::Twitter, val.to_string()),
             IdentityFieldValue::Matrix(val) => (AccountType::Matrix, val.to_string()),
-            IdentityFieldValue::PGPFingerprint(_) => (AccountType::PGPFingerprint, String::new()),
+            IdentityFieldValue::PGPFingerprint(val) => {
+                (AccountType::PGPFingerprint, val.to_string())
+            }
             IdentityFieldValue::Image(_) => (AccountType::Image, String::new()),
             IdentityFieldValue::Additional(_) => (AccountType::Additional, String::new()),
         }
@@ -205,12 +533,35 @@ impl IdentityFieldValue {
             (IdentityFieldValue::Web(val), AccountType::Web) => val == value,
             (IdentityFieldValue::Twitter(val), AccountType::Twitter) => val == value,
             (IdentityFieldValue::Matrix(val), AccountType::Matrix) => val == value,
-            (IdentityFieldValue::PGPFingerprint(_), AccountType::PGPFingerprint) => true,
+            (IdentityFieldValue::PGPFingerprint(val), AccountType::PGPFingerprint) => {
+                normalize_fingerprint(val) == normalize_fingerprint(value)
+            }
             (IdentityFieldValue::Image(_), AccountType::Image) => true,
             (IdentityFieldValue::Additional(_), AccountType::Additional) => true,
             _ => false,
         }
     }
+    pub fn matches_raw_name(&self, name: &RawFieldName) -> bool {
+        matches!(
+            (self, name),
+            (_, RawFieldName::All)
+                | (IdentityFieldValue::LegalName(_), RawFieldName::LegalName)
+                | (
+                    IdentityFieldValue::DisplayName(_),
+                    RawFieldName::DisplayName
+                )
+                | (IdentityFieldValue::Email(_), RawFieldName::Email)
+                | (IdentityFieldValue::Web(_), RawFieldName::Web)
+                | (IdentityFieldValue::Twitter(_), RawFieldName::Twitter)
+                | (IdentityFieldValue::Matrix(_), RawFieldName::Matrix)
+                | (
+                    IdentityFieldValue::PGPFingerprint(_),
+                    RawFieldName::PGPFingerprint
+                )
+                | (IdentityFieldValue::Image(_), RawFieldName::Image)
+                | (IdentityFieldValue::Additional(_), RawFieldName::Additional)
+        )
+    }
     pub fn matches_origin(&self, message: &ExternalMessage) -> bool {
         match self {
             IdentityFieldValue::Email(n1) => match &message.origin {
@@ -225,11 +576,39 @@ impl IdentityFieldValue {
                 ExternalMessageType::Matrix(n2) => n1 == n2,
                 _ => false,
             },
+            IdentityFieldValue::Web(n1) => match &message.origin {
+                ExternalMessageType::Web(n2) => n1 == n2,
+                _ => false,
+            },
+            IdentityFieldValue::PGPFingerprint(n1) => match &message.origin {
+                ExternalMessageType::PGPFingerprint(n2) => n1 == n2,
+                _ => false,
+            },
             _ => false,
         }
     }
 }
 
+fn deserialize_fingerprint<'de, D>(deserializer: D) -> std::result::Result<String, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let val: Option<String> = serde::Deserialize::deserialize(deserializer)?;
+    Ok(val.unwrap_or_default())
+}
+
+/// Strips the optional `0x` prefix as well as any whitespace from the
+/// fingerprint, so it can be compared with the one of a public key.
+pub fn normalize_fingerprint(val: &str) -> String {
+    let val = val.trim();
+    val.strip_prefix("0x")
+        .unwrap_or(val)
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_uppercase()
+}
+
 // The blanked judgement state sent to the frontend UI. Does not include the
 // secondary challenge. NOTE: `JudgementState` could be converted to take a
 // generic and `JudgementStateBlanked` could just be a type alias.
@@ -241,6 +620,9 @@ pub struct JudgementStateBlanked {
     pub inserted_timestamp: Timestamp,
     pub completion_timestamp: Option<Timestamp>,
     pub judgement_submitted: bool,
+    pub judgement_simulated: bool,
+    pub ownership: Option<OwnershipChallenge>,
+    pub rejection: Option<Rejection>,
     pub fields: Vec<IdentityFieldBlanked>,
 }
 
@@ -250,6 +632,7 @@ pub struct IdentityFieldBlanked {
     pub value: IdentityFieldValue,
     pub challenge: ChallengeTypeBlanked,
     failed_attempts: usize,
+    pub lock: Option<FieldLock>,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
@@ -278,12 +661,17 @@ pub struct ExpectedMessageBlanked {
 
 impl From<JudgementState> for JudgementStateBlanked {
     fn from(s: JudgementState) -> Self {
+        let reveal_challenges = s.reveals_challenges();
+
         JudgementStateBlanked {
             context: s.context,
             is_fully_verified: s.is_fully_verified,
             inserted_timestamp: s.inserted_timestamp,
             completion_timestamp: s.completion_timestamp,
             judgement_submitted: s.judgement_submitted,
+            judgement_simulated: s.judgement_simulated,
+            ownership: s.ownership,
+            rejection: s.rejection,
             fields: s
                 .fields
                 .into_iter()
@@ -291,7 +679,16 @@ impl From<JudgementState> for JudgementStateBlanked {
                     value: f.value,
                     challenge: {
                         match f.challenge {
-                            ChallengeType::ExpectedMessage { expected, second } => {
+                            ChallengeType::ExpectedMessage {
+                                mut expected,
+                                second,
+                            } => {
+                                // IMPORTANT: The challenge is blanked until
+                                // the ownership of the address was proven.
+                                if !reveal_challenges {
+                                    expected.value = String::new();
+                                }
+
                                 ChallengeTypeBlanked::ExpectedMessage {
                                     expected,
                                     second: second.map(|s| ExpectedMessageBlanked {
@@ -308,6 +705,7 @@ impl From<JudgementState> for JudgementStateBlanked {
                         }
                     },
                     failed_attempts: f.failed_attempts,
+                    lock: f.lock,
                 })
                 .collect(),
         }
@@ -322,26 +720,286 @@ pub struct JudgementState {
     pub inserted_timestamp: Timestamp,
     pub completion_timestamp: Option<Timestamp>,
     pub judgement_submitted: bool,
+    // Set if the judgement was only simulated (dry-run mode).
+    #[serde(default)]
+    pub judgement_simulated: bool,
     pub issue_judgement_at: Option<Timestamp>,
+    // Set if the user must prove the ownership of the address.
+    #[serde(default)]
+    pub ownership: Option<OwnershipChallenge>,
+    // Set if the identity was rejected by an admin.
+    #[serde(default)]
+    pub rejection: Option<Rejection>,
     pub fields: Vec<IdentityField>,
 }
 
+/// An entry of the judgement audit log, recorded when a judgement is sent to
+/// the Watcher. Entries are never modified.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct JudgementAuditEntry {
+    pub context: IdentityContext,
+    pub judgement: Judgement,
+    pub rejection: Option<Rejection>,
+    pub verified: Vec<VerifiedEntry>,
+    pub fields: Vec<FieldAudit>,
+    pub completion_timestamp: Option<Timestamp>,
+    pub timestamp: Timestamp,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct FieldAudit {
+    pub value: IdentityFieldValue,
+    pub method: VerificationMethod,
+    // The handle of the admin, for manual verifications.
+    pub admin: Option<String>,
+    // Set if the sender of a message for the field could not be
+    // authenticated.
+    #[serde(default)]
+    pub sender_auth_failure: Option<String>,
+}
+
+/// How a field was verified. The channel is implied by the field type.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerificationMethod {
+    /// The challenge was received via the channel of the field (email,
+    /// Twitter, Matrix), optionally with a second challenge.
+    Message,
+    /// The challenge was published on the website.
+    Website,
+    /// The challenge was signed by the PGP key.
+    PgpSignature,
+    DisplayNameCheck,
+    Manual,
+    /// Not verified, e.g. ignored by the judgement policy.
+    Unverified,
+}
+
+impl JudgementAuditEntry {
+    pub fn new(state: &JudgementState) -> Self {
+        JudgementAuditEntry {
+            context: state.context.clone(),
+            judgement: state.judgement(),
+            rejection: state.rejection.clone(),
+            verified: state.as_verified_entries(),
+            fields: state
+                .fields
+                .iter()
+                .map(|field| FieldAudit {
+                    value: field.value.clone(),
+                    method: field.verification_method(),
+                    admin: field.verified_by.clone(),
+                    sender_auth_failure: field.sender_auth_failure.clone(),
+                })
+                .collect(),
+            completion_timestamp: state.completion_timestamp,
+            timestamp: Timestamp::now(),
+        }
+    }
+}
+
+/// A judgement submission to the Watcher, tracked until the Watcher
+/// acknowledges that the judgement was given.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct JudgementSubmission {
+    pub context: IdentityContext,
+    // Distinguishes the submissions of the same identity, e.g. if the
+    // identity was verified again after a change.
+    pub completion_timestamp: Option<Timestamp>,
+    pub status: SubmissionStatus,
+    pub attempts: u32,
+    pub last_attempt: Timestamp,
+    pub next_attempt: Timestamp,
+    // The correlation IDs of all attempts.
+    #[serde(default)]
+    pub correlation_ids: Vec<String>,
+    // The last error reported by the Watcher.
+    #[serde(default)]
+    pub last_error: Option<String>,
+    // Whether the admins were notified about the failed submission.
+    pub reported: bool,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubmissionStatus {
+    Pending,
+    Acknowledged,
+    /// No acknowledgement was received after the maximum number of attempts.
+    Failed,
+}
+
+impl JudgementSubmission {
+    pub fn new(state: &JudgementState, next_attempt: Timestamp, correlation_id: String) -> Self {
+        JudgementSubmission {
+            context: state.context.clone(),
+            completion_timestamp: state.completion_timestamp,
+            status: SubmissionStatus::Pending,
+            attempts: 1,
+            last_attempt: Timestamp::now(),
+            next_attempt,
+            correlation_ids: vec![correlation_id],
+            last_error: None,
+            reported: false,
+        }
+    }
+}
+
+/// A negative judgement issued by an admin, overriding the verification
+/// state of the fields.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct Rejection {
+    pub judgement: Judgement,
+    pub reason: String,
+    pub timestamp: Timestamp,
+}
+
+/// When the ownership of the address must be proven by signing a nonce with
+/// the key of the account, configured per chain.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OwnershipProofMode {
+    /// The challenges are blanked until proven.
+    BeforeChallenges,
+    /// The challenges are revealed, but the judgement is not submitted until
+    /// proven.
+    BeforeJudgement,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct OwnershipChallenge {
+    pub nonce: String,
+    pub mode: OwnershipProofMode,
+    pub is_verified: bool,
+}
+
+impl OwnershipChallenge {
+    pub fn new(mode: OwnershipProofMode) -> Self {
+        use rand::{thread_rng, Rng};
+
+        let random: [u8; 16] = thread_rng().gen();
+        OwnershipChallenge {
+            nonce: format!("polkadot-registrar:{}", hex::encode(random)),
+            mode,
+            is_verified: false,
+        }
+    }
+    /// Verifies the (hex encoded) signature of the nonce, created by the key
+    /// of the given address. Both sr25519 and ed25519 keys are supported, as
+    /// well as nonces wrapped in `<Bytes>` tags, as done by most wallets.
+    pub fn verify_signature(&self, address: &ChainAddress, signature: &str) -> bool {
+        let public_key = match address.public_key() {
+            Ok(public_key) => public_key,
+            Err(err) => {
+                debug!("Failed to decode public key of {:?}: {:?}", address, err);
+                return false;
+            }
+        };
+
+        let signature = match hex::decode(signature.trim().trim_start_matches("0x")) {
+            Ok(signature) => signature,
+            Err(_) => return false,
+        };
+
+        let wrapped = format!("<Bytes>{}</Bytes>", self.nonce);
+        let messages = [self.nonce.as_bytes(), wrapped.as_bytes()];
+
+        // sr25519
+        if let (Ok(public_key), Ok(signature)) = (
+            schnorrkel::PublicKey::from_bytes(&public_key),
+            schnorrkel::Signature::from_bytes(&signature),
+        ) {
+            if messages.iter().any(|message| {
+                public_key
+                    .verify_simple(b"substrate", message, &signature)
+                    .is_ok()
+            }) {
+                return true;
+            }
+        }
+
+        // ed25519
+        if let (Ok(public_key), Ok(signature)) = (
+            ed25519_dalek::VerifyingKey::from_bytes(&public_key),
+            ed25519_dalek::Signature::from_slice(&signature),
+        ) {
+            if messages
+                .iter()
+                .any(|message| public_key.verify_strict(message, &signature).is_ok())
+            {
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
 impl JudgementState {
-    pub fn new(context: IdentityContext, fields: Vec<IdentityFieldValue>) -> Self {
+    pub fn new(
+        context: IdentityContext,
+        fields: Vec<IdentityFieldValue>,
+        second_challenge: &SecondChallengeConfig,
+        challenge_formats: &ChallengeFormatConfig,
+    ) -> Self {
         JudgementState {
             context,
             is_fully_verified: false,
             inserted_timestamp: Timestamp::now(),
             completion_timestamp: None,
             judgement_submitted: false,
+            judgement_simulated: false,
             issue_judgement_at: None,
-            fields: fields.into_iter().map(IdentityField::new).collect(),
+            ownership: None,
+            rejection: None,
+            fields: fields
+                .into_iter()
+                .map(|val| IdentityField::new(val, second_challenge, challenge_formats))
+                .collect(),
         }
     }
+    #[cfg(test)]
     pub fn check_full_verification(&self) -> bool {
-        self.fields
-            .iter()
-            .all(|field| field.challenge.is_verified())
+        self.check_full_verification_with(&JudgementPolicy::default())
+    }
+    /// Checks whether the identity is fully verified according to the given
+    /// policy.
+    pub fn check_full_verification_with(&self, policy: &JudgementPolicy) -> bool {
+        if self.rejection.is_some() {
+            return false;
+        }
+
+        let ownership_verified = self
+            .ownership
+            .as_ref()
+            .map(|ownership| ownership.is_verified)
+            .unwrap_or(true);
+
+        ownership_verified
+            && self.fields.iter().all(|field| {
+                match policy.field_policy(&field.value.as_account_type().0) {
+                    FieldPolicy::Required => field.challenge.is_verified(),
+                    FieldPolicy::Ignored => true,
+                    FieldPolicy::ManualReview => {
+                        field.manually_verified && field.challenge.is_verified()
+                    }
+                }
+            })
+    }
+    /// Whether the challenges can be revealed to the user, respectively
+    /// whether the ownership of the address was proven if required.
+    pub fn reveals_challenges(&self) -> bool {
+        match &self.ownership {
+            Some(ownership) => {
+                ownership.is_verified || ownership.mode != OwnershipProofMode::BeforeChallenges
+            }
+            None => true,
+        }
     }
     pub fn display_name(&self) -> Option<&str> {
         self.fields
@@ -369,9 +1027,21 @@ impl JudgementState {
 
         true
     }
+    /// The judgement which is submitted to the Watcher.
+    pub fn judgement(&self) -> Judgement {
+        self.rejection
+            .as_ref()
+            .map(|rejection| rejection.judgement)
+            .unwrap_or(Judgement::Reasonable)
+    }
     pub fn as_verified_entries(&self) -> Vec<VerifiedEntry> {
         let mut list = vec![];
 
+        // Rejected identities have no verified entries.
+        if self.rejection.is_some() {
+            return list;
+        }
+
         for field in &self.fields {
             let (account_ty, value) = field.value.as_account_type();
             list.push(VerifiedEntry { account_ty, value });
@@ -397,6 +1067,8 @@ pub enum ExternalMessageType {
     Email(String),
     Twitter(String),
     Matrix(String),
+    Web(String),
+    PGPFingerprint(String),
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
@@ -447,6 +1119,12 @@ impl Timestamp {
     }
 }
 
+impl From<u64> for Timestamp {
+    fn from(val: u64) -> Self {
+        Timestamp(val)
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub struct MessagePart(String);
@@ -522,6 +1200,30 @@ pub enum NotificationMessage {
     FullManualVerification {
         context: IdentityContext,
     },
+    ChallengeRegenerated {
+        context: IdentityContext,
+        field: IdentityFieldValue,
+    },
+    FieldLocked {
+        context: IdentityContext,
+        field: IdentityFieldValue,
+    },
+    FieldUnlocked {
+        context: IdentityContext,
+        field: IdentityFieldValue,
+    },
+    OwnershipVerified {
+        context: IdentityContext,
+    },
+    IdentityRejected {
+        context: IdentityContext,
+    },
+    IdentityRemoved {
+        context: IdentityContext,
+    },
+    JudgementSubmissionFailed {
+        context: IdentityContext,
+    },
 }
 
 impl NotificationMessage {
@@ -540,17 +1242,17 @@ impl NotificationMessage {
             JudgementProvided { context } => context,
             ManuallyVerified { context, field: _ } => context,
             FullManualVerification { context } => context,
+            ChallengeRegenerated { context, field: _ } => context,
+            FieldLocked { context, field: _ } => context,
+            FieldUnlocked { context, field: _ } => context,
+            OwnershipVerified { context } => context,
+            IdentityRejected { context } => context,
+            IdentityRemoved { context } => context,
+            JudgementSubmissionFailed { context } => context,
         }
     }
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
-pub struct IdentityJudged {
-    context: IdentityContext,
-    timestamp: Timestamp,
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -561,7 +1263,7 @@ mod tests {
                 address: ChainAddress(
                     "1a2YiGNu1UUhJtihq8961c7FZtWGQuWDVMWTNBKJdmpGhZP".to_string(),
                 ),
-                chain: ChainName::Polkadot,
+                chain: ChainName::from("polkadot"),
             }
         }
         pub fn bob() -> Self {
@@ -569,25 +1271,31 @@ mod tests {
                 address: ChainAddress(
                     "1b3NhsSEqWSQwS6nPGKgCrSjv9Kp13CnhraLV5Coyd8ooXB".to_string(),
                 ),
-                chain: ChainName::Polkadot,
+                chain: ChainName::from("polkadot"),
             }
         }
     }
 
     impl JudgementState {
         pub fn alice() -> Self {
+            let config = SecondChallengeConfig::default();
+            let formats = ChallengeFormatConfig::default();
+
             JudgementState {
                 context: IdentityContext::alice(),
                 is_fully_verified: false,
                 inserted_timestamp: Timestamp::now(),
                 completion_timestamp: None,
                 judgement_submitted: false,
+                judgement_simulated: false,
                 issue_judgement_at: None,
+                ownership: None,
+                rejection: None,
                 fields: vec![
-                    IdentityField::new(IdentityFieldValue::ALICE_DISPLAY_NAME()),
-                    IdentityField::new(IdentityFieldValue::ALICE_EMAIL()),
-                    IdentityField::new(IdentityFieldValue::ALICE_TWITTER()),
-                    IdentityField::new(IdentityFieldValue::ALICE_MATRIX()),
+                    IdentityField::new(IdentityFieldValue::ALICE_DISPLAY_NAME(), &config, &formats),
+                    IdentityField::new(IdentityFieldValue::ALICE_EMAIL(), &config, &formats),
+                    IdentityField::new(IdentityFieldValue::ALICE_TWITTER(), &config, &formats),
+                    IdentityField::new(IdentityFieldValue::ALICE_MATRIX(), &config, &formats),
                 ],
             }
         }
@@ -608,6 +1316,8 @@ mod tests {
                 ExternalMessageType::Email(n) => IdentityFieldValue::Email(n),
                 ExternalMessageType::Twitter(n) => IdentityFieldValue::Twitter(n),
                 ExternalMessageType::Matrix(n) => IdentityFieldValue::Matrix(n),
+                ExternalMessageType::Web(n) => IdentityFieldValue::Web(n),
+                ExternalMessageType::PGPFingerprint(n) => IdentityFieldValue::PGPFingerprint(n),
             }
         }
     }
@@ -631,6 +1341,7 @@ mod tests {
         }
     }
 
+    // Unchecked, for testing invalid addresses.
     impl From<&str> for ChainAddress {
         fn from(val: &str) -> Self {
             ChainAddress(val.to_string())
@@ -705,8 +1416,12 @@ mod tests {
         ]
         .into();
 
-        let state =
-            JudgementState::new(id, accounts.clone().into_iter().map(|a| a.into()).collect());
+        let state = JudgementState::new(
+            id,
+            accounts.clone().into_iter().map(|a| a.into()).collect(),
+            &SecondChallengeConfig::default(),
+            &ChallengeFormatConfig::default(),
+        );
 
         assert!(state.has_same_fields_as(&accounts));
 
@@ -733,4 +1448,203 @@ mod tests {
         assert!(!state.has_same_fields_as(&accounts_trimmed));
         assert!(state.has_same_fields_as(&accounts));
     }
+
+    #[test]
+    fn pgp_fingerprint_field() {
+        let field = IdentityFieldValue::PGPFingerprint(
+            "0x7E2F 0A1B 4C3D 5E6F 7A8B 9C0D 1E2F 3A4B 5C6D 7E8F".to_string(),
+        );
+
+        assert!(field.matches_type(
+            &AccountType::PGPFingerprint,
+            "7e2f0a1b4c3d5e6f7a8b9c0d1e2f3a4b5c6d7e8f"
+        ));
+        assert!(!field.matches_type(
+            &AccountType::PGPFingerprint,
+            "0000000000000000000000000000000000000000"
+        ));
+
+        // Legacy entries without a fingerprint.
+        let legacy: IdentityFieldValue =
+            serde_json::from_str(r#"{"type":"p_g_p_fingerprint","value":null}"#).unwrap();
+        assert_eq!(legacy, IdentityFieldValue::PGPFingerprint(String::new()));
+    }
+
+    #[test]
+    fn challenge_formats() {
+        let hex = ExpectedMessage::with_format(ChallengeFormat::Hex);
+        assert_eq!(hex.value.len(), 32);
+        assert!(hex.value.chars().all(|c| c.is_ascii_hexdigit()));
+
+        let words = ExpectedMessage::with_format(ChallengeFormat::Words);
+        let parts: Vec<&str> = words.value.split(' ').collect();
+        assert_eq!(parts.len(), 16);
+        assert!(parts.iter().all(|word| WORDLIST.contains(word)));
+        assert!(is_challenge_shaped(&words.value));
+
+        let base32 = ExpectedMessage::with_format(ChallengeFormat::Base32);
+        let groups: Vec<&str> = base32.value.split('-').collect();
+        assert_eq!(groups.len(), 6);
+        assert!(groups.iter().all(|group| group.len() == 4));
+
+        // Older entries do not specify a format.
+        let legacy: ExpectedMessage =
+            serde_json::from_str(r#"{"value":"abcd","is_verified":false}"#).unwrap();
+        assert_eq!(legacy.format, ChallengeFormat::Hex);
+        assert_eq!(legacy.issued_at, None);
+    }
+
+    #[test]
+    fn challenge_normalization() {
+        let mut words = ExpectedMessage::with_format(ChallengeFormat::Words);
+        words.value = "maple otter lotus cedar ferry quartz".to_string();
+
+        assert!(words.is_contained_in("maple otter lotus cedar ferry quartz"));
+        assert!(words.is_contained_in("Maple Otter\nLotus-cedar  ferry,quartz"));
+        assert!(words.is_contained_in("My challenge: maple-otter-lotus-cedar-ferry-quartz."));
+        assert!(!words.is_contained_in("maple otter lotus cedar ferry"));
+
+        let mut base32 = ExpectedMessage::with_format(ChallengeFormat::Base32);
+        base32.value = "K7QX-M2RA-ZT4B-P6WC-HN3D-JE5F".to_string();
+
+        assert!(base32.is_contained_in("k7qx m2ra zt4b p6wc hn3d je5f"));
+        assert!(base32.is_contained_in("K7QXM2RAZT4B\nP6WCHN3DJE5F"));
+        assert!(!base32.is_contained_in("K7QX-M2RA-ZT4B-P6WC-HN3D"));
+
+        let message = ExternalMessage {
+            origin: ExternalMessageType::Matrix("@alice:matrix.org".to_string()),
+            id: MessageId::from(0u32),
+            timestamp: Timestamp::now(),
+            values: vec!["k7qx-m2ra-zt4b\np6wc-hn3d-je5f".to_string().into()],
+        };
+
+        assert!(base32.verify_message(&message));
+        assert!(base32.is_verified);
+    }
+
+    #[test]
+    fn judgement_policy() {
+        let policy: JudgementPolicy = serde_yaml::from_str(
+            "legal_name: ignored\nimage: ignored\ndisplay_name: manual_review",
+        )
+        .unwrap();
+        assert_eq!(
+            policy.field_policy(&AccountType::LegalName),
+            FieldPolicy::Ignored
+        );
+        assert_eq!(
+            policy.field_policy(&AccountType::Email),
+            FieldPolicy::Required
+        );
+
+        let mut state = JudgementState::new(
+            IdentityContext::alice(),
+            vec![
+                IdentityFieldValue::LegalName("Alice".to_string()),
+                IdentityFieldValue::DisplayName("Alice".to_string()),
+                IdentityFieldValue::Email("alice@email.com".to_string()),
+                IdentityFieldValue::Image(()),
+            ],
+            &SecondChallengeConfig::default(),
+            &ChallengeFormatConfig::default(),
+        );
+
+        let set_verified = |state: &mut JudgementState, ty: AccountType| {
+            let field = state
+                .fields
+                .iter_mut()
+                .find(|f| f.value.as_account_type().0 == ty)
+                .unwrap();
+
+            match &mut field.challenge {
+                ChallengeType::ExpectedMessage { expected, second } => {
+                    expected.set_verified();
+                    if let Some(second) = second {
+                        second.set_verified();
+                    }
+                }
+                ChallengeType::DisplayNameCheck { passed, .. } => *passed = true,
+                ChallengeType::Unsupported { is_verified } => *is_verified = Some(true),
+            }
+        };
+
+        set_verified(&mut state, AccountType::Email);
+        set_verified(&mut state, AccountType::DisplayName);
+
+        // The display name must be reviewed by an admin.
+        assert!(!state.check_full_verification_with(&policy));
+
+        state
+            .fields
+            .iter_mut()
+            .find(|f| matches!(f.value, IdentityFieldValue::DisplayName(_)))
+            .unwrap()
+            .manually_verified = true;
+
+        assert!(state.check_full_verification_with(&policy));
+
+        // Without a policy, all fields are required.
+        assert!(!state.check_full_verification());
+
+        set_verified(&mut state, AccountType::LegalName);
+        set_verified(&mut state, AccountType::Image);
+        assert!(state.check_full_verification());
+    }
+
+    #[test]
+    fn chain_name_encoding() {
+        use bson::{doc, from_document, to_document};
+
+        // Documents stored with the former `Polkadot`/`Kusama` enum.
+        for chain in &["polkadot", "kusama"] {
+            let context: IdentityContext = from_document(doc! {
+                "address": "1a2YiGNu1UUhJtihq8961c7FZtWGQuWDVMWTNBKJdmpGhZP",
+                "chain": *chain,
+            })
+            .unwrap();
+
+            assert_eq!(context.chain, ChainName::from(*chain));
+        }
+
+        // Any configured network.
+        let context = IdentityContext::new(
+            ChainAddress::from("5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty"),
+            ChainName::from("westend"),
+        );
+
+        let doc = to_document(&context).unwrap();
+        assert_eq!(doc.get_str("chain").unwrap(), "westend");
+        assert_eq!(from_document::<IdentityContext>(doc).unwrap(), context);
+    }
+
+    #[test]
+    fn decode_ss58_address() {
+        let alice = IdentityContext::alice().address;
+        let public_key = alice.public_key().unwrap();
+
+        // The same account on different networks, including two byte prefixes.
+        for (address, prefix) in &[
+            (alice.as_str(), 0),
+            ("D9M4hMBfbDw1RheWttBqp8xYYB6NnAYbNTmgjTvELxnqWbv", 2),
+            ("5CdjQP1K3ED1FmtCkC58wrmxPwtra7MN8zd2J5BxkYkJ6NNR", 42),
+            ("Vdr6Z27SH1jCaPMdCbn4VLSJAs88xowf7yYUx1FwuFS86ina9", 1284),
+            ("unf65t7dVaFiZ4S6nAGSwYVvrEBj8KsWLARvEFwjrZtufka1k", 7391),
+        ] {
+            let address = ChainAddress::from_str(address).unwrap();
+            assert_eq!(address.decode().unwrap(), (*prefix, public_key));
+            assert_eq!(address.ss58_prefix().unwrap(), *prefix);
+        }
+
+        // Invalid checksum, length and encoding.
+        assert!(ChainAddress::from_str("1a2YiGNu1UUhJtihq8961c7FZtWGQuWDVMWTNBKJdmpGhZQ").is_err());
+        assert!(ChainAddress::from_str("1unknown").is_err());
+        assert!(ChainAddress::from_str("Alice").is_err());
+
+        // Validated when deserialized.
+        assert!(serde_json::from_str::<ChainAddress>("\"1unknown\"").is_err());
+        assert_eq!(
+            serde_json::from_str::<ChainAddress>(&format!("\"{}\"", alice.as_str())).unwrap(),
+            alice
+        );
+    }
 }
@@ -3,8 +3,8 @@ use crate::api::VerifyChallenge;
 use crate::api::{JsonResult, ResponseAccountState};
 use crate::connector::WatcherMessage;
 use crate::primitives::{
-    ExpectedMessage, ExternalMessage, ExternalMessageType, IdentityContext, MessageId,
-    NotificationMessage, Timestamp,
+    ChainAddress, ExpectedMessage, ExternalMessage, ExternalMessageType, IdentityContext,
+    MessageId, NotificationMessage, Timestamp,
 };
 use actix_http::StatusCode;
 use futures::{FutureExt, StreamExt};
@@ -882,3 +882,34 @@ async fn verify_full_identity() {
     // Empty stream.
     assert!(stream_alice.next().now_or_never().is_none());
 }
+
+#[actix::test]
+async fn invalid_addresses_are_rejected() {
+    let (_db, connector, mut api, _) = new_env().await;
+    let mut stream = api.ws_at("/api/account_status").await.unwrap();
+
+    // Subscribe with an invalid checksum.
+    stream
+        .send(Message::Text(
+            r#"{"address":"1a2YiGNu1UUhJtihq8961c7FZtWGQuWDVMWTNBKJdmpGhZQ","chain":"polkadot"}"#
+                .into(),
+        ))
+        .await
+        .unwrap();
+
+    let resp: JsonResult<ResponseAccountState> = stream.next().await.into();
+    match resp {
+        JsonResult::Err(err) => assert!(err.contains("invalid checksum")),
+        JsonResult::Ok(_) => panic!("invalid address was accepted"),
+    }
+
+    // The same account encoded for Kusama is not accepted by the Polkadot
+    // Watcher (mocked).
+    let mut request = JudgementRequest::alice();
+    request.address = ChainAddress::from("D9M4hMBfbDw1RheWttBqp8xYYB6NnAYbNTmgjTvELxnqWbv");
+
+    connector
+        .inject(WatcherMessage::new_judgement_request(request))
+        .await;
+    assert!(connector.inserted_states().await.is_empty());
+}
@@ -0,0 +1,264 @@
+use super::*;
+use crate::api::RefreshChallenge;
+use crate::primitives::{
+    ChainName, ExternalMessage, ExternalMessageType, MessageId, NotificationMessage, Timestamp,
+};
+use actix_http::StatusCode;
+
+#[actix::test]
+async fn refresh_challenge_via_api() {
+    let (db, connector, mut api, injector) = new_env().await;
+    let mut stream = api.ws_at("/api/account_status").await.unwrap();
+
+    // Insert judgement request.
+    connector.inject(alice_judgement_request()).await;
+    let states = connector.inserted_states().await;
+    let alice = states[0].clone();
+
+    // Subscribe to endpoint.
+    let resp = subscribe_context(&mut stream, IdentityContext::alice()).await;
+    assert_eq!(
+        resp,
+        JsonResult::Ok(ResponseAccountState::with_no_notifications(alice.clone()))
+    );
+
+    // The challenges were just issued.
+    let mut res = api
+        .post("/api/refresh_challenge")
+        .send_json(&RefreshChallenge {
+            context: alice.context.clone(),
+            entry: F::ALICE_EMAIL(),
+            signature: None,
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.json::<JsonResult<bool>>().await.unwrap(),
+        JsonResult::Ok(false)
+    );
+
+    sleep(Duration::from_secs(3)).await;
+
+    // Request a new challenge for the email field.
+    let mut res = api
+        .post("/api/refresh_challenge")
+        .send_json(&RefreshChallenge {
+            context: alice.context.clone(),
+            entry: F::ALICE_EMAIL(),
+            signature: None,
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.json::<JsonResult<bool>>().await.unwrap(),
+        JsonResult::Ok(true)
+    );
+
+    let new_alice = db
+        .fetch_judgement_state(&alice.context)
+        .await
+        .unwrap()
+        .unwrap();
+    let old = alice.get_field(&F::ALICE_EMAIL());
+    let new = new_alice.get_field(&F::ALICE_EMAIL());
+    assert_ne!(old.expected_message().value, new.expected_message().value);
+    assert_ne!(old.expected_second().value, new.expected_second().value);
+
+    // Other fields are unaffected.
+    assert_eq!(
+        alice.get_field(&F::ALICE_TWITTER()),
+        new_alice.get_field(&F::ALICE_TWITTER())
+    );
+
+    let expected = ResponseAccountState {
+        state: new_alice.clone().into(),
+        notifications: vec![NotificationMessage::ChallengeRegenerated {
+            context: alice.context.clone(),
+            field: F::ALICE_EMAIL(),
+        }],
+    };
+
+    let resp: JsonResult<ResponseAccountState> = stream.next().await.into();
+    assert_eq!(resp, JsonResult::Ok(expected));
+
+    // Verify the Matrix account.
+    injector
+        .send(ExternalMessage {
+            origin: ExternalMessageType::Matrix("@alice:matrix.org".to_string()),
+            id: MessageId::from(0u32),
+            timestamp: Timestamp::now(),
+            values: new_alice
+                .get_field(&F::ALICE_MATRIX())
+                .expected_message()
+                .to_message_parts(),
+        })
+        .await;
+
+    let _: JsonResult<ResponseAccountState> = stream.next().await.into();
+
+    // Verified challenges are not replaced.
+    let mut res = api
+        .post("/api/refresh_challenge")
+        .send_json(&RefreshChallenge {
+            context: alice.context.clone(),
+            entry: F::ALICE_MATRIX(),
+            signature: None,
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.json::<JsonResult<bool>>().await.unwrap(),
+        JsonResult::Ok(false)
+    );
+
+    // Empty stream.
+    sleep(Duration::from_secs(2)).await;
+    assert!(stream.next().now_or_never().is_none());
+}
+
+#[actix::test]
+async fn unauthenticated_refresh_requires_opt_in() {
+    let (db, connector, _api, _) = new_env().await;
+
+    // Insert judgement request, without ownership proof.
+    connector.inject(alice_judgement_request()).await;
+    let states = connector.inserted_states().await;
+    let alice = states[0].clone();
+
+    sleep(Duration::from_secs(3)).await;
+
+    assert!(!db
+        .refresh_challenge(&alice.context, &F::ALICE_EMAIL(), None, false)
+        .await
+        .unwrap());
+    assert!(db
+        .refresh_challenge(&alice.context, &F::ALICE_EMAIL(), None, true)
+        .await
+        .unwrap());
+}
+
+#[actix::test]
+async fn pending_second_challenge_is_not_refreshed() {
+    let (db, connector, api, injector) = new_env().await;
+
+    // Insert judgement request.
+    connector.inject(alice_judgement_request()).await;
+    let states = connector.inserted_states().await;
+    let alice = states[0].clone();
+
+    // Verify the email account, the second challenge was sent.
+    injector
+        .send(ExternalMessage {
+            origin: ExternalMessageType::Email("alice@email.com".to_string()),
+            id: MessageId::from(0u32),
+            timestamp: Timestamp::now(),
+            values: alice
+                .get_field(&F::ALICE_EMAIL())
+                .expected_message()
+                .to_message_parts(),
+        })
+        .await;
+
+    sleep(Duration::from_secs(3)).await;
+
+    let mut res = api
+        .post("/api/refresh_challenge")
+        .send_json(&RefreshChallenge {
+            context: alice.context.clone(),
+            entry: F::ALICE_EMAIL(),
+            signature: None,
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.json::<JsonResult<bool>>().await.unwrap(),
+        JsonResult::Ok(false)
+    );
+
+    let new_alice = db
+        .fetch_judgement_state(&alice.context)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        alice.get_field(&F::ALICE_EMAIL()).expected_second().value,
+        new_alice
+            .get_field(&F::ALICE_EMAIL())
+            .expected_second()
+            .value
+    );
+}
+
+#[actix::test]
+async fn expired_challenges_are_regenerated() {
+    let (db, connector, _api, injector) = new_env().await;
+
+    // Insert judgement request.
+    connector.inject(alice_judgement_request()).await;
+    let states = connector.inserted_states().await;
+    let alice = states[0].clone();
+
+    // Verify the email account, the second challenge is now pending.
+    injector
+        .send(ExternalMessage {
+            origin: ExternalMessageType::Email("alice@email.com".to_string()),
+            id: MessageId::from(0u32),
+            timestamp: Timestamp::now(),
+            values: alice
+                .get_field(&F::ALICE_EMAIL())
+                .expected_message()
+                .to_message_parts(),
+        })
+        .await;
+
+    sleep(Duration::from_secs(3)).await;
+
+    // Nothing has expired yet.
+    let count = db
+        .regenerate_expired_challenges(&ChainName::from("polkadot"), 3600)
+        .await
+        .unwrap();
+    assert_eq!(count, 0);
+
+    // Email (second challenge), Twitter and Matrix.
+    let count = db
+        .regenerate_expired_challenges(&ChainName::from("polkadot"), 1)
+        .await
+        .unwrap();
+    assert_eq!(count, 3);
+
+    let new_alice = db
+        .fetch_judgement_state(&alice.context)
+        .await
+        .unwrap()
+        .unwrap();
+
+    let (old, new) = (
+        alice.get_field(&F::ALICE_EMAIL()),
+        new_alice.get_field(&F::ALICE_EMAIL()),
+    );
+    assert_eq!(old.expected_message().value, new.expected_message().value);
+    assert!(new.expected_message().is_verified);
+    assert_ne!(old.expected_second().value, new.expected_second().value);
+
+    for field in &[F::ALICE_TWITTER(), F::ALICE_MATRIX()] {
+        let (old, new) = (alice.get_field(field), new_alice.get_field(field));
+        assert_ne!(old.expected_message().value, new.expected_message().value);
+        assert!(!new.expected_message().is_verified);
+    }
+
+    // Other networks are unaffected.
+    let count = db
+        .regenerate_expired_challenges(&ChainName::from("kusama"), 1)
+        .await
+        .unwrap();
+    assert_eq!(count, 0);
+}
@@ -0,0 +1,51 @@
+use super::*;
+use crate::adapters::admin::{process_admin, Command, Response};
+use crate::connector::Judgement;
+
+#[actix::test]
+async fn judgements_are_simulated_in_dry_run() {
+    let (db, _, _api, _) = new_env().await;
+    let mut connector = ConnectorMocker::with_dry_run(db.clone());
+
+    // Insert judgement request.
+    connector.inject(alice_judgement_request()).await;
+    let states = connector.inserted_states().await;
+    let alice = states[0].clone();
+
+    // Reject the identity, the judgement is sent immediately.
+    let resp = process_admin(
+        &db,
+        Command::Reject(
+            alice.context.address.clone().into(),
+            Judgement::Erroneous,
+            "impersonator".to_string(),
+        ),
+        ADMIN,
+    )
+    .await;
+    assert_eq!(
+        resp,
+        Response::Rejected(alice.context.address.clone(), Judgement::Erroneous)
+    );
+
+    sleep(Duration::from_secs(5)).await;
+
+    // Nothing was sent to the Watcher.
+    let (_, counter) = connector.outgoing();
+    assert_eq!(counter.provide_judgement, 0);
+
+    // The judgement was recorded once and the identity marked as judged.
+    let simulated = db.fetch_simulated_judgements(&alice.context).await.unwrap();
+    assert_eq!(simulated.len(), 1);
+    assert_eq!(simulated[0].address, alice.context.address);
+    assert_eq!(simulated[0].judgement, Judgement::Erroneous);
+    assert!(simulated[0].verified.is_empty());
+
+    let state = db
+        .fetch_judgement_state(&alice.context)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(state.judgement_submitted);
+    assert!(state.judgement_simulated);
+}
@@ -0,0 +1,185 @@
+use super::*;
+use crate::adapters::admin::{process_admin, Command, RawFieldName, Response};
+use crate::api::VerifyChallenge;
+use crate::primitives::{
+    ExpectedMessage, ExternalMessage, ExternalMessageType, MessageId, NotificationMessage,
+    Timestamp,
+};
+use actix_http::StatusCode;
+
+fn lockout() -> Option<LockoutConfig> {
+    Some(LockoutConfig {
+        max_attempts: 2,
+        window: 60,
+        cooldown: None,
+    })
+}
+
+fn alice_email(values: &ExpectedMessage) -> ExternalMessage {
+    ExternalMessage {
+        origin: ExternalMessageType::Email("alice@email.com".to_string()),
+        id: MessageId::from(0u32),
+        timestamp: Timestamp::now(),
+        values: values.to_message_parts(),
+    }
+}
+
+async fn next_notifications(
+    stream: &mut Framed<impl AsyncRead + AsyncWrite + std::marker::Unpin, Codec>,
+) -> Vec<NotificationMessage> {
+    let resp: JsonResult<ResponseAccountState> = stream.next().await.into();
+    match resp {
+        JsonResult::Ok(resp) => resp.notifications,
+        JsonResult::Err(err) => panic!("{}", err),
+    }
+}
+
+#[actix::test]
+async fn field_locked_after_failed_attempts() {
+    let (db, connector, mut api, injector) = new_env_with_lockout(lockout()).await;
+    let mut stream = api.ws_at("/api/account_status").await.unwrap();
+
+    // Insert judgement request.
+    connector.inject(alice_judgement_request()).await;
+    let states = connector.inserted_states().await;
+    let alice = states[0].clone();
+    let context = alice.context.clone();
+
+    let resp = subscribe_context(&mut stream, IdentityContext::alice()).await;
+    assert_eq!(
+        resp,
+        JsonResult::Ok(ResponseAccountState::with_no_notifications(alice.clone()))
+    );
+
+    // Two invalid messages.
+    injector.send(alice_email(&ExpectedMessage::random())).await;
+    injector.send(alice_email(&ExpectedMessage::random())).await;
+
+    assert_eq!(
+        next_notifications(&mut stream).await,
+        vec![NotificationMessage::FieldVerificationFailed {
+            context: context.clone(),
+            field: F::ALICE_EMAIL(),
+        }]
+    );
+    assert_eq!(
+        next_notifications(&mut stream).await,
+        vec![NotificationMessage::FieldVerificationFailed {
+            context: context.clone(),
+            field: F::ALICE_EMAIL(),
+        }]
+    );
+    assert_eq!(
+        next_notifications(&mut stream).await,
+        vec![NotificationMessage::FieldLocked {
+            context: context.clone(),
+            field: F::ALICE_EMAIL(),
+        }]
+    );
+
+    // The field is locked and the challenge was replaced.
+    let state = db.fetch_judgement_state(&context).await.unwrap().unwrap();
+    let field = state.get_field(&F::ALICE_EMAIL());
+    assert!(field.is_locked());
+    assert_eq!(field.failed_attempts, 2);
+
+    let old = alice
+        .get_field(&F::ALICE_EMAIL())
+        .expected_message()
+        .clone();
+    let new = field.expected_message().clone();
+    assert_ne!(old.value, new.value);
+
+    // Valid messages are ignored while locked.
+    injector.send(alice_email(&new)).await;
+    sleep(Duration::from_secs(3)).await;
+    assert!(stream.next().now_or_never().is_none());
+
+    // Unlock the field.
+    let resp = process_admin(
+        &db,
+        Command::Unlock(context.address.clone().into(), vec![RawFieldName::Email]),
+        ADMIN,
+    )
+    .await;
+    assert_eq!(
+        resp,
+        Response::Unlocked(context.address.clone(), vec![RawFieldName::Email])
+    );
+
+    assert_eq!(
+        next_notifications(&mut stream).await,
+        vec![NotificationMessage::FieldUnlocked {
+            context: context.clone(),
+            field: F::ALICE_EMAIL(),
+        }]
+    );
+
+    // The new challenge is accepted.
+    injector.send(alice_email(&new)).await;
+
+    assert_eq!(
+        next_notifications(&mut stream).await,
+        vec![NotificationMessage::FieldVerified {
+            context: context.clone(),
+            field: F::ALICE_EMAIL(),
+        }]
+    );
+}
+
+#[actix::test]
+async fn second_challenge_locked_after_failed_attempts() {
+    let (db, connector, api, injector) = new_env_with_lockout(lockout()).await;
+
+    // Insert judgement request.
+    connector.inject(alice_judgement_request()).await;
+    let states = connector.inserted_states().await;
+    let alice = states[0].clone();
+    let context = alice.context.clone();
+
+    // Verify the first challenge.
+    injector
+        .send(alice_email(
+            alice.get_field(&F::ALICE_EMAIL()).expected_message(),
+        ))
+        .await;
+    sleep(Duration::from_secs(3)).await;
+
+    // Two invalid second challenges.
+    for _ in 0..2 {
+        let res = api
+            .post("/api/verify_second_challenge")
+            .send_json(&VerifyChallenge {
+                entry: F::ALICE_EMAIL(),
+                challenge: ExpectedMessage::random().value,
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    let state = db.fetch_judgement_state(&context).await.unwrap().unwrap();
+    let field = state.get_field(&F::ALICE_EMAIL());
+    assert!(field.is_locked());
+    assert!(field.expected_message().is_verified);
+
+    let old = alice.get_field(&F::ALICE_EMAIL()).expected_second().clone();
+    let new = field.expected_second().clone();
+    assert_ne!(old.value, new.value);
+
+    // Valid challenges are rejected while locked.
+    let mut res = api
+        .post("/api/verify_second_challenge")
+        .send_json(&VerifyChallenge {
+            entry: F::ALICE_EMAIL(),
+            challenge: new.value,
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(
+        res.json::<JsonResult<bool>>().await.unwrap(),
+        JsonResult::Ok(false)
+    );
+}
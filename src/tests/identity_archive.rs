@@ -0,0 +1,93 @@
+use super::*;
+use crate::primitives::{ChainName, NotificationMessage};
+
+#[actix::test]
+async fn abandoned_identities_are_archived() {
+    let (db, connector, mut api, _) = new_env().await;
+    let mut stream = api.ws_at("/api/account_status").await.unwrap();
+
+    // Insert judgement requests.
+    connector.inject(alice_judgement_request()).await;
+    connector.inject(bob_judgement_request()).await;
+    let states = connector.inserted_states().await;
+    let alice = states[0].clone();
+    let bob = states[1].clone();
+
+    // Subscribe to endpoint.
+    let resp = subscribe_context(&mut stream, IdentityContext::alice()).await;
+    assert_eq!(
+        resp,
+        JsonResult::Ok(ResponseAccountState::with_no_notifications(alice.clone()))
+    );
+
+    // Only Bob is still pending on-chain.
+    connector
+        .inject(WatcherMessage::PendingJudgementsRequests(vec![
+            JudgementRequest::bob(),
+        ]))
+        .await;
+
+    // Alice was archived.
+    assert!(db
+        .fetch_judgement_state(&alice.context)
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        db.fetch_archived_judgement_state(&alice.context)
+            .await
+            .unwrap()
+            .unwrap(),
+        alice
+    );
+
+    // Bob is unaffected.
+    assert_eq!(
+        db.fetch_judgement_state(&bob.context)
+            .await
+            .unwrap()
+            .unwrap(),
+        bob
+    );
+
+    let expected = ResponseAccountState {
+        state: alice.clone().into(),
+        notifications: vec![NotificationMessage::IdentityRemoved {
+            context: alice.context.clone(),
+        }],
+    };
+
+    let resp: JsonResult<ResponseAccountState> = stream.next().await.into();
+    assert_eq!(resp, JsonResult::Ok(expected));
+
+    // An empty list is ignored.
+    connector
+        .inject(WatcherMessage::PendingJudgementsRequests(vec![]))
+        .await;
+    assert!(db
+        .fetch_judgement_state(&bob.context)
+        .await
+        .unwrap()
+        .is_some());
+
+    // Other networks are unaffected.
+    let count = db
+        .archive_abandoned_states(
+            &ChainName::from("kusama"),
+            std::slice::from_ref(&alice.context.address),
+        )
+        .await
+        .unwrap();
+    assert_eq!(count, 0);
+    assert!(db
+        .fetch_judgement_state(&bob.context)
+        .await
+        .unwrap()
+        .is_some());
+
+    // A new request for Alice starts from scratch.
+    connector.inject(alice_judgement_request()).await;
+    let states = connector.inserted_states().await;
+    assert_eq!(states.len(), 1);
+    assert_ne!(states[0], alice);
+}
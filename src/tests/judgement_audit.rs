@@ -0,0 +1,117 @@
+use super::*;
+use crate::adapters::admin::{process_admin, Command, RawFieldName, Response};
+use crate::api::FetchJudgementAudit;
+use crate::connector::Judgement;
+use crate::primitives::{
+    ExternalMessage, ExternalMessageType, JudgementAuditEntry, MessageId, Timestamp,
+    VerificationMethod,
+};
+use actix_http::StatusCode;
+
+fn field_audit(entry: &JudgementAuditEntry, field: &F) -> (VerificationMethod, Option<String>) {
+    let audit = entry.fields.iter().find(|f| &f.value == field).unwrap();
+    (audit.method, audit.admin.clone())
+}
+
+#[actix::test]
+async fn judgement_audit_is_recorded() {
+    let (db, connector, api, injector) = new_env().await;
+
+    // Insert judgement request.
+    connector.inject(alice_judgement_request()).await;
+    let states = connector.inserted_states().await;
+    let alice = states[0].clone();
+
+    // Verify the Matrix account via message and Twitter manually.
+    injector
+        .send(ExternalMessage {
+            origin: ExternalMessageType::Matrix("@alice:matrix.org".to_string()),
+            id: MessageId::from(0u32),
+            timestamp: Timestamp::now(),
+            values: alice
+                .get_field(&F::ALICE_MATRIX())
+                .expected_message()
+                .to_message_parts(),
+        })
+        .await;
+
+    let resp = process_admin(
+        &db,
+        Command::Verify(
+            alice.context.address.clone().into(),
+            vec![RawFieldName::Twitter],
+        ),
+        ADMIN,
+    )
+    .await;
+    assert_eq!(
+        resp,
+        Response::Verified(alice.context.address.clone(), vec![RawFieldName::Twitter])
+    );
+
+    // Reject the identity, the judgement is sent immediately.
+    let resp = process_admin(
+        &db,
+        Command::Reject(
+            alice.context.address.clone().into(),
+            Judgement::Erroneous,
+            "impersonator".to_string(),
+        ),
+        ADMIN,
+    )
+    .await;
+    assert_eq!(
+        resp,
+        Response::Rejected(alice.context.address.clone(), Judgement::Erroneous)
+    );
+
+    // The judgement is resent until confirmed by the Watcher, but
+    // only recorded once.
+    sleep(Duration::from_secs(5)).await;
+
+    let mut res = api
+        .post("/api/judgement_audit")
+        .send_json(&FetchJudgementAudit {
+            address: alice.context.address.clone(),
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+    let entries = match res
+        .json::<JsonResult<Vec<JudgementAuditEntry>>>()
+        .await
+        .unwrap()
+    {
+        JsonResult::Ok(entries) => entries,
+        JsonResult::Err(err) => panic!("{}", err),
+    };
+
+    assert_eq!(entries.len(), 1);
+    let entry = &entries[0];
+    assert_eq!(entry.context, alice.context);
+    assert_eq!(entry.judgement, Judgement::Erroneous);
+    assert_eq!(entry.rejection.as_ref().unwrap().reason, "impersonator");
+    assert!(entry.verified.is_empty());
+
+    assert_eq!(
+        field_audit(entry, &F::ALICE_MATRIX()),
+        (VerificationMethod::Message, None)
+    );
+    assert_eq!(
+        field_audit(entry, &F::ALICE_TWITTER()),
+        (VerificationMethod::Manual, Some(ADMIN.to_string()))
+    );
+    assert_eq!(
+        field_audit(entry, &F::ALICE_EMAIL()),
+        (VerificationMethod::Unverified, None)
+    );
+
+    // The same log is available via the admin command.
+    let resp = process_admin(&db, Command::Audit(alice.context.address.clone()), ADMIN).await;
+    assert_eq!(resp, Response::Audit(entries));
+
+    // Other addresses have no entries.
+    let resp = process_admin(&db, Command::Audit(IdentityContext::bob().address), ADMIN).await;
+    assert_eq!(resp, Response::Audit(vec![]));
+}
@@ -0,0 +1,239 @@
+use super::*;
+use crate::adapters::admin::{process_admin, Command, Response};
+use crate::connector::{ClientCommand, Handshake, Judgement};
+use crate::primitives::{JudgementState, NotificationMessage, SubmissionStatus};
+
+async fn reject_alice(db: &Database, alice: &JudgementState) {
+    let resp = process_admin(
+        db,
+        Command::Reject(
+            alice.context.address.clone().into(),
+            Judgement::Erroneous,
+            "impersonator".to_string(),
+        ),
+        ADMIN,
+    )
+    .await;
+    assert_eq!(
+        resp,
+        Response::Rejected(alice.context.address.clone(), Judgement::Erroneous)
+    );
+}
+
+#[actix::test]
+async fn judgement_submission_fails_without_ack() {
+    let (db, mut connector, mut api, _) = new_env().await;
+    let mut stream = api.ws_at("/api/account_status").await.unwrap();
+
+    // Insert judgement request.
+    connector.inject(alice_judgement_request()).await;
+    let states = connector.inserted_states().await;
+    let alice = states[0].clone();
+
+    let _ = subscribe_context(&mut stream, alice.context.clone()).await;
+
+    reject_alice(&db, &alice).await;
+    let _: JsonResult<ResponseAccountState> = stream.next().await.into();
+
+    // The judgement is resent with a backoff, up to the maximum attempts.
+    sleep(Duration::from_secs(12)).await;
+
+    let (_, counter) = connector.outgoing();
+    assert_eq!(counter.provide_judgement, 3);
+
+    let resp: JsonResult<ResponseAccountState> = stream.next().await.into();
+    let resp = match resp {
+        JsonResult::Ok(resp) => resp,
+        JsonResult::Err(err) => panic!("{}", err),
+    };
+    assert_eq!(
+        resp.notifications,
+        vec![NotificationMessage::JudgementSubmissionFailed {
+            context: alice.context.clone(),
+        }]
+    );
+    assert!(!resp.state.judgement_submitted);
+
+    // The failed submission is reported once.
+    let failed = db.fetch_unreported_failed_submissions().await.unwrap();
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].context, alice.context);
+    assert_eq!(failed[0].attempts, 3);
+
+    db.set_submission_reported(&failed[0]).await.unwrap();
+    assert!(db
+        .fetch_unreported_failed_submissions()
+        .await
+        .unwrap()
+        .is_empty());
+
+    // No further attempts.
+    sleep(Duration::from_secs(3)).await;
+    let (_, counter) = connector.outgoing();
+    assert_eq!(counter.provide_judgement, 0);
+}
+
+#[actix::test]
+async fn judgement_submission_stops_after_ack() {
+    let (db, mut connector, _api, _) = new_env().await;
+
+    // Insert judgement request.
+    connector.inject(alice_judgement_request()).await;
+    let states = connector.inserted_states().await;
+    let alice = states[0].clone();
+
+    reject_alice(&db, &alice).await;
+
+    sleep(Duration::from_secs(3)).await;
+    let (_, counter) = connector.outgoing();
+    assert!(counter.provide_judgement > 0);
+
+    // The Watcher confirms the judgement.
+    connector
+        .inject(WatcherMessage::new_judgement_given(
+            alice.context.address.clone(),
+        ))
+        .await;
+
+    let state = db
+        .fetch_judgement_state(&alice.context)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(state.judgement_submitted);
+
+    // No further attempts and nothing to report.
+    sleep(Duration::from_secs(10)).await;
+    let (_, counter) = connector.outgoing();
+    assert_eq!(counter.provide_judgement, 0);
+    assert!(db
+        .fetch_unreported_failed_submissions()
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[actix::test]
+async fn typed_ack_resolves_correlation_id() {
+    let (db, mut connector, _api, _) = new_env().await;
+
+    // The Watcher supports the current protocol.
+    connector
+        .inject(WatcherMessage::Handshake(Handshake::supported()))
+        .await;
+
+    // Insert judgement request.
+    connector.inject(alice_judgement_request()).await;
+    let states = connector.inserted_states().await;
+    let alice = states[0].clone();
+
+    reject_alice(&db, &alice).await;
+
+    sleep(Duration::from_secs(3)).await;
+    let (outgoing, _) = connector.outgoing();
+    let correlation_id = outgoing
+        .into_iter()
+        .find_map(|msg| match msg {
+            ClientCommand::ProvideJudgement(_, id) => Some(id),
+            _ => None,
+        })
+        .unwrap();
+
+    // The acknowledgement only contains the correlation ID.
+    connector
+        .inject(WatcherMessage::new_judgement_given_with_id(correlation_id))
+        .await;
+
+    let state = db
+        .fetch_judgement_state(&alice.context)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(state.judgement_submitted);
+}
+
+#[actix::test]
+async fn late_ack_does_not_acknowledge_newer_submission() {
+    let (db, mut connector, _api, _) = new_env().await;
+
+    // The Watcher supports the current protocol.
+    connector
+        .inject(WatcherMessage::Handshake(Handshake::supported()))
+        .await;
+
+    // Insert judgement request.
+    connector.inject(alice_judgement_request()).await;
+    let states = connector.inserted_states().await;
+    let alice = states[0].clone();
+
+    reject_alice(&db, &alice).await;
+    sleep(Duration::from_secs(2)).await;
+
+    // The rejection is changed before the Watcher confirms the first one.
+    let resp = process_admin(
+        &db,
+        Command::Reject(
+            alice.context.address.clone().into(),
+            Judgement::LowQuality,
+            "spam".to_string(),
+        ),
+        ADMIN,
+    )
+    .await;
+    assert_eq!(
+        resp,
+        Response::Rejected(alice.context.address.clone(), Judgement::LowQuality)
+    );
+
+    sleep(Duration::from_secs(2)).await;
+
+    let (outgoing, _) = connector.outgoing();
+    let correlation_id = |judgement: Judgement| {
+        outgoing
+            .iter()
+            .find_map(|msg| match msg {
+                ClientCommand::ProvideJudgement(state, id) if state.judgement() == judgement => {
+                    Some(id.clone())
+                }
+                _ => None,
+            })
+            .unwrap()
+    };
+    let (old_id, new_id) = (
+        correlation_id(Judgement::Erroneous),
+        correlation_id(Judgement::LowQuality),
+    );
+
+    // Late acknowledgement of the first submission.
+    connector
+        .inject(WatcherMessage::new_judgement_given_with_id(old_id.clone()))
+        .await;
+
+    let status = |id: String| {
+        let db = db.clone();
+        async move { db.fetch_submission(&id).await.unwrap().unwrap().status }
+    };
+    assert_eq!(status(old_id).await, SubmissionStatus::Acknowledged);
+    assert_eq!(status(new_id.clone()).await, SubmissionStatus::Pending);
+
+    let state = db
+        .fetch_judgement_state(&alice.context)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(!state.judgement_submitted);
+
+    // The current submission is acknowledged.
+    connector
+        .inject(WatcherMessage::new_judgement_given_with_id(new_id.clone()))
+        .await;
+
+    assert_eq!(status(new_id).await, SubmissionStatus::Acknowledged);
+
+    let state = db
+        .fetch_judgement_state(&alice.context)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(state.judgement_submitted);
+}
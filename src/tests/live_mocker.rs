@@ -31,6 +31,7 @@ async fn run_mocker() -> Result<()> {
             enabled: true,
             limit: 0.85,
         },
+        unauthenticated_challenge_refresh: true,
     };
 
     info!("Starting mock adapter and session notifier instances");
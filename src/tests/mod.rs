@@ -4,7 +4,8 @@ use crate::api::{JsonResult, ResponseAccountState};
 use crate::connector::{AccountType, JudgementRequest, WatcherMessage};
 use crate::database::Database;
 use crate::notifier::run_session_notifier;
-use crate::primitives::{IdentityContext, IdentityFieldValue};
+use crate::primitives::{ChainName, IdentityContext, IdentityFieldValue};
+use crate::LockoutConfig;
 use crate::{api::tests::run_test_server, connector::tests::ConnectorMocker};
 use actix_codec::{AsyncRead, AsyncWrite, Framed};
 use actix_http::ws::Codec;
@@ -19,10 +20,22 @@ use tokio::time::{sleep, Duration};
 
 mod api_judgement_state;
 mod background_tasks;
+mod challenge_expiry;
 mod display_name_verification;
+mod dry_run;
 mod explicit;
+mod field_lockout;
+mod identity_archive;
+mod judgement_audit;
+mod judgement_outbox;
 mod live_mocker;
+mod ownership_proof;
+mod pgp_verification;
 mod process_admin_cmds;
+mod processed_messages;
+mod sender_auth;
+mod user_messages;
+mod web_verification;
 
 // Convenience type
 pub type F = IdentityFieldValue;
@@ -63,6 +76,9 @@ pub async fn subscribe_context(
     resp
 }
 
+// The admin handle used for admin commands.
+pub const ADMIN: &str = "@admin:matrix.org";
+
 pub fn alice_judgement_request() -> WatcherMessage {
     WatcherMessage::new_judgement_request(JudgementRequest::alice())
 }
@@ -73,14 +89,28 @@ pub fn bob_judgement_request() -> WatcherMessage {
 
 // async fn new_env() -> (TestServer, ConnectorMocker, MessageInjector) {
 async fn new_env() -> (Database, ConnectorMocker, TestServer, MessageInjector) {
+    new_env_with_lockout(None).await
+}
+
+async fn new_env_with_lockout(
+    lockout: Option<LockoutConfig>,
+) -> (Database, ConnectorMocker, TestServer, MessageInjector) {
     // Setup MongoDb database.
     let random: u32 = thread_rng().gen_range(u32::MIN..u32::MAX);
-    let db = Database::new(
+    let mut db = Database::new(
         "mongodb://localhost:27017/?replicaSet=rs0",
         &format!("registrar_test_{}", random),
     )
     .await
-    .unwrap();
+    .unwrap()
+    .with_networks(vec![
+        (ChainName::from("polkadot"), 0),
+        (ChainName::from("kusama"), 2),
+    ]);
+
+    if let Some(lockout) = lockout {
+        db = db.with_lockout(lockout);
+    }
 
     // Setup API
     let (server, actor) = run_test_server(db.clone()).await;
@@ -0,0 +1,193 @@
+use super::*;
+use crate::adapters::admin::{process_admin, Command, RawFieldName, Response};
+use crate::api::{OwnershipSigner, RefreshChallenge, VerifyOwnership};
+use crate::primitives::{
+    ChallengeTypeBlanked, JudgementState, NotificationMessage, OwnershipProofMode,
+};
+use actix_http::StatusCode;
+use std::collections::HashMap;
+
+fn judgement_request(signer: &OwnershipSigner) -> WatcherMessage {
+    WatcherMessage::new_judgement_request(JudgementRequest {
+        address: signer.address(),
+        accounts: HashMap::from([
+            (AccountType::Email, "alice@email.com".to_string()),
+            (AccountType::Twitter, "@alice".to_string()),
+        ]),
+    })
+}
+
+async fn verify_ownership(
+    api: &TestServer,
+    state: &JudgementState,
+    signature: String,
+) -> JsonResult<bool> {
+    let mut res = api
+        .post("/api/verify_ownership")
+        .send_json(&VerifyOwnership {
+            context: state.context.clone(),
+            signature,
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+    res.json::<JsonResult<bool>>().await.unwrap()
+}
+
+fn revealed_challenges(resp: &ResponseAccountState) -> Vec<String> {
+    resp.state
+        .fields
+        .iter()
+        .filter_map(|field| match &field.challenge {
+            ChallengeTypeBlanked::ExpectedMessage {
+                expected,
+                second: _,
+            } => Some(expected.value.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[actix::test]
+async fn challenges_revealed_after_ownership_proof() {
+    let (db, _, mut api, _) = new_env().await;
+    let connector =
+        ConnectorMocker::with_ownership_proof(db.clone(), OwnershipProofMode::BeforeChallenges);
+    let mut stream = api.ws_at("/api/account_status").await.unwrap();
+
+    let signer = OwnershipSigner::sr25519(1);
+
+    // Insert judgement request.
+    connector.inject(judgement_request(&signer)).await;
+    let states = connector.inserted_states().await;
+    let state = states[0].clone();
+    let nonce = state.ownership.as_ref().unwrap().nonce.clone();
+
+    // The challenges are blanked.
+    let resp = match subscribe_context(&mut stream, state.context.clone()).await {
+        JsonResult::Ok(resp) => resp,
+        JsonResult::Err(err) => panic!("{}", err),
+    };
+    assert_eq!(resp.state.ownership.as_ref().unwrap().nonce, nonce);
+    assert!(revealed_challenges(&resp).iter().all(|v| v.is_empty()));
+
+    // Signed by another key.
+    let other = OwnershipSigner::sr25519(2);
+    assert_eq!(
+        verify_ownership(&api, &state, other.sign(&nonce)).await,
+        JsonResult::Ok(false)
+    );
+
+    // Valid signature.
+    assert_eq!(
+        verify_ownership(&api, &state, signer.sign(&nonce)).await,
+        JsonResult::Ok(true)
+    );
+
+    let resp: JsonResult<ResponseAccountState> = stream.next().await.into();
+    let resp = match resp {
+        JsonResult::Ok(resp) => resp,
+        JsonResult::Err(err) => panic!("{}", err),
+    };
+    assert_eq!(
+        resp.notifications,
+        vec![NotificationMessage::OwnershipVerified {
+            context: state.context.clone(),
+        }]
+    );
+    assert!(resp.state.ownership.as_ref().unwrap().is_verified);
+    assert!(revealed_challenges(&resp).iter().all(|v| !v.is_empty()));
+}
+
+#[actix::test]
+async fn judgement_requires_ownership_proof() {
+    let (db, _, api, _) = new_env().await;
+    let connector =
+        ConnectorMocker::with_ownership_proof(db.clone(), OwnershipProofMode::BeforeJudgement);
+
+    let signer = OwnershipSigner::ed25519(1);
+
+    // Insert judgement request.
+    connector.inject(judgement_request(&signer)).await;
+    let states = connector.inserted_states().await;
+    let state = states[0].clone();
+    let nonce = state.ownership.as_ref().unwrap().nonce.clone();
+
+    // Verify all fields.
+    let fields = vec![RawFieldName::Email, RawFieldName::Twitter];
+    let resp = process_admin(
+        &db,
+        Command::Verify(state.context.address.clone().into(), fields.clone()),
+        ADMIN,
+    )
+    .await;
+    assert_eq!(
+        resp,
+        Response::Verified(state.context.address.clone(), fields)
+    );
+
+    // Not fully verified without the ownership proof.
+    let current = db
+        .fetch_judgement_state(&state.context)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(!current.is_fully_verified);
+
+    assert_eq!(
+        verify_ownership(&api, &state, signer.sign(&nonce)).await,
+        JsonResult::Ok(true)
+    );
+
+    let current = db
+        .fetch_judgement_state(&state.context)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(current.is_fully_verified);
+}
+
+#[actix::test]
+async fn refresh_challenge_requires_ownership_proof() {
+    let (db, _, api, _) = new_env().await;
+    let connector =
+        ConnectorMocker::with_ownership_proof(db.clone(), OwnershipProofMode::BeforeJudgement);
+
+    let signer = OwnershipSigner::sr25519(1);
+
+    // Insert judgement request.
+    connector.inject(judgement_request(&signer)).await;
+    let states = connector.inserted_states().await;
+    let state = states[0].clone();
+    let nonce = state.ownership.as_ref().unwrap().nonce.clone();
+
+    sleep(Duration::from_secs(3)).await;
+
+    let refresh = |signature: Option<String>| {
+        api.post("/api/refresh_challenge")
+            .send_json(&RefreshChallenge {
+                context: state.context.clone(),
+                entry: IdentityFieldValue::Email("alice@email.com".to_string()),
+                signature,
+            })
+    };
+
+    // Missing or invalid proof.
+    for signature in [None, Some(OwnershipSigner::sr25519(2).sign(&nonce))] {
+        let mut res = refresh(signature).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.json::<JsonResult<bool>>().await.unwrap(),
+            JsonResult::Ok(false)
+        );
+    }
+
+    // Valid proof.
+    let mut res = refresh(Some(signer.sign(&nonce))).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.json::<JsonResult<bool>>().await.unwrap(),
+        JsonResult::Ok(true)
+    );
+}
@@ -0,0 +1,163 @@
+use super::*;
+use crate::api::{JsonResult, PgpSigner, ResponseAccountState, VerifyPgpChallenge};
+use crate::connector::WatcherMessage;
+use crate::primitives::{IdentityContext, NotificationMessage};
+use actix_http::StatusCode;
+use futures::{FutureExt, StreamExt};
+
+fn alice_pgp_request(signer: &PgpSigner) -> WatcherMessage {
+    let mut req = JudgementRequest::alice();
+    req.accounts
+        .insert(AccountType::PGPFingerprint, signer.fingerprint());
+
+    WatcherMessage::new_judgement_request(req)
+}
+
+fn alice_pgp(signer: &PgpSigner) -> IdentityFieldValue {
+    IdentityFieldValue::PGPFingerprint(signer.fingerprint())
+}
+
+#[actix::test]
+async fn verify_pgp_clearsigned_challenge() {
+    let (_db, connector, mut api, _) = new_env().await;
+    let mut stream = api.ws_at("/api/account_status").await.unwrap();
+    let signer = PgpSigner::new();
+
+    // Insert judgement request.
+    connector.inject(alice_pgp_request(&signer)).await;
+    let states = connector.inserted_states().await;
+    let mut alice = states[0].clone();
+
+    // Subscribe to endpoint.
+    let resp = subscribe_context(&mut stream, IdentityContext::alice()).await;
+    assert_eq!(
+        resp,
+        JsonResult::Ok(ResponseAccountState::with_no_notifications(alice.clone()))
+    );
+
+    // Sign the challenge.
+    let challenge = alice
+        .get_field(&alice_pgp(&signer))
+        .expected_message()
+        .value
+        .clone();
+
+    let req = VerifyPgpChallenge {
+        entry: alice_pgp(&signer),
+        public_key: signer.public_key(),
+        signature: signer.clearsigned(&challenge),
+        message: None,
+    };
+
+    // Send it to the API endpoint.
+    let mut res = api
+        .post("/api/verify_pgp_challenge")
+        .send_json(&req)
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.json::<JsonResult<bool>>().await.unwrap(),
+        JsonResult::Ok(true)
+    );
+
+    // PGP field of Alice is now verified.
+    alice
+        .get_field_mut(&alice_pgp(&signer))
+        .expected_message_mut()
+        .set_verified();
+
+    let expected = ResponseAccountState {
+        state: alice.clone().into(),
+        notifications: vec![NotificationMessage::FieldVerified {
+            context: alice.context.clone(),
+            field: alice_pgp(&signer),
+        }],
+    };
+
+    let resp: JsonResult<ResponseAccountState> = stream.next().await.into();
+    assert_eq!(resp, JsonResult::Ok(expected));
+
+    // Empty stream.
+    assert!(stream.next().now_or_never().is_none());
+}
+
+#[actix::test]
+async fn verify_pgp_invalid_challenge() {
+    let (_db, connector, mut api, _) = new_env().await;
+    let mut stream = api.ws_at("/api/account_status").await.unwrap();
+    let signer = PgpSigner::new();
+
+    // Insert judgement request.
+    connector.inject(alice_pgp_request(&signer)).await;
+    let states = connector.inserted_states().await;
+    let mut alice = states[0].clone();
+
+    // Subscribe to endpoint.
+    let resp = subscribe_context(&mut stream, IdentityContext::alice()).await;
+    assert_eq!(
+        resp,
+        JsonResult::Ok(ResponseAccountState::with_no_notifications(alice.clone()))
+    );
+
+    // Signed by another key, rejected before touching the state.
+    let other = PgpSigner::new();
+    let req = VerifyPgpChallenge {
+        entry: alice_pgp(&signer),
+        public_key: signer.public_key(),
+        signature: other.detached("INVALID"),
+        message: Some("INVALID".to_string()),
+    };
+
+    let mut res = api
+        .post("/api/verify_pgp_challenge")
+        .send_json(&req)
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(matches!(
+        res.json::<JsonResult<bool>>().await.unwrap(),
+        JsonResult::Err(_)
+    ));
+    assert!(stream.next().now_or_never().is_none());
+
+    // Valid signature, but invalid challenge.
+    let req = VerifyPgpChallenge {
+        entry: alice_pgp(&signer),
+        public_key: signer.public_key(),
+        signature: signer.detached("INVALID"),
+        message: Some("INVALID".to_string()),
+    };
+
+    let mut res = api
+        .post("/api/verify_pgp_challenge")
+        .send_json(&req)
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.json::<JsonResult<bool>>().await.unwrap(),
+        JsonResult::Ok(false)
+    );
+
+    *alice
+        .get_field_mut(&alice_pgp(&signer))
+        .failed_attempts_mut() = 1;
+
+    let expected = ResponseAccountState {
+        state: alice.clone().into(),
+        notifications: vec![NotificationMessage::FieldVerificationFailed {
+            context: alice.context.clone(),
+            field: alice_pgp(&signer),
+        }],
+    };
+
+    let resp: JsonResult<ResponseAccountState> = stream.next().await.into();
+    assert_eq!(resp, JsonResult::Ok(expected));
+
+    // Empty stream.
+    assert!(stream.next().now_or_never().is_none());
+}
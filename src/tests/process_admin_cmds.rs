@@ -1,9 +1,12 @@
 use super::*;
-use crate::adapters::admin::{process_admin, Command, RawFieldName, Response};
-use crate::api::{JsonResult, ResponseAccountState};
+use crate::adapters::admin::{process_admin, Command, RawFieldName, Response, Target};
+use crate::api::{JsonResult, PgpSigner, ResponseAccountState};
+use crate::connector::{ClientCommand, Judgement, WatcherMessage};
 use crate::primitives::{
-    IdentityContext, IdentityFieldValue, JudgementStateBlanked, NotificationMessage,
+    ChainAddress, ChainName, IdentityContext, IdentityFieldValue, JudgementState,
+    JudgementStateBlanked, NotificationMessage,
 };
+use crate::SecondChallengeConfig;
 use futures::{FutureExt, StreamExt};
 
 #[actix::test]
@@ -16,8 +19,16 @@ async fn command_status() {
     let alice = states[0].clone();
 
     // Request status.
-    let res = process_admin(&db, Command::Status(alice.context.address.clone())).await;
-    assert_eq!(res, Response::Status(JudgementStateBlanked::from(alice)));
+    let res = process_admin(
+        &db,
+        Command::Status(alice.context.address.clone().into()),
+        ADMIN,
+    )
+    .await;
+    assert_eq!(
+        res,
+        Response::Status(Box::new(JudgementStateBlanked::from(alice)))
+    );
 }
 
 #[actix::test]
@@ -43,9 +54,10 @@ async fn command_verify_multiple_challenge_types() {
     let resp = process_admin(
         &db,
         Command::Verify(
-            alice.context.address.clone(),
+            alice.context.address.clone().into(),
             vec![RawFieldName::DisplayName, RawFieldName::Email],
         ),
+        ADMIN,
     )
     .await;
 
@@ -100,7 +112,11 @@ async fn command_verify_multiple_challenge_types() {
     // Manually verify twitter field.
     let resp = process_admin(
         &db,
-        Command::Verify(alice.context.address.clone(), vec![RawFieldName::Twitter]),
+        Command::Verify(
+            alice.context.address.clone().into(),
+            vec![RawFieldName::Twitter],
+        ),
+        ADMIN,
     )
     .await;
 
@@ -160,7 +176,11 @@ async fn command_verify_unsupported_field() {
     // Manually verify.
     let resp = process_admin(
         &db,
-        Command::Verify(alice.context.address.clone(), vec![RawFieldName::Web]),
+        Command::Verify(
+            alice.context.address.clone().into(),
+            vec![RawFieldName::Web],
+        ),
+        ADMIN,
     )
     .await;
 
@@ -213,7 +233,11 @@ async fn command_verify_all() {
     // Manually verify.
     let resp = process_admin(
         &db,
-        Command::Verify(alice.context.address.clone(), vec![RawFieldName::All]),
+        Command::Verify(
+            alice.context.address.clone().into(),
+            vec![RawFieldName::All],
+        ),
+        ADMIN,
     )
     .await;
 
@@ -277,6 +301,200 @@ async fn command_verify_all() {
     assert!(stream.next().now_or_never().is_none());
 }
 
+#[actix::test]
+async fn command_verify_with_second_challenges() {
+    for enabled in [true, false] {
+        let (db, _, _api, _) = new_env().await;
+        let connector = ConnectorMocker::with_second_challenge(
+            db.clone(),
+            SecondChallengeConfig {
+                email: enabled,
+                twitter: enabled,
+                matrix: enabled,
+            },
+        );
+
+        // Insert judgement request.
+        connector.inject(alice_judgement_request()).await;
+        let states = connector.inserted_states().await;
+        let alice = states[0].clone();
+
+        // Verify a single field.
+        let resp = process_admin(
+            &db,
+            Command::Verify(
+                alice.context.address.clone().into(),
+                vec![RawFieldName::Email],
+            ),
+            ADMIN,
+        )
+        .await;
+        assert_eq!(
+            resp,
+            Response::Verified(alice.context.address.clone(), vec![RawFieldName::Email])
+        );
+
+        let state = db
+            .fetch_judgement_state(&alice.context)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(state.get_field(&F::ALICE_EMAIL()).challenge.is_verified());
+
+        // Verify the remaining fields.
+        let resp = process_admin(
+            &db,
+            Command::Verify(
+                alice.context.address.clone().into(),
+                vec![RawFieldName::All],
+            ),
+            ADMIN,
+        )
+        .await;
+        assert_eq!(resp, Response::FullyVerified(alice.context.address.clone()));
+
+        let state = db
+            .fetch_judgement_state(&alice.context)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(state.is_fully_verified);
+        for field in &[F::ALICE_EMAIL(), F::ALICE_TWITTER(), F::ALICE_MATRIX()] {
+            assert!(state.get_field(field).challenge.is_verified());
+        }
+    }
+}
+
+#[actix::test]
+async fn command_verify_pgp_fingerprint() {
+    let (db, connector, _api, _) = new_env().await;
+    let signer = PgpSigner::new();
+    let pgp = IdentityFieldValue::PGPFingerprint(signer.fingerprint());
+
+    // Insert judgement request with a PGP fingerprint.
+    let mut req = JudgementRequest::alice();
+    req.accounts
+        .insert(AccountType::PGPFingerprint, signer.fingerprint());
+    connector
+        .inject(WatcherMessage::new_judgement_request(req))
+        .await;
+    let states = connector.inserted_states().await;
+    let alice = states[0].clone();
+
+    assert!(!alice.get_field(&pgp).challenge.is_verified());
+
+    // Verify the PGP field.
+    let resp = process_admin(
+        &db,
+        Command::Verify(
+            alice.context.address.clone().into(),
+            vec![RawFieldName::PGPFingerprint],
+        ),
+        ADMIN,
+    )
+    .await;
+    assert_eq!(
+        resp,
+        Response::Verified(
+            alice.context.address.clone(),
+            vec![RawFieldName::PGPFingerprint]
+        )
+    );
+
+    let state = db
+        .fetch_judgement_state(&alice.context)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(state.get_field(&pgp).challenge.is_verified());
+    assert!(state.get_field(&pgp).manually_verified);
+    assert!(!state.get_field(&F::ALICE_EMAIL()).challenge.is_verified());
+
+    // Verifying all fields includes the PGP field, too.
+    let (db, connector, _api, _) = new_env().await;
+    let mut req = JudgementRequest::alice();
+    req.accounts
+        .insert(AccountType::PGPFingerprint, signer.fingerprint());
+    connector
+        .inject(WatcherMessage::new_judgement_request(req))
+        .await;
+
+    let resp = process_admin(
+        &db,
+        Command::Verify(
+            alice.context.address.clone().into(),
+            vec![RawFieldName::All],
+        ),
+        ADMIN,
+    )
+    .await;
+    assert_eq!(resp, Response::FullyVerified(alice.context.address.clone()));
+
+    let state = db
+        .fetch_judgement_state(&alice.context)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(state.is_fully_verified);
+    assert!(state.get_field(&pgp).challenge.is_verified());
+}
+
+#[actix::test]
+async fn command_requires_network_for_shared_prefix() {
+    let (db, connector, _api, _) = new_env().await;
+
+    // Polkadot and its People chain share the same prefix.
+    let db = db.with_networks(vec![
+        (ChainName::from("polkadot"), 0),
+        (ChainName::from("people"), 0),
+    ]);
+
+    // Insert judgement request.
+    connector.inject(alice_judgement_request()).await;
+    let states = connector.inserted_states().await;
+    let alice = states[0].clone();
+
+    let resp = process_admin(
+        &db,
+        Command::Verify(
+            alice.context.address.clone().into(),
+            vec![RawFieldName::Email],
+        ),
+        ADMIN,
+    )
+    .await;
+    assert!(matches!(resp, Response::InvalidSyntax(Some(_))));
+
+    // The network is specified explicitly.
+    let target = Target {
+        address: alice.context.address.clone(),
+        network: Some(ChainName::from("polkadot")),
+    };
+    let resp = process_admin(
+        &db,
+        Command::Verify(target, vec![RawFieldName::Email]),
+        ADMIN,
+    )
+    .await;
+    assert_eq!(
+        resp,
+        Response::Verified(alice.context.address.clone(), vec![RawFieldName::Email])
+    );
+
+    // No identity on the other network.
+    let target = Target {
+        address: alice.context.address.clone(),
+        network: Some(ChainName::from("people")),
+    };
+    let resp = process_admin(
+        &db,
+        Command::Verify(target, vec![RawFieldName::Email]),
+        ADMIN,
+    )
+    .await;
+    assert_eq!(resp, Response::IdentityNotFound);
+}
+
 #[actix::test]
 async fn command_verify_missing_field() {
     let (db, connector, mut api, _) = new_env().await;
@@ -305,7 +523,11 @@ async fn command_verify_missing_field() {
     // Manually verify a field that does not exist.
     let resp = process_admin(
         &db,
-        Command::Verify(alice.context.address.clone(), vec![RawFieldName::Email]),
+        Command::Verify(
+            alice.context.address.clone().into(),
+            vec![RawFieldName::Email],
+        ),
+        ADMIN,
     )
     .await;
 
@@ -314,3 +536,87 @@ async fn command_verify_missing_field() {
     // Empty stream.
     assert!(stream.next().now_or_never().is_none());
 }
+
+#[actix::test]
+async fn command_reject() {
+    let (db, mut connector, mut api, _) = new_env().await;
+    let mut stream = api.ws_at("/api/account_status").await.unwrap();
+
+    // Insert judgement request.
+    connector.inject(alice_judgement_request()).await;
+    let states = connector.inserted_states().await;
+    let alice = states[0].clone();
+
+    // Subscribe to endpoint.
+    let resp = subscribe_context(&mut stream, IdentityContext::alice()).await;
+    assert_eq!(
+        resp,
+        JsonResult::Ok(ResponseAccountState::with_no_notifications(alice.clone()))
+    );
+
+    // Reject the identity.
+    let resp = process_admin(
+        &db,
+        Command::Reject(
+            alice.context.address.clone().into(),
+            Judgement::Erroneous,
+            "impersonator".to_string(),
+        ),
+        ADMIN,
+    )
+    .await;
+
+    assert_eq!(
+        resp,
+        Response::Rejected(alice.context.address.clone(), Judgement::Erroneous)
+    );
+
+    // Expected event on stream.
+    let resp: JsonResult<ResponseAccountState> = stream.next().await.into();
+    let resp = match resp {
+        JsonResult::Ok(resp) => resp,
+        JsonResult::Err(err) => panic!("{}", err),
+    };
+
+    assert_eq!(
+        resp.notifications,
+        vec![NotificationMessage::IdentityRejected {
+            context: alice.context.clone(),
+        }]
+    );
+
+    let rejection = resp.state.rejection.unwrap();
+    assert_eq!(rejection.judgement, Judgement::Erroneous);
+    assert_eq!(rejection.reason, "impersonator");
+    assert!(!resp.state.is_fully_verified);
+
+    // The negative judgement is submitted to the Watcher.
+    sleep(Duration::from_secs(3)).await;
+
+    let (outgoing, _) = connector.outgoing();
+    let submitted = outgoing
+        .iter()
+        .filter_map(|cmd| match cmd {
+            ClientCommand::ProvideJudgement(state, _) => Some(state.as_ref()),
+            _ => None,
+        })
+        .collect::<Vec<&JudgementState>>();
+
+    assert!(!submitted.is_empty());
+    assert!(submitted.iter().all(|state| state.context == alice.context
+        && state.rejection.as_ref().unwrap().judgement == Judgement::Erroneous));
+
+    // Unknown identity.
+    let resp = process_admin(
+        &db,
+        Command::Reject(
+            ChainAddress::from("1unknown").into(),
+            Judgement::Erroneous,
+            "impersonator".to_string(),
+        ),
+        ADMIN,
+    )
+    .await;
+
+    assert_eq!(resp, Response::IdentityNotFound);
+}
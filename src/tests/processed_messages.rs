@@ -0,0 +1,141 @@
+use super::*;
+use crate::adapters::twitter::TwitterId;
+use crate::primitives::{
+    ExpectedMessage, ExternalMessage, ExternalMessageType, MessageId, Timestamp,
+};
+use std::collections::HashMap;
+
+fn alice_email(id: u32, values: &ExpectedMessage) -> ExternalMessage {
+    ExternalMessage {
+        origin: ExternalMessageType::Email("alice@email.com".to_string()),
+        id: MessageId::from(id),
+        timestamp: Timestamp::now(),
+        values: values.to_message_parts(),
+    }
+}
+
+async fn failed_attempts(db: &Database, context: &IdentityContext, field: &F) -> usize {
+    db.fetch_judgement_state(context)
+        .await
+        .unwrap()
+        .unwrap()
+        .get_field(field)
+        .failed_attempts
+}
+
+#[actix::test]
+async fn processed_messages_are_skipped() {
+    let (db, connector, _api, _) = new_env().await;
+
+    // Setup email adapter.
+    let email = MessageInjector::with_name("email").with_unique_ids();
+    let listener = AdapterListener::new(db.clone()).await;
+    listener.start_message_adapter(email.clone(), 1).await;
+
+    // Insert judgement request.
+    connector.inject(alice_judgement_request()).await;
+    let states = connector.inserted_states().await;
+    let alice = states[0].clone();
+
+    // Invalid message.
+    let invalid = alice_email(1, &ExpectedMessage::random());
+    email.send(invalid.clone()).await;
+    sleep(Duration::from_secs(3)).await;
+    assert_eq!(
+        failed_attempts(&db, &alice.context, &F::ALICE_EMAIL()).await,
+        1
+    );
+
+    // The same message is not processed again.
+    email.send(invalid.clone()).await;
+    sleep(Duration::from_secs(3)).await;
+    assert_eq!(
+        failed_attempts(&db, &alice.context, &F::ALICE_EMAIL()).await,
+        1
+    );
+
+    // Neither after a restart.
+    let email = MessageInjector::with_name("email").with_unique_ids();
+    let listener = AdapterListener::new(db.clone()).await;
+    listener.start_message_adapter(email.clone(), 1).await;
+
+    email.send(invalid).await;
+    sleep(Duration::from_secs(3)).await;
+    assert_eq!(
+        failed_attempts(&db, &alice.context, &F::ALICE_EMAIL()).await,
+        1
+    );
+
+    // Another adapter might use the same Id.
+    let twitter = MessageInjector::with_name("twitter").with_unique_ids();
+    listener.start_message_adapter(twitter.clone(), 1).await;
+
+    twitter
+        .send(ExternalMessage {
+            origin: ExternalMessageType::Twitter("@alice".to_string()),
+            id: MessageId::from(1u32),
+            timestamp: Timestamp::now(),
+            values: ExpectedMessage::random().to_message_parts(),
+        })
+        .await;
+
+    sleep(Duration::from_secs(3)).await;
+    assert_eq!(
+        failed_attempts(&db, &alice.context, &F::ALICE_TWITTER()).await,
+        1
+    );
+
+    // New valid message.
+    let expected = alice
+        .get_field(&F::ALICE_EMAIL())
+        .expected_message()
+        .clone();
+    email.send(alice_email(2, &expected)).await;
+    sleep(Duration::from_secs(3)).await;
+
+    let state = db
+        .fetch_judgement_state(&alice.context)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(
+        state
+            .get_field(&F::ALICE_EMAIL())
+            .expected_message()
+            .is_verified
+    );
+}
+
+#[actix::test]
+async fn twitter_handles_are_persisted() {
+    let (db, _, _api, _) = new_env().await;
+    let alice = TwitterId::from(1);
+    let bob = TwitterId::from(2);
+
+    // Nothing looked up yet.
+    assert!(db
+        .fetch_twitter_handles(&[&alice])
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(db.fetch_twitter_id("@alice").await.unwrap().is_none());
+
+    let mut handles = HashMap::new();
+    handles.insert(alice.clone(), "@alice".to_string());
+    db.set_twitter_handles(&handles).await.unwrap();
+
+    let found = db.fetch_twitter_handles(&[&alice, &bob]).await.unwrap();
+    assert_eq!(found, handles);
+    assert_eq!(
+        db.fetch_twitter_id("@alice").await.unwrap(),
+        Some(alice.clone())
+    );
+
+    // Renamed handles are updated.
+    handles.insert(alice.clone(), "@alice_new".to_string());
+    db.set_twitter_handles(&handles).await.unwrap();
+
+    let found = db.fetch_twitter_handles(&[&alice]).await.unwrap();
+    assert_eq!(found, handles);
+    assert!(db.fetch_twitter_id("@alice").await.unwrap().is_none());
+}
@@ -0,0 +1,64 @@
+use super::*;
+use crate::adapters::dkim::SenderAuthenticator;
+use crate::adapters::email::authenticate_sender;
+use crate::adapters::web::tests::TxtResolverMocker;
+use crate::primitives::{ExternalMessage, ExternalMessageType, MessageId, Timestamp};
+use crate::SenderAuthPolicy;
+
+#[actix::test]
+async fn flag_unauthenticated_sender() {
+    let (db, connector, _api, _) = new_env().await;
+    let authenticator = SenderAuthenticator::new(TxtResolverMocker::default(), None);
+
+    // Insert judgement request.
+    connector.inject(alice_judgement_request()).await;
+    let states = connector.inserted_states().await;
+    let alice = states[0].clone();
+
+    // Unsigned message.
+    let raw = b"From: alice@email.com\nSubject: Challenge\n\n123456\n";
+    let message = ExternalMessage {
+        origin: ExternalMessageType::Email("alice@email.com".to_string()),
+        id: MessageId::from(0u32),
+        timestamp: Timestamp::now(),
+        values: vec![],
+    };
+
+    // The message is dropped, nothing is recorded.
+    let process = authenticate_sender(&db, SenderAuthPolicy::Reject, &authenticator, &message, raw)
+        .await
+        .unwrap();
+    assert!(!process);
+
+    let state = db
+        .fetch_judgement_state(&alice.context)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(state
+        .get_field(&F::ALICE_EMAIL())
+        .sender_auth_failure
+        .is_none());
+
+    // The message is processed, but the failure is recorded on the field.
+    let process = authenticate_sender(&db, SenderAuthPolicy::Flag, &authenticator, &message, raw)
+        .await
+        .unwrap();
+    assert!(process);
+
+    let state = db
+        .fetch_judgement_state(&alice.context)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(state
+        .get_field(&F::ALICE_EMAIL())
+        .sender_auth_failure
+        .is_some());
+
+    // Other fields are not affected.
+    assert!(state
+        .get_field(&F::ALICE_MATRIX())
+        .sender_auth_failure
+        .is_none());
+}
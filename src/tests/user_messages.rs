@@ -0,0 +1,239 @@
+use super::*;
+use crate::adapters::UserMessage;
+use crate::api::VerifyChallenge;
+use crate::primitives::{
+    ExpectedMessage, ExternalMessage, ExternalMessageType, MessageId, NotificationMessage,
+    Timestamp,
+};
+use crate::SecondChallengeConfig;
+use actix_http::StatusCode;
+
+#[actix::test]
+async fn matrix_challenge_and_follow_ups() {
+    let (db, connector, _api, _) = new_env().await;
+
+    // Setup Matrix adapter.
+    let matrix = MessageInjector::with_name("matrix");
+    let listener = AdapterListener::new(db.clone()).await;
+    listener.start_message_adapter(matrix.clone(), 1).await;
+
+    // Insert judgement request.
+    connector.inject(alice_judgement_request()).await;
+    let states = connector.inserted_states().await;
+    let alice = states[0].clone();
+
+    let challenge = alice
+        .get_field(&F::ALICE_MATRIX())
+        .expected_message()
+        .clone();
+
+    // The challenge is sent to the Matrix account of Alice, exactly once.
+    sleep(Duration::from_secs(3)).await;
+    assert_eq!(
+        matrix.sent_messages().await,
+        vec![(
+            "@alice:matrix.org".to_string(),
+            UserMessage::Challenge(challenge.clone())
+        )]
+    );
+
+    // Invalid reply.
+    matrix
+        .send(ExternalMessage {
+            origin: ExternalMessageType::Matrix("@alice:matrix.org".to_string()),
+            id: MessageId::from(0u32),
+            timestamp: Timestamp::now(),
+            values: ExpectedMessage::random().to_message_parts(),
+        })
+        .await;
+
+    sleep(Duration::from_secs(3)).await;
+    assert_eq!(
+        matrix.sent_messages().await,
+        vec![(
+            "@alice:matrix.org".to_string(),
+            UserMessage::FieldVerificationFailed
+        )]
+    );
+
+    // Valid reply.
+    matrix
+        .send(ExternalMessage {
+            origin: ExternalMessageType::Matrix("@alice:matrix.org".to_string()),
+            id: MessageId::from(0u32),
+            timestamp: Timestamp::now(),
+            values: challenge.to_message_parts(),
+        })
+        .await;
+
+    sleep(Duration::from_secs(3)).await;
+    assert_eq!(
+        matrix.sent_messages().await,
+        vec![("@alice:matrix.org".to_string(), UserMessage::FieldVerified)]
+    );
+
+    // Other accounts are not contacted by the Matrix adapter.
+    connector.inject(bob_judgement_request()).await;
+    sleep(Duration::from_secs(3)).await;
+
+    let sent = matrix.sent_messages().await;
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].0, "@bob:matrix.org");
+}
+
+#[actix::test]
+async fn matrix_challenge_retried_on_failure() {
+    let (db, connector, _api, _) = new_env().await;
+
+    // The first two attempts fail.
+    let matrix = MessageInjector::with_name("matrix");
+    matrix.fail_sends(2).await;
+
+    let listener = AdapterListener::new(db.clone()).await;
+    listener.start_message_adapter(matrix.clone(), 1).await;
+
+    // Insert judgement request.
+    connector.inject(alice_judgement_request()).await;
+    let states = connector.inserted_states().await;
+    let alice = states[0].clone();
+
+    let challenge = alice
+        .get_field(&F::ALICE_MATRIX())
+        .expected_message()
+        .clone();
+
+    // The challenge is delivered on the third attempt, exactly once.
+    sleep(Duration::from_secs(5)).await;
+    assert_eq!(
+        matrix.sent_messages().await,
+        vec![(
+            "@alice:matrix.org".to_string(),
+            UserMessage::Challenge(challenge)
+        )]
+    );
+
+    // Sending is given up after the maximum number of attempts.
+    matrix.fail_sends(usize::MAX).await;
+    connector.inject(bob_judgement_request()).await;
+    sleep(Duration::from_secs(8)).await;
+
+    matrix.fail_sends(0).await;
+    sleep(Duration::from_secs(3)).await;
+    assert!(matrix.sent_messages().await.is_empty());
+}
+
+#[actix::test]
+async fn twitter_second_challenge() {
+    let (db, _, mut api, _) = new_env().await;
+    let mut stream = api.ws_at("/api/account_status").await.unwrap();
+
+    // Enable the second challenge for Twitter.
+    let connector = ConnectorMocker::with_second_challenge(
+        db.clone(),
+        SecondChallengeConfig {
+            email: true,
+            twitter: true,
+            matrix: false,
+        },
+    );
+
+    // Setup Twitter adapter.
+    let twitter = MessageInjector::with_name("twitter");
+    let listener = AdapterListener::new(db.clone()).await;
+    listener.start_message_adapter(twitter.clone(), 1).await;
+
+    // Insert judgement request.
+    connector.inject(alice_judgement_request()).await;
+    let states = connector.inserted_states().await;
+    let mut alice = states[0].clone();
+
+    // Subscribe to endpoint.
+    let resp = subscribe_context(&mut stream, IdentityContext::alice()).await;
+    assert_eq!(
+        resp,
+        JsonResult::Ok(ResponseAccountState::with_no_notifications(alice.clone()))
+    );
+
+    // Send valid message.
+    twitter
+        .send(ExternalMessage {
+            origin: ExternalMessageType::Twitter("@alice".to_string()),
+            id: MessageId::from(0u32),
+            timestamp: Timestamp::now(),
+            values: alice
+                .get_field(&F::ALICE_TWITTER())
+                .expected_message()
+                .to_message_parts(),
+        })
+        .await;
+
+    // Twitter account of Alice is now verified, the second challenge is pending.
+    alice
+        .get_field_mut(&F::ALICE_TWITTER())
+        .expected_message_mut()
+        .set_verified();
+
+    let expected = ResponseAccountState {
+        state: alice.clone().into(),
+        notifications: vec![NotificationMessage::FieldVerified {
+            context: alice.context.clone(),
+            field: F::ALICE_TWITTER(),
+        }],
+    };
+
+    let resp: JsonResult<ResponseAccountState> = stream.next().await.into();
+    assert_eq!(resp, JsonResult::Ok(expected));
+
+    let expected = ResponseAccountState {
+        state: alice.clone().into(),
+        notifications: vec![NotificationMessage::AwaitingSecondChallenge {
+            context: alice.context.clone(),
+            field: F::ALICE_TWITTER(),
+        }],
+    };
+
+    let resp: JsonResult<ResponseAccountState> = stream.next().await.into();
+    assert_eq!(resp, JsonResult::Ok(expected));
+
+    // The second challenge was sent via the Twitter adapter.
+    sleep(Duration::from_secs(2)).await;
+    let second = alice
+        .get_field(&F::ALICE_TWITTER())
+        .expected_second()
+        .clone();
+    assert!(twitter.sent_messages().await.contains(&(
+        "@alice".to_string(),
+        UserMessage::SecondChallenge(second.clone())
+    )));
+
+    // Verify second challenge.
+    let res = api
+        .post("/api/verify_second_challenge")
+        .send_json(&VerifyChallenge {
+            entry: F::ALICE_TWITTER(),
+            challenge: second.value,
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+
+    alice
+        .get_field_mut(&F::ALICE_TWITTER())
+        .expected_second_mut()
+        .set_verified();
+
+    let expected = ResponseAccountState {
+        state: alice.clone().into(),
+        notifications: vec![NotificationMessage::SecondFieldVerified {
+            context: alice.context.clone(),
+            field: F::ALICE_TWITTER(),
+        }],
+    };
+
+    let resp: JsonResult<ResponseAccountState> = stream.next().await.into();
+    assert_eq!(resp, JsonResult::Ok(expected));
+
+    // Empty stream.
+    assert!(stream.next().now_or_never().is_none());
+}
@@ -0,0 +1,209 @@
+use super::*;
+use crate::adapters::web::tests::TxtResolverMocker;
+use crate::adapters::web::{HttpFetcher, WebClient, TXT_RECORD_PREFIX};
+use crate::api::{JsonResult, ResponseAccountState};
+use crate::connector::WatcherMessage;
+use crate::primitives::{ExpectedMessage, IdentityContext, NotificationMessage};
+use actix_web::{web, App, HttpResponse};
+use futures::{FutureExt, StreamExt};
+
+fn alice_web_request() -> WatcherMessage {
+    let mut req = JudgementRequest::alice();
+    req.accounts
+        .insert(AccountType::Web, "https://alice.com".to_string());
+
+    WatcherMessage::new_judgement_request(req)
+}
+
+fn alice_web() -> IdentityFieldValue {
+    IdentityFieldValue::Web("https://alice.com".to_string())
+}
+
+#[actix::test]
+async fn verify_web_txt_record() {
+    let (db, connector, mut api, _) = new_env().await;
+    let mut stream = api.ws_at("/api/account_status").await.unwrap();
+
+    // Setup web adapter, nothing is served via HTTP.
+    let resolver = TxtResolverMocker::default();
+    let stub = actix_test::start(App::new);
+    let client = WebClient::new(
+        db.clone(),
+        resolver.clone(),
+        HttpFetcher::with_base_url(stub.url("")).unwrap(),
+    );
+    let listener = AdapterListener::new(db.clone()).await;
+    listener.start_message_adapter(client, 1).await;
+
+    // Insert judgement request.
+    connector.inject(alice_web_request()).await;
+    let states = connector.inserted_states().await;
+    let mut alice = states[0].clone();
+
+    // Subscribe to endpoint.
+    let resp = subscribe_context(&mut stream, IdentityContext::alice()).await;
+    assert_eq!(
+        resp,
+        JsonResult::Ok(ResponseAccountState::with_no_notifications(alice.clone()))
+    );
+
+    // Publish TXT record.
+    let challenge = alice
+        .get_field(&alice_web())
+        .expected_message()
+        .value
+        .clone();
+    resolver
+        .insert("alice.com", format!("{}{}", TXT_RECORD_PREFIX, challenge))
+        .await;
+
+    // Web field of Alice is now verified.
+    alice
+        .get_field_mut(&alice_web())
+        .expected_message_mut()
+        .set_verified();
+
+    let expected = ResponseAccountState {
+        state: alice.clone().into(),
+        notifications: vec![NotificationMessage::FieldVerified {
+            context: alice.context.clone(),
+            field: alice_web(),
+        }],
+    };
+
+    let resp: JsonResult<ResponseAccountState> = stream.next().await.into();
+    assert_eq!(resp, JsonResult::Ok(expected));
+
+    // Empty stream.
+    assert!(stream.next().now_or_never().is_none());
+}
+
+#[actix::test]
+async fn verify_web_well_known_file() {
+    let (db, connector, mut api, _) = new_env().await;
+    let mut stream = api.ws_at("/api/account_status").await.unwrap();
+
+    // Insert judgement request.
+    connector.inject(alice_web_request()).await;
+    let states = connector.inserted_states().await;
+    let mut alice = states[0].clone();
+
+    // Subscribe to endpoint.
+    let resp = subscribe_context(&mut stream, IdentityContext::alice()).await;
+    assert_eq!(
+        resp,
+        JsonResult::Ok(ResponseAccountState::with_no_notifications(alice.clone()))
+    );
+
+    // Serve the challenge via HTTP and setup web adapter.
+    let challenge = alice
+        .get_field(&alice_web())
+        .expected_message()
+        .value
+        .clone();
+    let stub = actix_test::start(move || {
+        let challenge = challenge.clone();
+        App::new().route(
+            "/.well-known/polkadot-registrar.txt",
+            web::get().to(move || {
+                let challenge = challenge.clone();
+                async move { HttpResponse::Ok().body(challenge) }
+            }),
+        )
+    });
+
+    let client = WebClient::new(
+        db.clone(),
+        TxtResolverMocker::default(),
+        HttpFetcher::with_base_url(stub.url("")).unwrap(),
+    );
+    let listener = AdapterListener::new(db.clone()).await;
+    listener.start_message_adapter(client, 1).await;
+
+    // Web field of Alice is now verified.
+    alice
+        .get_field_mut(&alice_web())
+        .expected_message_mut()
+        .set_verified();
+
+    let expected = ResponseAccountState {
+        state: alice.clone().into(),
+        notifications: vec![NotificationMessage::FieldVerified {
+            context: alice.context.clone(),
+            field: alice_web(),
+        }],
+    };
+
+    let resp: JsonResult<ResponseAccountState> = stream.next().await.into();
+    assert_eq!(resp, JsonResult::Ok(expected));
+
+    // Empty stream.
+    assert!(stream.next().now_or_never().is_none());
+}
+
+#[actix::test]
+async fn verify_web_invalid_challenge() {
+    let (db, connector, mut api, _) = new_env().await;
+    let mut stream = api.ws_at("/api/account_status").await.unwrap();
+
+    let resolver = TxtResolverMocker::default();
+    let stub = actix_test::start(App::new);
+    let client = WebClient::new(
+        db.clone(),
+        resolver.clone(),
+        HttpFetcher::with_base_url(stub.url("")).unwrap(),
+    );
+    let listener = AdapterListener::new(db.clone()).await;
+    listener.start_message_adapter(client, 1).await;
+
+    // Insert judgement request.
+    connector.inject(alice_web_request()).await;
+    let states = connector.inserted_states().await;
+    let mut alice = states[0].clone();
+
+    // Subscribe to endpoint.
+    let resp = subscribe_context(&mut stream, IdentityContext::alice()).await;
+    assert_eq!(
+        resp,
+        JsonResult::Ok(ResponseAccountState::with_no_notifications(alice.clone()))
+    );
+
+    // Unrelated TXT records are ignored.
+    resolver
+        .insert(
+            "alice.com",
+            "v=spf1 include:_spf.alice.com ~all".to_string(),
+        )
+        .await;
+    resolver
+        .insert("alice.com", "google-site-verification=abc123".to_string())
+        .await;
+
+    sleep(Duration::from_secs(3)).await;
+    assert!(stream.next().now_or_never().is_none());
+
+    // Publish invalid challenge.
+    resolver
+        .insert(
+            "alice.com",
+            format!("{}{}", TXT_RECORD_PREFIX, ExpectedMessage::random().value),
+        )
+        .await;
+
+    *alice.get_field_mut(&alice_web()).failed_attempts_mut() = 1;
+
+    let expected = ResponseAccountState {
+        state: alice.clone().into(),
+        notifications: vec![NotificationMessage::FieldVerificationFailed {
+            context: alice.context.clone(),
+            field: alice_web(),
+        }],
+    };
+
+    let resp: JsonResult<ResponseAccountState> = stream.next().await.into();
+    assert_eq!(resp, JsonResult::Ok(expected));
+
+    // The unchanged record is not counted as another failed attempt.
+    sleep(Duration::from_secs(3)).await;
+    assert!(stream.next().now_or_never().is_none());
+}
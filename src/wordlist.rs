@@ -0,0 +1,33 @@
+// Words used for human-friendly challenges, see
+// `crate::primitives::ChallengeFormat::Words`. Short, common and distinct
+// words, so those can be typed easily. Each word encodes 8 bits.
+pub const WORDLIST: [&str; 256] = [
+    "acid", "acorn", "actor", "agent", "alarm", "album", "alley", "amber", "angle", "ankle",
+    "apple", "april", "apron", "arena", "arrow", "atlas", "attic", "audio", "autumn", "badge",
+    "bagel", "baker", "bamboo", "banana", "banjo", "barrel", "basil", "basket", "beach", "beaver",
+    "berry", "bicycle", "bison", "blanket", "blossom", "bonnet", "border", "bottle", "bounce",
+    "bracket", "bread", "breeze", "bridge", "bronze", "bucket", "bundle", "butter", "cabin",
+    "cactus", "camel", "candle", "canoe", "canyon", "carbon", "carpet", "carrot", "castle",
+    "cedar", "cello", "cement", "cherry", "chimney", "cinema", "circus", "citrus", "clover",
+    "cobalt", "cocoa", "comet", "copper", "coral", "cotton", "cradle", "crayon", "cricket",
+    "dairy", "daisy", "dancer", "delta", "denim", "desert", "dinner", "dolphin", "domino",
+    "donkey", "dragon", "drawer", "dune", "eagle", "echo", "eclipse", "elbow", "ember", "engine",
+    "falcon", "feather", "fennel", "ferry", "fiddle", "fig", "finch", "flannel", "flute", "forest",
+    "fossil", "fountain", "fox", "galaxy", "garden", "garlic", "gazelle", "ginger", "glacier",
+    "globe", "goblet", "gopher", "granite", "grape", "gravel", "guitar", "hammer", "harbor",
+    "harvest", "hazel", "helmet", "hermit", "honey", "hornet", "husky", "igloo", "indigo",
+    "island", "ivory", "jacket", "jaguar", "jasmine", "jelly", "jigsaw", "jungle", "kayak",
+    "kernel", "kettle", "kitten", "koala", "ladder", "lagoon", "lantern", "lemon", "lentil",
+    "lizard", "lobster", "locket", "lotus", "lumber", "magnet", "mango", "maple", "marble",
+    "meadow", "melon", "meteor", "mitten", "monkey", "mosaic", "muffin", "mustard", "napkin",
+    "nectar", "needle", "nickel", "noodle", "nutmeg", "oasis", "ocean", "olive", "onion", "orbit",
+    "orchid", "otter", "oyster", "paddle", "panda", "papaya", "parrot", "peanut", "pebble",
+    "pelican", "pepper", "pickle", "pigeon", "pillow", "pirate", "planet", "plum", "pocket",
+    "potato", "puzzle", "quartz", "quiver", "rabbit", "radar", "radish", "raven", "ribbon",
+    "river", "robot", "rocket", "saddle", "salmon", "sandal", "satin", "scarf", "shadow", "shovel",
+    "silver", "sketch", "sparrow", "spider", "spinach", "sponge", "squash", "statue", "summit",
+    "sunset", "tablet", "tandem", "teapot", "temple", "thistle", "thunder", "tiger", "timber",
+    "tomato", "topaz", "tractor", "tulip", "tunnel", "turkey", "turtle", "valley", "velvet",
+    "violin", "volcano", "waffle", "wagon", "walnut", "walrus", "willow", "window", "winter",
+    "wizard", "wombat", "yogurt", "zebra", "zenith", "zipper",
+];
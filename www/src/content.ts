@@ -98,11 +98,11 @@ export class ContentManager {
                     if (field.challenge.content.second && !field.challenge.content.second!.is_verified) {
                         validity = BadgeVerifiedHalf;
 
-                        this.setEmailSecondChallengeContent(field.value.value);
+                        this.setEmailSecondChallengeContent(field.value.type, field.value.value);
                     } else {
                         validity = BadgeVerified;
 
-                        if (field.value.type == "email") {
+                        if (field.challenge.content.second) {
                             this.wipeEmailSecondChallengeContent();
                         }
                     }
@@ -118,6 +118,10 @@ export class ContentManager {
                     to = "@w3f_registrar";
                 } else if (field.value.type == "matrix") {
                     to = "@registrar-v2:web3.foundation";
+                } else if (field.value.type == "web") {
+                    to = "DNS TXT record (polkadot-registrar=<challenge>) or /.well-known/polkadot-registrar.txt";
+                } else if (field.value.type == "p_g_p_fingerprint") {
+                    to = "Signed message via /api/verify_pgp_challenge";
                 }
 
                 table += `
@@ -234,11 +238,20 @@ export class ContentManager {
     wipeVerificationOverviewContent() {
         this.div_verification_overview.innerHTML = "";
     }
-    setEmailSecondChallengeContent(address: string) {
+    setEmailSecondChallengeContent(ty: string, address: string) {
+        let sender = "the registrar";
+        if (ty == "email") {
+            sender = "<em>registrar@web3.foundation</em>";
+        } else if (ty == "twitter") {
+            sender = "<em>@w3f_registrar</em>";
+        } else if (ty == "matrix") {
+            sender = "<em>@registrar-v2:web3.foundation</em>";
+        }
+
         this.div_email_second_challenge.innerHTML = `
             <div class="col-10">
                 <h2>⚠️️ Additional Challenge</h2>
-                <p>A message was sent from <em>registrar@web3.foundation</em> to <strong>${address}</strong> containing an additional challenge
+                <p>A message was sent from ${sender} to <strong>${address}</strong> containing an additional challenge
                     (make sure to check the spam folder). Please insert that challenge into the following field:
                 </p>
                 <div class="input-group">
@@ -272,7 +285,7 @@ export class ContentManager {
 
                 let body = JSON.stringify({
                     entry: {
-                        type: "email",
+                        type: ty,
                         value: address,
                     },
                     challenge: second_challenge.value,